@@ -1,3 +1,5 @@
+use crate::time::TimeParseError;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Errors that can occur when parsing VAST XML
@@ -21,8 +23,250 @@ pub enum VastError {
     #[error("Unsupported VAST feature: {0}")]
     UnsupportedFeature(String),
     
+    #[error("Unexpected HTTP status: {0}")]
+    HttpStatus(u16),
+    
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+    
+    #[error("Invalid time in {location} at line {line}, column {column}: {source}")]
+    InvalidTime {
+        /// The element or attribute holding the value, e.g. "Linear/Duration"
+        location: String,
+        line: usize,
+        column: usize,
+        source: TimeParseError,
+    },
+    
+    #[error("Invalid value in {location} at line {line}, column {column}: {message}")]
+    InvalidValue {
+        /// The element or attribute holding the value, e.g. "InLine/Expires"
+        location: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    
     #[error("Unknown error: {0}")]
     Other(String),
 }
 
-pub type Result<T> = std::result::Result<T, VastError>; 
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, VastError>; 
+impl VastError {
+    /// The IAB error code that best describes this error when reported to an Error URL
+    pub fn error_code(&self) -> VastErrorCode {
+        match self {
+            VastError::XmlParseError(_) => VastErrorCode::XmlParsingError,
+            VastError::InvalidVersion(_) => VastErrorCode::VersionNotSupported,
+            VastError::MissingField(_)
+            | VastError::InvalidTime { .. }
+            | VastError::InvalidValue { .. } => VastErrorCode::SchemaValidationError,
+            VastError::IoError(_)
+            | VastError::UrlError(_)
+            | VastError::HttpStatus(_)
+            | VastError::Timeout(_) => VastErrorCode::WrapperTimeout,
+            VastError::LimitExceeded(_) => VastErrorCode::GeneralWrapperError,
+            VastError::UnsupportedFeature(_) | VastError::Other(_) => VastErrorCode::UndefinedError,
+        }
+    }
+}
+
+/// IAB VAST error codes, reported to Error URLs through the `[ERRORCODE]` macro
+/// 
+/// Serialized as the numeric code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u16", try_from = "u16")]
+pub enum VastErrorCode {
+    /// 100: XML parsing error
+    XmlParsingError,
+    /// 101: VAST schema validation error
+    SchemaValidationError,
+    /// 102: VAST version of response not supported
+    VersionNotSupported,
+    /// 200: Trafficking error; the ad type doesn't match what the player expects
+    TraffickingError,
+    /// 201: The player expected a different linearity
+    UnexpectedLinearity,
+    /// 202: The player expected a different duration
+    UnexpectedDuration,
+    /// 203: The player expected a different size
+    UnexpectedSize,
+    /// 204: Ad category was required but not provided
+    AdCategoryRequired,
+    /// 205: InLine category violates the wrapper's BlockedAdCategories
+    AdCategoryBlocked,
+    /// 206: Ad break shortened; the ad was not served
+    AdBreakShortened,
+    /// 300: General wrapper error
+    GeneralWrapperError,
+    /// 301: Timeout or unavailable VAST URI in a wrapper
+    WrapperTimeout,
+    /// 302: Wrapper limit reached
+    WrapperLimitReached,
+    /// 303: No VAST response after one or more wrappers
+    NoAdsAfterWrapper,
+    /// 304: InLine response returned an ad unit that failed to load in time
+    InlineTimeout,
+    /// 400: General linear error
+    GeneralLinearError,
+    /// 401: File not found; unable to find the linear media file
+    FileNotFound,
+    /// 402: Timeout of the media file
+    MediaFileTimeout,
+    /// 403: Couldn't find a media file supported by the player
+    SupportedMediaNotFound,
+    /// 405: Problem displaying the media file
+    MediaFileDisplayError,
+    /// 406: Mezzanine required but not provided
+    MezzanineRequired,
+    /// 407: Mezzanine is being downloaded for the first time
+    MezzanineDownloadInProgress,
+    /// 408: Conditional ad rejected
+    ConditionalAdRejected,
+    /// 409: Interactive unit in the InteractiveCreativeFile node was not executed
+    InteractiveUnitNotExecuted,
+    /// 410: Verification unit in the Verification node was not executed
+    VerificationUnitNotExecuted,
+    /// 411: Mezzanine provided but not of the expected format
+    MezzanineInvalid,
+    /// 500: General non-linear ad error
+    GeneralNonLinearError,
+    /// 501: Non-linear ad dimensions don't fit the display area
+    NonLinearDimensionsError,
+    /// 502: Unable to fetch the non-linear resource
+    NonLinearResourceFetchError,
+    /// 503: Couldn't find a supported non-linear resource
+    NonLinearResourceNotFound,
+    /// 600: General companion ad error
+    GeneralCompanionError,
+    /// 601: Companion dimensions don't fit the display area
+    CompanionDimensionsError,
+    /// 602: Unable to display a required companion
+    RequiredCompanionNotDisplayed,
+    /// 603: Unable to fetch the companion resource
+    CompanionResourceFetchError,
+    /// 604: Couldn't find a supported companion resource
+    CompanionResourceNotFound,
+    /// 900: Undefined error
+    UndefinedError,
+    /// 901: General VPAID error
+    VpaidError,
+    /// 902: General InteractiveCreativeFile error
+    InteractiveCreativeFileError,
+}
+
+impl VastErrorCode {
+    /// All known error codes, in numeric order
+    pub const ALL: [VastErrorCode; 38] = [
+        VastErrorCode::XmlParsingError,
+        VastErrorCode::SchemaValidationError,
+        VastErrorCode::VersionNotSupported,
+        VastErrorCode::TraffickingError,
+        VastErrorCode::UnexpectedLinearity,
+        VastErrorCode::UnexpectedDuration,
+        VastErrorCode::UnexpectedSize,
+        VastErrorCode::AdCategoryRequired,
+        VastErrorCode::AdCategoryBlocked,
+        VastErrorCode::AdBreakShortened,
+        VastErrorCode::GeneralWrapperError,
+        VastErrorCode::WrapperTimeout,
+        VastErrorCode::WrapperLimitReached,
+        VastErrorCode::NoAdsAfterWrapper,
+        VastErrorCode::InlineTimeout,
+        VastErrorCode::GeneralLinearError,
+        VastErrorCode::FileNotFound,
+        VastErrorCode::MediaFileTimeout,
+        VastErrorCode::SupportedMediaNotFound,
+        VastErrorCode::MediaFileDisplayError,
+        VastErrorCode::MezzanineRequired,
+        VastErrorCode::MezzanineDownloadInProgress,
+        VastErrorCode::ConditionalAdRejected,
+        VastErrorCode::InteractiveUnitNotExecuted,
+        VastErrorCode::VerificationUnitNotExecuted,
+        VastErrorCode::MezzanineInvalid,
+        VastErrorCode::GeneralNonLinearError,
+        VastErrorCode::NonLinearDimensionsError,
+        VastErrorCode::NonLinearResourceFetchError,
+        VastErrorCode::NonLinearResourceNotFound,
+        VastErrorCode::GeneralCompanionError,
+        VastErrorCode::CompanionDimensionsError,
+        VastErrorCode::RequiredCompanionNotDisplayed,
+        VastErrorCode::CompanionResourceFetchError,
+        VastErrorCode::CompanionResourceNotFound,
+        VastErrorCode::UndefinedError,
+        VastErrorCode::VpaidError,
+        VastErrorCode::InteractiveCreativeFileError,
+    ];
+    
+    /// The numeric IAB error code
+    pub fn code(&self) -> u16 {
+        match self {
+            VastErrorCode::XmlParsingError => 100,
+            VastErrorCode::SchemaValidationError => 101,
+            VastErrorCode::VersionNotSupported => 102,
+            VastErrorCode::TraffickingError => 200,
+            VastErrorCode::UnexpectedLinearity => 201,
+            VastErrorCode::UnexpectedDuration => 202,
+            VastErrorCode::UnexpectedSize => 203,
+            VastErrorCode::AdCategoryRequired => 204,
+            VastErrorCode::AdCategoryBlocked => 205,
+            VastErrorCode::AdBreakShortened => 206,
+            VastErrorCode::GeneralWrapperError => 300,
+            VastErrorCode::WrapperTimeout => 301,
+            VastErrorCode::WrapperLimitReached => 302,
+            VastErrorCode::NoAdsAfterWrapper => 303,
+            VastErrorCode::InlineTimeout => 304,
+            VastErrorCode::GeneralLinearError => 400,
+            VastErrorCode::FileNotFound => 401,
+            VastErrorCode::MediaFileTimeout => 402,
+            VastErrorCode::SupportedMediaNotFound => 403,
+            VastErrorCode::MediaFileDisplayError => 405,
+            VastErrorCode::MezzanineRequired => 406,
+            VastErrorCode::MezzanineDownloadInProgress => 407,
+            VastErrorCode::ConditionalAdRejected => 408,
+            VastErrorCode::InteractiveUnitNotExecuted => 409,
+            VastErrorCode::VerificationUnitNotExecuted => 410,
+            VastErrorCode::MezzanineInvalid => 411,
+            VastErrorCode::GeneralNonLinearError => 500,
+            VastErrorCode::NonLinearDimensionsError => 501,
+            VastErrorCode::NonLinearResourceFetchError => 502,
+            VastErrorCode::NonLinearResourceNotFound => 503,
+            VastErrorCode::GeneralCompanionError => 600,
+            VastErrorCode::CompanionDimensionsError => 601,
+            VastErrorCode::RequiredCompanionNotDisplayed => 602,
+            VastErrorCode::CompanionResourceFetchError => 603,
+            VastErrorCode::CompanionResourceNotFound => 604,
+            VastErrorCode::UndefinedError => 900,
+            VastErrorCode::VpaidError => 901,
+            VastErrorCode::InteractiveCreativeFileError => 902,
+        }
+    }
+    
+    /// Look up an error code by its numeric value
+    pub fn from_code(code: u16) -> Option<VastErrorCode> {
+        VastErrorCode::ALL.iter().copied().find(|error_code| error_code.code() == code)
+    }
+}
+
+impl std::fmt::Display for VastErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl From<VastErrorCode> for u16 {
+    fn from(error_code: VastErrorCode) -> Self {
+        error_code.code()
+    }
+}
+
+impl TryFrom<u16> for VastErrorCode {
+    type Error = String;
+    
+    fn try_from(code: u16) -> std::result::Result<Self, Self::Error> {
+        VastErrorCode::from_code(code).ok_or_else(|| format!("Unknown VAST error code: {}", code))
+    }
+}
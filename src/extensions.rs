@@ -0,0 +1,507 @@
+use crate::error::{Result, VastError};
+use crate::models::{Creative, Extension, InLine, Verification, Wrapper};
+use crate::parser;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::str::from_utf8;
+
+/// A typed view of one kind of `<Extension>` or `<CreativeExtension>`
+/// 
+/// Implemented for the common extensions below; implement it for your own types to
+/// decode partner-specific extensions, either directly with `Extension::decode` or
+/// through an `ExtensionRegistry`.
+/// 
+/// ```
+/// use vast_parser::error::Result;
+/// use vast_parser::extensions::VastExtension;
+/// use vast_parser::models::Extension;
+/// 
+/// struct DealId(String);
+/// 
+/// impl VastExtension for DealId {
+///     fn matches(extension: &Extension) -> bool {
+///         extension.r#type.as_deref() == Some("deal")
+///     }
+/// 
+///     fn decode(extension: &Extension) -> Result<Self> {
+///         Ok(DealId(extension.child_text("DealId")?.unwrap_or_default()))
+///     }
+/// }
+/// ```
+pub trait VastExtension: Sized {
+    /// Whether the extension is of this kind, usually judged by its type attribute
+    fn matches(extension: &Extension) -> bool;
+    
+    /// Decode an extension of this kind
+    fn decode(extension: &Extension) -> Result<Self>;
+}
+
+/// The extension decoders to try on extensions of unknown kind
+/// 
+/// Starts out with the decoders this crate ships (AdVerifications, waterfall, geo and
+/// Count); register your own types to have them decoded too. When several decoders
+/// match an extension, the one registered last wins.
+/// 
+/// ```
+/// use vast_parser::error::Result;
+/// use vast_parser::extensions::{ExtensionRegistry, GeoExtension, VastExtension};
+/// use vast_parser::models::Extension;
+/// 
+/// struct DealId(String);
+/// 
+/// impl VastExtension for DealId {
+///     fn matches(extension: &Extension) -> bool {
+///         extension.r#type.as_deref() == Some("deal")
+///     }
+/// 
+///     fn decode(extension: &Extension) -> Result<Self> {
+///         Ok(DealId(extension.text()?))
+///     }
+/// }
+/// 
+/// let registry = ExtensionRegistry::new().with_extension::<DealId>();
+/// let extensions = [
+///     Extension { r#type: Some("deal".to_string()), attributes: Vec::new(), content: "D-42".to_string() },
+///     Extension { r#type: Some("geo".to_string()), attributes: Vec::new(), content: "<Country>US</Country>".to_string() },
+/// ];
+/// 
+/// let decoded = registry.decode_all(&extensions).unwrap();
+/// assert_eq!(decoded[0].downcast_ref::<DealId>().unwrap().0, "D-42");
+/// assert_eq!(decoded[1].downcast_ref::<GeoExtension>().unwrap().country.as_deref(), Some("US"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExtensionRegistry {
+    decoders: Vec<Decoder>,
+}
+
+/// A registered extension type
+#[derive(Debug, Clone, Copy)]
+struct Decoder {
+    type_id: TypeId,
+    type_name: &'static str,
+    matches: fn(&Extension) -> bool,
+    decode: fn(&Extension) -> Result<DecodedExtension>,
+}
+
+impl Default for ExtensionRegistry {
+    fn default() -> Self {
+        ExtensionRegistry::empty()
+            .with_extension::<AdVerificationsExtension>()
+            .with_extension::<WaterfallExtension>()
+            .with_extension::<GeoExtension>()
+            .with_extension::<CountExtension>()
+    }
+}
+
+impl ExtensionRegistry {
+    /// A registry with the decoders this crate ships
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// A registry without any decoders
+    pub fn empty() -> Self {
+        ExtensionRegistry { decoders: Vec::new() }
+    }
+    
+    /// Add the decoder of `T`
+    pub fn with_extension<T: VastExtension + Send + Sync + 'static>(mut self) -> Self {
+        self.register::<T>();
+        self
+    }
+    
+    /// Add the decoder of `T`, replacing it if it was already registered
+    pub fn register<T: VastExtension + Send + Sync + 'static>(&mut self) {
+        self.decoders.retain(|decoder| decoder.type_id != TypeId::of::<T>());
+        self.decoders.push(Decoder {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            matches: T::matches,
+            decode: |extension| T::decode(extension).map(DecodedExtension::new),
+        });
+    }
+    
+    /// Whether `T` has been registered
+    pub fn is_registered<T: 'static>(&self) -> bool {
+        self.decoders.iter().any(|decoder| decoder.type_id == TypeId::of::<T>())
+    }
+    
+    /// Decode an extension with the last registered decoder that matches it, or None if none does
+    pub fn decode(&self, extension: &Extension) -> Option<Result<DecodedExtension>> {
+        self.decoders.iter().rev()
+            .find(|decoder| (decoder.matches)(extension))
+            .map(|decoder| (decoder.decode)(extension))
+    }
+    
+    /// Decode every extension a registered decoder matches, skipping the others
+    pub fn decode_all(&self, extensions: &[Extension]) -> Result<Vec<DecodedExtension>> {
+        extensions.iter().filter_map(|extension| self.decode(extension)).collect()
+    }
+    
+    /// The names of the registered types, in registration order
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.decoders.iter().map(|decoder| decoder.type_name)
+    }
+}
+
+/// An extension decoded by an `ExtensionRegistry`, as whichever registered type matched it
+pub struct DecodedExtension {
+    type_name: &'static str,
+    value: Box<dyn Any + Send + Sync>,
+}
+
+impl DecodedExtension {
+    fn new<T: Send + Sync + 'static>(value: T) -> Self {
+        DecodedExtension {
+            type_name: std::any::type_name::<T>(),
+            value: Box::new(value),
+        }
+    }
+    
+    /// The name of the type the extension was decoded as
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+    
+    /// Whether the extension was decoded as `T`
+    pub fn is<T: 'static>(&self) -> bool {
+        self.value.is::<T>()
+    }
+    
+    /// The decoded extension, if it was decoded as `T`
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+    
+    /// Take the decoded extension, or get it back if it wasn't decoded as `T`
+    pub fn downcast<T: 'static>(self) -> std::result::Result<T, Self> {
+        let type_name = self.type_name;
+        self.value.downcast()
+            .map(|value| *value)
+            .map_err(|value| DecodedExtension { type_name, value })
+    }
+}
+
+impl fmt::Debug for DecodedExtension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DecodedExtension").field(&self.type_name).finish()
+    }
+}
+
+impl Extension {
+    /// Decode the extension as `T`, or None if it is a different kind of extension
+    pub fn decode<T: VastExtension>(&self) -> Option<Result<T>> {
+        T::matches(self).then(|| T::decode(self))
+    }
+    
+    /// The value of an attribute other than type
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+    
+    /// The unescaped text of the extension, ignoring any markup
+    pub fn text(&self) -> Result<String> {
+        let mut reader = Reader::from_str(&self.content);
+        reader.trim_text(true);
+        read_text(&mut reader, None)
+    }
+    
+    /// The unescaped text of the first element with the given name, at any depth
+    pub fn child_text(&self, name: &str) -> Result<Option<String>> {
+        let mut reader = Reader::from_str(&self.content);
+        reader.trim_text(true);
+        
+        loop {
+            match reader.read_event()? {
+                Event::Start(ref e) if e.name().as_ref() == name.as_bytes() => {
+                    return read_text(&mut reader, Some(name)).map(Some);
+                },
+                Event::Empty(ref e) if e.name().as_ref() == name.as_bytes() => return Ok(Some(String::new())),
+                Event::Eof => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Collect text until the end of the named element, or of the fragment
+fn read_text(reader: &mut Reader<&[u8]>, end: Option<&str>) -> Result<String> {
+    let mut text = String::new();
+    let mut depth = 0;
+    
+    loop {
+        match reader.read_event()? {
+            Event::Text(e) => text.push_str(&e.unescape()?),
+            Event::CData(e) => {
+                if let Ok(value) = from_utf8(&e) {
+                    text.push_str(value);
+                }
+            },
+            Event::Start(_) => depth += 1,
+            Event::End(ref e) if depth == 0 && end.is_some_and(|end| e.name().as_ref() == end.as_bytes()) => break,
+            Event::End(_) => depth -= 1,
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    
+    Ok(text)
+}
+
+/// Find and decode the first extension of kind `T`
+pub fn find_extension<T: VastExtension>(extensions: &[Extension]) -> Option<Result<T>> {
+    extensions.iter().find_map(Extension::decode)
+}
+
+/// The verifications in `<AdVerifications>`, followed by those in VAST 3 `<Extension type="AdVerifications">`s
+fn merge_verifications(ad_verifications: &[Verification], extensions: &[Extension]) -> Result<Vec<Verification>> {
+    let mut verifications = ad_verifications.to_vec();
+    for extension in extensions {
+        if let Some(extension) = extension.decode::<AdVerificationsExtension>() {
+            verifications.extend(extension?.verifications);
+        }
+    }
+    Ok(verifications)
+}
+
+impl InLine {
+    /// Decode the first `<Extension>` of kind `T`
+    pub fn extension<T: VastExtension>(&self) -> Option<Result<T>> {
+        find_extension(&self.extensions)
+    }
+    
+    /// All Open Measurement verifications of the ad, whether in `<AdVerifications>` or,
+    /// as VAST 3 documents carry them, in an `<Extension type="AdVerifications">`
+    /// 
+    /// ```
+    /// use vast_parser::parser;
+    /// 
+    /// let vast = parser::parse_vast(r#"<VAST version="3.0"><Ad><InLine><Extensions>
+    ///     <Extension type="AdVerifications"><AdVerifications>
+    ///         <Verification vendor="acme"><JavaScriptResource>https://example.com/v.js</JavaScriptResource></Verification>
+    ///     </AdVerifications></Extension>
+    /// </Extensions></InLine></Ad></VAST>"#).unwrap();
+    /// let inline = vast.ads[0].inline.as_ref().unwrap();
+    /// 
+    /// // The extension is kept as it was, and its verifications are still found
+    /// assert!(inline.ad_verifications.is_empty());
+    /// assert_eq!(inline.extensions.len(), 1);
+    /// assert_eq!(inline.verifications().unwrap()[0].vendor.as_deref(), Some("acme"));
+    /// ```
+    pub fn verifications(&self) -> Result<Vec<Verification>> {
+        merge_verifications(&self.ad_verifications, &self.extensions)
+    }
+}
+
+impl Wrapper {
+    /// Decode the first `<Extension>` of kind `T`
+    pub fn extension<T: VastExtension>(&self) -> Option<Result<T>> {
+        find_extension(&self.extensions)
+    }
+    
+    /// All Open Measurement verifications of the wrapper, like `InLine::verifications`
+    pub fn verifications(&self) -> Result<Vec<Verification>> {
+        merge_verifications(&self.ad_verifications, &self.extensions)
+    }
+}
+
+impl Creative {
+    /// Decode the first `<CreativeExtension>` of kind `T`
+    pub fn creative_extension<T: VastExtension>(&self) -> Option<Result<T>> {
+        find_extension(&self.creative_extensions)
+    }
+}
+
+/// Check an extension's type attribute
+fn has_type(extension: &Extension, r#type: &str) -> bool {
+    extension.r#type.as_deref() == Some(r#type)
+}
+
+/// Parse a numeric extension value
+fn parse_number(value: &str, what: &str) -> Result<u32> {
+    value.trim().parse()
+        .map_err(|_| VastError::Other(format!("Invalid {} in extension: {}", what, value)))
+}
+
+/// VAST 3 Open Measurement verification, `<Extension type="AdVerifications">`
+/// 
+/// The parser keeps these as extensions; `InLine::verifications` and `Wrapper::verifications`
+/// merge them with the ones in `<AdVerifications>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdVerificationsExtension {
+    /// The verification vendors
+    pub verifications: Vec<Verification>,
+}
+
+impl VastExtension for AdVerificationsExtension {
+    fn matches(extension: &Extension) -> bool {
+        has_type(extension, "AdVerifications")
+    }
+    
+    fn decode(extension: &Extension) -> Result<Self> {
+        Ok(AdVerificationsExtension {
+            verifications: parser::parse_ad_verifications_extension_content(&extension.content)?,
+        })
+    }
+}
+
+/// Google Ad Manager waterfall position, `<Extension type="waterfall" fallback_index="1"/>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaterfallExtension {
+    /// Position of the ad in the fallback waterfall, starting at 0
+    pub fallback_index: u32,
+}
+
+impl VastExtension for WaterfallExtension {
+    fn matches(extension: &Extension) -> bool {
+        has_type(extension, "waterfall")
+    }
+    
+    fn decode(extension: &Extension) -> Result<Self> {
+        let fallback_index = extension.attribute("fallback_index")
+            .ok_or_else(|| VastError::MissingField("waterfall fallback_index".to_string()))?;
+        
+        Ok(WaterfallExtension {
+            fallback_index: parse_number(fallback_index, "fallback_index")?,
+        })
+    }
+}
+
+/// Google Ad Manager viewer location and connection, `<Extension type="geo">`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoExtension {
+    /// The viewer's country code
+    pub country: Option<String>,
+    
+    /// The connection bandwidth class
+    pub bandwidth: Option<u32>,
+    
+    /// The connection bandwidth in kbps
+    pub bandwidth_kbps: Option<u32>,
+}
+
+impl VastExtension for GeoExtension {
+    fn matches(extension: &Extension) -> bool {
+        has_type(extension, "geo")
+    }
+    
+    fn decode(extension: &Extension) -> Result<Self> {
+        let number = |name: &str| -> Result<Option<u32>> {
+            extension.child_text(name)?
+                .map(|value| parse_number(&value, name))
+                .transpose()
+        };
+        
+        Ok(GeoExtension {
+            country: extension.child_text("Country")?,
+            bandwidth: number("Bandwidth")?,
+            bandwidth_kbps: number("BandwidthKbps")?,
+        })
+    }
+}
+
+/// Google Ad Manager ad count, `<Extension type="Count">`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CountExtension {
+    /// The number of ads counted
+    pub count: u32,
+}
+
+impl VastExtension for CountExtension {
+    fn matches(extension: &Extension) -> bool {
+        has_type(extension, "Count")
+    }
+    
+    fn decode(extension: &Extension) -> Result<Self> {
+        Ok(CountExtension {
+            count: parse_number(&extension.text()?, "Count")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn extension(r#type: &str, attributes: &[(&str, &str)], content: &str) -> Extension {
+        Extension {
+            r#type: Some(r#type.to_string()),
+            attributes: attributes.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            content: content.to_string(),
+        }
+    }
+    
+    /// A partner extension that claims Google's geo type
+    struct PartnerGeo(String);
+    
+    impl VastExtension for PartnerGeo {
+        fn matches(extension: &Extension) -> bool {
+            has_type(extension, "geo")
+        }
+        
+        fn decode(extension: &Extension) -> Result<Self> {
+            Ok(PartnerGeo(extension.child_text("Country")?.unwrap_or_default()))
+        }
+    }
+    
+    #[test]
+    fn registry_decodes_the_shipped_extensions() {
+        let extensions = [
+            extension("waterfall", &[("fallback_index", "2")], ""),
+            extension("geo", &[], "<Country>DE</Country><Bandwidth>4</Bandwidth>"),
+            extension("Count", &[], "<![CDATA[3]]>"),
+            extension("AdVerifications", &[], r#"<AdVerifications><Verification vendor="acme"/></AdVerifications>"#),
+            extension("unknown", &[], "ignored"),
+        ];
+        
+        let decoded = ExtensionRegistry::new().decode_all(&extensions).unwrap();
+        
+        assert_eq!(decoded.len(), 4);
+        assert_eq!(decoded[0].downcast_ref::<WaterfallExtension>(), Some(&WaterfallExtension { fallback_index: 2 }));
+        assert_eq!(decoded[1].downcast_ref::<GeoExtension>(), Some(&GeoExtension {
+            country: Some("DE".to_string()),
+            bandwidth: Some(4),
+            bandwidth_kbps: None,
+        }));
+        assert_eq!(decoded[2].downcast_ref::<CountExtension>(), Some(&CountExtension { count: 3 }));
+        assert!(decoded[3].is::<AdVerificationsExtension>());
+    }
+    
+    #[test]
+    fn later_registrations_take_precedence() {
+        let geo = extension("geo", &[], "<Country>FR</Country>");
+        
+        let registry = ExtensionRegistry::new().with_extension::<PartnerGeo>();
+        let decoded = registry.decode(&geo).unwrap().unwrap();
+        assert_eq!(decoded.downcast_ref::<PartnerGeo>().unwrap().0, "FR");
+        
+        // Registering a type again moves it to the end
+        let registry = registry.with_extension::<GeoExtension>();
+        assert!(registry.decode(&geo).unwrap().unwrap().is::<GeoExtension>());
+        assert_eq!(registry.type_names().filter(|name| name.ends_with("GeoExtension")).count(), 1);
+    }
+    
+    #[test]
+    fn empty_registry_decodes_nothing() {
+        let registry = ExtensionRegistry::empty();
+        
+        assert!(!registry.is_registered::<GeoExtension>());
+        assert!(registry.decode(&extension("geo", &[], "")).is_none());
+    }
+    
+    #[test]
+    fn failed_decodes_and_downcasts_are_reported() {
+        let registry = ExtensionRegistry::new();
+        
+        assert!(registry.decode_all(&[extension("Count", &[], "many")]).is_err());
+        
+        let decoded = registry.decode(&extension("Count", &[], "1")).unwrap().unwrap();
+        let decoded = decoded.downcast::<GeoExtension>().unwrap_err();
+        assert_eq!(decoded.downcast::<CountExtension>().unwrap(), CountExtension { count: 1 });
+    }
+}
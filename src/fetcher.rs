@@ -0,0 +1,301 @@
+use crate::error::{Result, VastError};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Duration;
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+
+/// Future returned by `AsyncVastFetcher::fetch_async`
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<FetchResponse>> + Send + 'a>>;
+
+/// Time allowed for a single fetch unless the request says otherwise
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A request for the VAST document behind a VASTAdTagURI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchRequest<'a> {
+    /// The VASTAdTagURI or file path to fetch
+    pub uri: &'a str,
+    
+    /// How long the fetch may take
+    pub timeout: Duration,
+    
+    /// The largest response body to accept, in bytes
+    pub max_bytes: Option<usize>,
+}
+
+impl<'a> FetchRequest<'a> {
+    /// A request for a URI with the default timeout and no size limit
+    pub fn new(uri: &'a str) -> Self {
+        FetchRequest {
+            uri,
+            timeout: DEFAULT_FETCH_TIMEOUT,
+            max_bytes: None,
+        }
+    }
+    
+    /// Set how long the fetch may take
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Set the largest response body to accept, in bytes
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+    
+    /// Fail with `VastError::LimitExceeded` if a response of `len` bytes is too large
+    pub fn check_size(&self, len: usize) -> Result<()> {
+        match self.max_bytes {
+            Some(max_bytes) if len > max_bytes => Err(VastError::LimitExceeded(
+                format!("Response from {} exceeds {} bytes", self.uri, max_bytes)
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The VAST document returned for a fetch request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchResponse {
+    /// The VAST XML
+    pub body: String,
+    
+    /// The HTTP status of the response, if it came over HTTP
+    pub http_status: Option<u16>,
+}
+
+impl FetchResponse {
+    /// A response with the given body and no HTTP status
+    pub fn new(body: impl Into<String>) -> Self {
+        FetchResponse {
+            body: body.into(),
+            http_status: None,
+        }
+    }
+    
+    /// Set the HTTP status of the response
+    pub fn with_http_status(mut self, http_status: u16) -> Self {
+        self.http_status = Some(http_status);
+        self
+    }
+}
+
+/// Resolves a VASTAdTagURI (or file path) to the VAST XML it points to
+/// 
+/// Implementations should honor the request's timeout and size limit.
+pub trait VastFetcher: Send + Sync {
+    /// Fetch the VAST XML for a request
+    fn fetch(&self, request: FetchRequest<'_>) -> Result<FetchResponse>;
+}
+
+/// Async version of VastFetcher
+pub trait AsyncVastFetcher: Send + Sync {
+    /// Fetch the VAST XML for a request asynchronously
+    fn fetch_async<'a>(&'a self, request: FetchRequest<'a>) -> FetchFuture<'a>;
+}
+
+/// The default fetcher: reads `file://` URIs and local paths from disk and
+/// everything else over HTTP
+/// 
+/// `file://` paths that don't exist are also looked up in the `samples` directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFetcher;
+
+impl VastFetcher for DefaultFetcher {
+    fn fetch(&self, request: FetchRequest<'_>) -> Result<FetchResponse> {
+        if let Some(file_path) = resolve_file_path(request.uri) {
+            debug!(path:% = file_path.display(); "Reading VAST from file");
+            let content = std::fs::read_to_string(file_path)
+                .map_err(VastError::IoError)?;
+            request.check_size(content.len())?;
+            return Ok(FetchResponse::new(content));
+        }
+        
+        // Assume it's a web URL - use a runtime to run the async function
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| VastError::Other(format!("Failed to create Tokio runtime: {}", e)))?;
+        
+        rt.block_on(fetch_vast_from_url(request))
+    }
+}
+
+impl AsyncVastFetcher for DefaultFetcher {
+    fn fetch_async<'a>(&'a self, request: FetchRequest<'a>) -> FetchFuture<'a> {
+        Box::pin(async move {
+            if let Some(file_path) = resolve_file_path(request.uri) {
+                debug!(path:% = file_path.display(); "Reading VAST from file");
+                let content = tokio::fs::read_to_string(file_path)
+                    .await
+                    .map_err(VastError::IoError)?;
+                request.check_size(content.len())?;
+                return Ok(FetchResponse::new(content));
+            }
+            
+            fetch_vast_from_url(request).await
+        })
+    }
+}
+
+/// A fetcher that serves VAST documents from memory, keyed by URI
+/// 
+/// Useful for testing wrapper chains without touching the disk or network.
+#[derive(Debug, Clone, Default)]
+pub struct MapFetcher {
+    documents: HashMap<String, String>,
+}
+
+impl MapFetcher {
+    /// Create an empty fetcher
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a document served for the given URI
+    pub fn with_document(mut self, uri: impl Into<String>, xml: impl Into<String>) -> Self {
+        self.insert(uri, xml);
+        self
+    }
+    
+    /// Add or replace the document served for the given URI
+    pub fn insert(&mut self, uri: impl Into<String>, xml: impl Into<String>) {
+        self.documents.insert(uri.into(), xml.into());
+    }
+}
+
+impl VastFetcher for MapFetcher {
+    fn fetch(&self, request: FetchRequest<'_>) -> Result<FetchResponse> {
+        let content = self.documents.get(request.uri)
+            .cloned()
+            .ok_or_else(|| VastError::Other(format!("No document registered for URI: {}", request.uri)))?;
+        request.check_size(content.len())?;
+        Ok(FetchResponse::new(content))
+    }
+}
+
+impl AsyncVastFetcher for MapFetcher {
+    fn fetch_async<'a>(&'a self, request: FetchRequest<'a>) -> FetchFuture<'a> {
+        Box::pin(async move { self.fetch(request) })
+    }
+}
+
+/// Map a `file://` URI or existing local path to the file to read
+fn resolve_file_path(url_or_path: &str) -> Option<PathBuf> {
+    // Check if it's a file URL
+    if url_or_path.starts_with("file://") {
+        let path = url_or_path.trim_start_matches("file://");
+        
+        #[cfg(target_os = "windows")]
+        let path = path.trim_start_matches("/");
+        
+        // If the path doesn't exist directly, try to resolve it relative to the current directory
+        let path_buf = PathBuf::from(path);
+        if path_buf.exists() {
+            return Some(path_buf);
+        }
+        
+        // Check if we need to look in the samples directory
+        let samples_path = PathBuf::from("samples").join(path);
+        if samples_path.exists() {
+            return Some(samples_path);
+        }
+        
+        // Try current directory
+        return Some(path_buf);
+    }
+    
+    // Check if it's a plain file path
+    if Path::new(url_or_path).exists() {
+        return Some(PathBuf::from(url_or_path));
+    }
+    
+    None
+}
+
+/// Fetch VAST XML from a URL, honoring the request's timeout and size limit
+async fn fetch_vast_from_url(request: FetchRequest<'_>) -> Result<FetchResponse> {
+    // Generate a random request ID for tracking in logs
+    let req_id: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(6)
+        .map(char::from)
+        .collect();
+    
+    // Validate URL
+    let url = url::Url::parse(request.uri).map_err(VastError::UrlError)?;
+    
+    debug!(request_id = req_id.as_str(), uri = url.as_str(); "Fetching VAST from URL");
+    
+    // Start timing
+    let start_time = std::time::Instant::now();
+    
+    // Create a client with timeout
+    let client = reqwest::Client::builder()
+        .timeout(request.timeout)
+        .build()
+        .map_err(|e| VastError::Other(format!("Failed to build HTTP client: {}", e)))?;
+    
+    // Fetch content from URL with timeout
+    let mut response = client.get(url).send().await.map_err(|e| {
+        warn!(
+            request_id = req_id.as_str(),
+            elapsed_ms = start_time.elapsed().as_millis() as u64;
+            "Request failed: {}", e
+        );
+        request_error(e, "Failed to fetch URL")
+    })?;
+    
+    debug!(
+        request_id = req_id.as_str(),
+        status = response.status().as_u16(),
+        elapsed_ms = start_time.elapsed().as_millis() as u64;
+        "Received response"
+    );
+    
+    let http_status = response.status().as_u16();
+    if !response.status().is_success() {
+        return Err(VastError::HttpStatus(http_status));
+    }
+    
+    // Reject oversized responses up front when the server announces their length
+    if let Some(content_length) = response.content_length() {
+        request.check_size(usize::try_from(content_length).unwrap_or(usize::MAX))?;
+    }
+    
+    // Read the body in chunks so an oversized response is aborted early
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        request_error(e, "Failed to read response body")
+    })? {
+        body.extend_from_slice(&chunk);
+        request.check_size(body.len())?;
+    }
+    
+    let xml_content = String::from_utf8(body).map_err(|e| {
+        VastError::Other(format!("Failed to read response body: {}", e))
+    })?;
+    
+    info!(
+        request_id = req_id.as_str(),
+        uri = request.uri,
+        bytes = xml_content.len(),
+        elapsed_ms = start_time.elapsed().as_millis() as u64;
+        "Fetched VAST from URL"
+    );
+    
+    Ok(FetchResponse::new(xml_content).with_http_status(http_status))
+}
+
+/// Map a reqwest error to a VastError, keeping timeouts distinguishable
+fn request_error(error: reqwest::Error, context: &str) -> VastError {
+    if error.is_timeout() {
+        VastError::Timeout(format!("{}: {}", context, error))
+    } else {
+        VastError::Other(format!("{}: {}", context, error))
+    }
+}
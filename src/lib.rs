@@ -1,8 +1,17 @@
 pub mod models;
 pub mod parser;
 pub mod error;
+pub mod fetcher;
 pub mod unwrap;
+pub mod trace;
 pub mod stitcher;
+pub mod serializer;
+pub mod extensions;
+pub mod pod;
+pub mod playback;
+pub mod macros;
+pub mod media;
+pub mod time;
 
 pub mod async_api {
     use crate::error::Result;
@@ -17,7 +26,27 @@ pub mod async_api {
         crate::unwrap::unwrap_vast_async(xml_content).await
     }
 
+    pub async fn unwrap_vast_with_options(xml_content: &str, options: &crate::unwrap::UnwrapOptions) -> Result<Vast> {
+        crate::unwrap::unwrap_vast_async_with_options(xml_content, options).await
+    }
+
+    pub async fn unwrap_vast_with_trace(
+        xml_content: &str,
+        options: &crate::unwrap::UnwrapOptions,
+    ) -> Result<(Vast, crate::trace::ChainTrace)> {
+        crate::unwrap::unwrap_vast_with_trace_async(xml_content, &crate::fetcher::DefaultFetcher, options).await
+    }
+
+    pub async fn unwrap_vast_with_errors(xml_content: &str) -> Result<crate::unwrap::UnwrapReport> {
+        let options = crate::unwrap::UnwrapOptions::default();
+        crate::unwrap::unwrap_vast_with_errors_async(xml_content, &crate::fetcher::DefaultFetcher, &options).await
+    }
+
     pub async fn stitch_vast(xml_content: &str) -> Result<String> {
         crate::stitcher::stitch_vast_async(xml_content).await
     }
+
+    pub async fn stitch_vast_with_options(xml_content: &str, options: &crate::unwrap::UnwrapOptions) -> Result<String> {
+        crate::stitcher::stitch_vast_async_with_options(xml_content, options).await
+    }
 } 
\ No newline at end of file
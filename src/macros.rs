@@ -0,0 +1,334 @@
+use crate::error::VastErrorCode;
+use rand::{thread_rng, Rng};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What to put in place of a macro the context has no value for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownMacroPolicy {
+    /// Leave the macro as it is
+    #[default]
+    Leave,
+    
+    /// Replace the macro with nothing
+    Blank,
+    
+    /// Replace the macro with `-1`, the IAB value for "unknown"
+    Unknown,
+}
+
+/// Values for the IAB macros in tracking, impression, error and VASTAdTagURI URLs
+/// 
+/// Macros are written `[NAME]`, URL-encoded as `%5BNAME%5D`, or in the `%%NAME%%` style some
+/// ad servers use. Values are percent-encoded when substituted. `[TIMESTAMP]` and
+/// `[CACHEBUSTING]` get fresh values on every expansion unless set.
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::macros::{MacroContext, UnknownMacroPolicy};
+/// 
+/// let context = MacroContext::new()
+///     .with_ad_playhead(Duration::from_millis(5_250))
+///     .with_page_url("https://example.com/watch?v=1")
+///     .with_unknown_macros(UnknownMacroPolicy::Unknown);
+/// 
+/// let url = context.expand("https://t.example/p?t=[ADPLAYHEAD]&u=[PAGEURL]&ifa=[IFA]");
+/// assert_eq!(url, "https://t.example/p?t=00%3A00%3A05.250&u=https%3A%2F%2Fexample.com%2Fwatch%3Fv%3D1&ifa=-1");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MacroContext {
+    /// `[ERRORCODE]`: the IAB error code being reported
+    pub error_code: Option<VastErrorCode>,
+    
+    /// `[CONTENTPLAYHEAD]` / `[MEDIAPLAYHEAD]`: the playhead of the content the ad plays in
+    pub content_playhead: Option<Duration>,
+    
+    /// `[ADPLAYHEAD]`: the playhead of the ad
+    pub ad_playhead: Option<Duration>,
+    
+    /// `[ASSETURI]`: the URI of the media file being played
+    pub asset_uri: Option<String>,
+    
+    /// `[GDPRCONSENT]`: the TCF consent string
+    pub gdpr_consent: Option<String>,
+    
+    /// `[IFA]`: the device's identifier for advertising
+    pub ifa: Option<String>,
+    
+    /// `[DEVICEUA]`: the device's user agent
+    pub device_ua: Option<String>,
+    
+    /// `[DOMAIN]`: the domain of the page or app showing the ad
+    pub domain: Option<String>,
+    
+    /// `[PAGEURL]`: the URL of the page showing the ad
+    pub page_url: Option<String>,
+    
+    /// `[TIMESTAMP]`: the time of the expansion, now if not set
+    pub timestamp: Option<SystemTime>,
+    
+    /// `[CACHEBUSTING]` / `%%CACHEBUSTER%%`: a random 8 digit number if not set
+    pub cache_busting: Option<String>,
+    
+    /// Values for any other macros, by name without brackets
+    pub custom: BTreeMap<String, String>,
+    
+    /// What to do with macros that have no value
+    pub unknown_macros: UnknownMacroPolicy,
+}
+
+impl MacroContext {
+    /// A context with only the generated `[TIMESTAMP]` and `[CACHEBUSTING]` values
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the `[ERRORCODE]` value
+    pub fn with_error_code(mut self, error_code: VastErrorCode) -> Self {
+        self.error_code = Some(error_code);
+        self
+    }
+    
+    /// Set the `[CONTENTPLAYHEAD]` value
+    pub fn with_content_playhead(mut self, content_playhead: Duration) -> Self {
+        self.content_playhead = Some(content_playhead);
+        self
+    }
+    
+    /// Set the `[ADPLAYHEAD]` value
+    pub fn with_ad_playhead(mut self, ad_playhead: Duration) -> Self {
+        self.ad_playhead = Some(ad_playhead);
+        self
+    }
+    
+    /// Set the `[ASSETURI]` value
+    pub fn with_asset_uri(mut self, asset_uri: impl Into<String>) -> Self {
+        self.asset_uri = Some(asset_uri.into());
+        self
+    }
+    
+    /// Set the `[GDPRCONSENT]` value
+    pub fn with_gdpr_consent(mut self, gdpr_consent: impl Into<String>) -> Self {
+        self.gdpr_consent = Some(gdpr_consent.into());
+        self
+    }
+    
+    /// Set the `[IFA]` value
+    pub fn with_ifa(mut self, ifa: impl Into<String>) -> Self {
+        self.ifa = Some(ifa.into());
+        self
+    }
+    
+    /// Set the `[DEVICEUA]` value
+    pub fn with_device_ua(mut self, device_ua: impl Into<String>) -> Self {
+        self.device_ua = Some(device_ua.into());
+        self
+    }
+    
+    /// Set the `[DOMAIN]` value
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+    
+    /// Set the `[PAGEURL]` value
+    pub fn with_page_url(mut self, page_url: impl Into<String>) -> Self {
+        self.page_url = Some(page_url.into());
+        self
+    }
+    
+    /// Set the `[TIMESTAMP]` value instead of using the time of each expansion
+    pub fn with_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+    
+    /// Set the `[CACHEBUSTING]` value instead of generating one for each expansion
+    pub fn with_cache_busting(mut self, cache_busting: impl Into<String>) -> Self {
+        self.cache_busting = Some(cache_busting.into());
+        self
+    }
+    
+    /// Set the value of any other macro, by name without brackets
+    pub fn with_macro(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom.insert(name.into(), value.into());
+        self
+    }
+    
+    /// Set what to do with macros that have no value
+    pub fn with_unknown_macros(mut self, unknown_macros: UnknownMacroPolicy) -> Self {
+        self.unknown_macros = unknown_macros;
+        self
+    }
+    
+    /// The encoded value of a macro, or None if the context has none
+    fn value(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "ERRORCODE" => self.error_code.map(|code| code.to_string()),
+            "CACHEBUSTING" | "CACHEBUSTER" => Some(self.cache_busting.clone().unwrap_or_else(|| {
+                format!("{:08}", thread_rng().gen_range(0..100_000_000u32))
+            })),
+            "TIMESTAMP" => Some(iso8601_timestamp(self.timestamp.unwrap_or_else(SystemTime::now))),
+            "CONTENTPLAYHEAD" | "MEDIAPLAYHEAD" => self.content_playhead.map(format_playhead),
+            "ADPLAYHEAD" => self.ad_playhead.map(format_playhead),
+            "ASSETURI" => self.asset_uri.clone(),
+            "GDPRCONSENT" => self.gdpr_consent.clone(),
+            "IFA" => self.ifa.clone(),
+            "DEVICEUA" => self.device_ua.clone(),
+            "DOMAIN" => self.domain.clone(),
+            "PAGEURL" => self.page_url.clone(),
+            _ => None,
+        };
+        
+        value.or_else(|| self.custom.get(name).cloned())
+            .map(|value| percent_encode(&value))
+    }
+    
+    /// Substitute the macros of a URL
+    pub fn expand(&self, url: &str) -> String {
+        let mut result = String::with_capacity(url.len());
+        let mut rest = url;
+        
+        while !rest.is_empty() {
+            let Some((name, len)) = find_macro(rest) else {
+                let next = rest.chars().next().map_or(1, char::len_utf8);
+                result.push_str(&rest[..next]);
+                rest = &rest[next..];
+                continue;
+            };
+            
+            match (self.value(name), self.unknown_macros) {
+                (Some(value), _) => result.push_str(&value),
+                (None, UnknownMacroPolicy::Leave) => result.push_str(&rest[..len]),
+                (None, UnknownMacroPolicy::Blank) => (),
+                (None, UnknownMacroPolicy::Unknown) => result.push_str("-1"),
+            }
+            rest = &rest[len..];
+        }
+        
+        result
+    }
+}
+
+/// The name and length of the macro at the start of the text, if there is one
+fn find_macro(text: &str) -> Option<(&str, usize)> {
+    for (open, close) in [("[", "]"), ("%5B", "%5D"), ("%5b", "%5d"), ("%%", "%%")] {
+        let Some(after_open) = text.strip_prefix(open) else {
+            continue;
+        };
+        let Some(end) = after_open.find(close) else {
+            continue;
+        };
+        
+        // IAB macro names start with an uppercase letter, so e.g. "a[0]=1" is left alone
+        let name = &after_open[..end];
+        let is_name = name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_');
+        if is_name {
+            return Some((name, open.len() + end + close.len()));
+        }
+    }
+    None
+}
+
+/// Percent-encode everything but the RFC 3986 unreserved characters
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Format a playhead as `HH:MM:SS.mmm`
+fn format_playhead(playhead: Duration) -> String {
+    let secs = playhead.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3_600, (secs % 3_600) / 60, secs % 60,
+        playhead.subsec_millis()
+    )
+}
+
+/// Format a time as an ISO 8601 UTC timestamp with milliseconds
+fn iso8601_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+    
+    // Convert days since the epoch into a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day,
+        secs_of_day / 3_600, (secs_of_day % 3_600) / 60, secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn expands_every_macro_style() {
+        let context = MacroContext::new()
+            .with_error_code(VastErrorCode::NoAdsAfterWrapper)
+            .with_cache_busting("12345678");
+        
+        assert_eq!(
+            context.expand("https://t.example/e?c=[ERRORCODE]&a=%5BCACHEBUSTING%5D&b=%5bCACHEBUSTING%5d&d=%%CACHEBUSTER%%"),
+            "https://t.example/e?c=303&a=12345678&b=12345678&d=12345678",
+        );
+    }
+    
+    #[test]
+    fn encodes_values() {
+        let context = MacroContext::new()
+            .with_content_playhead(Duration::from_millis(3_723_004))
+            .with_timestamp(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123))
+            .with_macro("CLIENTUA", "a b&c");
+        
+        assert_eq!(context.expand("[CONTENTPLAYHEAD]"), "01%3A02%3A03.004");
+        assert_eq!(context.expand("[TIMESTAMP]"), "2023-11-14T22%3A13%3A20.123Z");
+        assert_eq!(context.expand("[CLIENTUA]"), "a%20b%26c");
+    }
+    
+    #[test]
+    fn applies_the_unknown_macro_policy() {
+        let url = "https://t.example/p?ifa=[IFA]";
+        let expand = |policy| MacroContext::new().with_unknown_macros(policy).expand(url);
+        
+        assert_eq!(expand(UnknownMacroPolicy::Leave), url);
+        assert_eq!(expand(UnknownMacroPolicy::Blank), "https://t.example/p?ifa=");
+        assert_eq!(expand(UnknownMacroPolicy::Unknown), "https://t.example/p?ifa=-1");
+    }
+    
+    #[test]
+    fn leaves_text_that_is_not_a_macro() {
+        let context = MacroContext::new().with_unknown_macros(UnknownMacroPolicy::Blank);
+        
+        for url in [
+            "https://t.example/p?a[0]=1&a[1]=2",
+            "https://t.example/p?a%5B0%5D=1",
+            "https://t.example/p?q=[lower]&r=[]&s=[_X]",
+            "https://t.example/p?discount=50%%20off",
+        ] {
+            assert_eq!(context.expand(url), url);
+        }
+        assert_eq!(context.expand("[a[IFA]"), "[a");
+    }
+}
@@ -1,18 +1,47 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use log::LevelFilter;
 use std::path::PathBuf;
+use std::time::Duration;
 
 // Import the library
 use vast_parser::{parser, unwrap};
 use vast_parser::async_api;
+use vast_parser::fetcher::DEFAULT_FETCH_TIMEOUT;
+use vast_parser::media::{DeviceProfile, MediaSelector};
+use vast_parser::stitcher::UrlDedup;
+use vast_parser::unwrap::UnwrapOptions;
 
 /// VAST parser and unwrapper
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
+    /// Increase log verbosity on stderr (-v for info, -vv for debug, -vvv for trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    
     #[command(subcommand)]
     command: Commands,
 }
 
+impl Cli {
+    /// Log level selected by the verbosity flags
+    fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::Error;
+        }
+        match self.verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Parse a VAST file or URL
@@ -35,6 +64,13 @@ enum Commands {
         /// Pretty print the output
         #[arg(short, long)]
         pretty: bool,
+        
+        /// Print a JSON trace of the wrapper chain instead of the unwrapped VAST
+        #[arg(long)]
+        trace: bool,
+        
+        #[command(flatten)]
+        limits: LimitArgs,
     },
     
     /// Stitch together a complete VAST XML with merged tracking elements
@@ -46,13 +82,161 @@ enum Commands {
         /// Output file path (if not specified, prints to stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
+        
+        /// Keep elements and attributes the parser doesn't model (e.g. vendor extensions)
+        #[arg(long)]
+        lossless: bool,
+        
+        /// Drop repeated tracking URLs: none, exact or normalized
+        #[arg(long, default_value = "none")]
+        dedup: UrlDedup,
+        
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    
+    /// Rank the media files of each linear ad for a device, explaining the scores
+    SelectMedia {
+        /// Path to the VAST file or URL
+        #[arg(short, long)]
+        input: String,
+        
+        #[command(flatten)]
+        profile: ProfileArgs,
+        
+        #[command(flatten)]
+        limits: LimitArgs,
     },
 }
 
+/// The device profile media files are ranked for
+#[derive(Args)]
+struct ProfileArgs {
+    /// Playable MIME types, comma separated (default: any)
+    #[arg(long, value_delimiter = ',')]
+    mime_types: Vec<String>,
+    
+    /// Playable codecs, comma separated (default: any)
+    #[arg(long, value_delimiter = ',')]
+    codecs: Vec<String>,
+    
+    /// Widest playable video, in pixels
+    #[arg(long)]
+    max_width: Option<u32>,
+    
+    /// Tallest playable video, in pixels
+    #[arg(long)]
+    max_height: Option<u32>,
+    
+    /// Measured bandwidth, in kbps
+    #[arg(long)]
+    bandwidth: Option<u32>,
+    
+    /// Highest playable bitrate, in kbps
+    #[arg(long)]
+    max_bitrate: Option<u32>,
+    
+    /// Preferred delivery: progressive or streaming
+    #[arg(long)]
+    delivery: Option<String>,
+    
+    /// Prefer HLS and DASH files
+    #[arg(long)]
+    prefer_adaptive: bool,
+    
+    /// Allow VPAID and Flash files
+    #[arg(long)]
+    allow_vpaid: bool,
+    
+    /// APIs offered to interactive creative files, comma separated (e.g. SIMID)
+    #[arg(long, value_delimiter = ',')]
+    interactive_apis: Vec<String>,
+}
+
+impl ProfileArgs {
+    /// Build the device profile for these flags
+    fn to_profile(&self) -> DeviceProfile {
+        let mut profile = DeviceProfile::new()
+            .with_mime_types(self.mime_types.iter().cloned())
+            .with_codecs(self.codecs.iter().cloned())
+            .with_prefer_adaptive(self.prefer_adaptive)
+            .with_allow_vpaid(self.allow_vpaid)
+            .with_interactive_apis(self.interactive_apis.iter().cloned());
+        
+        profile.max_width = self.max_width;
+        profile.max_height = self.max_height;
+        profile.bandwidth = self.bandwidth;
+        profile.max_bitrate = self.max_bitrate;
+        profile.delivery = self.delivery.clone();
+        
+        profile
+    }
+}
+
+/// Limits applied while following the wrapper chain
+#[derive(Args)]
+struct LimitArgs {
+    /// Maximum depth of the wrapper chain to follow
+    #[arg(long, default_value_t = unwrap::DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    
+    /// Timeout for each VASTAdTagURI fetch, in milliseconds
+    #[arg(long, default_value_t = DEFAULT_FETCH_TIMEOUT.as_millis() as u64)]
+    timeout_ms: u64,
+    
+    /// Time budget for the whole wrapper chain, in milliseconds
+    #[arg(long)]
+    deadline_ms: Option<u64>,
+    
+    /// Largest response to accept from a VASTAdTagURI, in bytes
+    #[arg(long)]
+    max_bytes: Option<usize>,
+    
+    /// Maximum number of ads taken from each VAST document
+    #[arg(long)]
+    max_ads: Option<usize>,
+    
+    /// Maximum number of VASTAdTagURIs fetched in total
+    #[arg(long)]
+    max_fetches: Option<usize>,
+}
+
+impl LimitArgs {
+    /// Build the unwrap options for these limits
+    fn to_options(&self) -> UnwrapOptions {
+        let mut options = UnwrapOptions::new()
+            .with_max_depth(self.max_depth)
+            .with_hop_timeout(Duration::from_millis(self.timeout_ms));
+        
+        if let Some(deadline_ms) = self.deadline_ms {
+            options = options.with_deadline(Duration::from_millis(deadline_ms));
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            options = options.with_max_response_bytes(max_bytes);
+        }
+        if let Some(max_ads) = self.max_ads {
+            options = options.with_max_ads_per_document(max_ads);
+        }
+        if let Some(max_fetches) = self.max_fetches {
+            options = options.with_max_total_fetches(max_fetches);
+        }
+        
+        options
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
-
+    
+    // Logs always go to stderr so stdout only ever carries command output;
+    // RUST_LOG still overrides the level for individual modules
+    env_logger::Builder::new()
+        .filter_level(cli.log_level())
+        .parse_default_env()
+        .target(env_logger::Target::Stderr)
+        .init();
+    
     match &cli.command {
         Commands::Parse { input, pretty } => {
             // Fetch the VAST content asynchronously
@@ -68,12 +252,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("{:?}", vast);
             }
         },
-        Commands::Unwrap { input, pretty } => {
+        Commands::Unwrap { input, pretty, trace, limits } => {
             // Fetch the VAST content asynchronously
             let content = unwrap::fetch_vast_content_async(input).await?;
             
+            if *trace {
+                // Unwrap the VAST asynchronously, keeping a trace of every hop
+                let (_, chain_trace) = async_api::unwrap_vast_with_trace(&content, &limits.to_options()).await?;
+                
+                // Print the trace as JSON
+                if *pretty {
+                    println!("{}", serde_json::to_string_pretty(&chain_trace)?);
+                } else {
+                    println!("{}", serde_json::to_string(&chain_trace)?);
+                }
+                return Ok(());
+            }
+            
             // Unwrap the VAST asynchronously
-            let vast = async_api::unwrap_vast(&content).await?;
+            let vast = async_api::unwrap_vast_with_options(&content, &limits.to_options()).await?;
             
             // Print the unwrapped VAST
             if *pretty {
@@ -82,22 +279,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("{:?}", vast);
             }
         },
-        Commands::Stitch { input, output } => {
+        Commands::Stitch { input, output, lossless, dedup, limits } => {
             // Fetch the VAST content asynchronously
             let content = unwrap::fetch_vast_content_async(input).await?;
             
             // Stitch the VAST asynchronously
-            let stitched_xml = async_api::stitch_vast(&content).await?;
+            let options = limits.to_options().with_lossless(*lossless).with_dedup(*dedup);
+            let stitched_xml = async_api::stitch_vast_with_options(&content, &options).await?;
             
             // Output the stitched VAST
             if let Some(output_path) = output {
                 tokio::fs::write(output_path, &stitched_xml).await?;
-                println!("Stitched VAST written to {}", output_path.display());
+                eprintln!("Stitched VAST written to {}", output_path.display());
             } else {
                 println!("{}", stitched_xml);
             }
         },
+        Commands::SelectMedia { input, profile, limits } => {
+            // Fetch the VAST content asynchronously
+            let content = unwrap::fetch_vast_content_async(input).await?;
+            
+            // Unwrap the VAST asynchronously
+            let vast = async_api::unwrap_vast_with_options(&content, &limits.to_options()).await?;
+            let selector = MediaSelector::new(profile.to_profile());
+            
+            // Print the ranking of each linear creative
+            for (ad_index, ad) in vast.ads.iter().enumerate() {
+                let Some(inline) = &ad.inline else {
+                    continue;
+                };
+                for (creative_index, creative) in inline.creatives.iter().enumerate() {
+                    let Some(linear) = &creative.linear else {
+                        continue;
+                    };
+                    
+                    println!(
+                        "Ad {} ({}), creative {} ({}):",
+                        ad_index + 1, ad.id.as_deref().unwrap_or("no id"),
+                        creative_index + 1, creative.id.as_deref().unwrap_or("no id")
+                    );
+                    for (rank, score) in selector.rank(linear).iter().enumerate() {
+                        if score.is_playable() {
+                            println!("  {}. [{}] {} ({})", rank + 1, score.score, score.media_file.url, score.media_file.mime_type);
+                        } else {
+                            println!("  -  [rejected] {} ({})", score.media_file.url, score.media_file.mime_type);
+                            for rejection in &score.rejections {
+                                println!("       x {}", rejection);
+                            }
+                        }
+                        for reason in &score.reasons {
+                            println!("       {}", reason);
+                        }
+                    }
+                    if let Some(interactive) = selector.select_interactive(linear) {
+                        println!("  interactive: {} ({})", interactive.url, interactive.api_framework.as_deref().unwrap_or_default());
+                    }
+                    if let Some(mezzanine) = selector.select_mezzanine(linear) {
+                        println!("  mezzanine: {} ({})", mezzanine.url, mezzanine.mime_type);
+                    }
+                }
+            }
+        },
     }
-
+    
     Ok(())
 }
@@ -0,0 +1,375 @@
+use crate::models::{InteractiveCreativeFile, Linear, MediaFile, Mezzanine};
+use std::cmp::Reverse;
+
+/// MIME types of adaptive streaming manifests
+const ADAPTIVE_MIME_TYPES: [&str; 4] = [
+    "application/x-mpegurl",
+    "application/vnd.apple.mpegurl",
+    "audio/mpegurl",
+    "application/dash+xml",
+];
+
+/// MIME types of VPAID and Flash creatives, which run code rather than play media
+const EXECUTABLE_MIME_TYPES: [&str; 3] = [
+    "application/javascript",
+    "text/javascript",
+    "application/x-shockwave-flash",
+];
+
+/// Share of the measured bandwidth a rendition should use, leaving headroom for the rest of the page
+const BANDWIDTH_HEADROOM: f64 = 0.8;
+
+/// Points for each scoring criterion
+const ADAPTIVE_POINTS: i64 = 1_000;
+const DELIVERY_POINTS: i64 = 200;
+const BITRATE_POINTS: f64 = 500.0;
+const RESOLUTION_POINTS: f64 = 300.0;
+
+/// Resolution treated as full quality when the profile has no maximum
+const FULL_HD_PIXELS: f64 = 1920.0 * 1080.0;
+
+/// What the playing device can handle
+/// 
+/// Empty lists and unset limits accept everything.
+/// 
+/// ```
+/// use vast_parser::media::DeviceProfile;
+/// 
+/// let profile = DeviceProfile::new()
+///     .with_mime_types(["video/mp4", "application/x-mpegURL"])
+///     .with_max_resolution(1280, 720)
+///     .with_bandwidth(2_500)
+///     .with_prefer_adaptive(true);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceProfile {
+    /// Playable MIME types, compared case-insensitively
+    pub mime_types: Vec<String>,
+    
+    /// Playable codecs, matched against the start of each codec in a media file's codec
+    /// attribute, so "avc1" accepts "avc1.4D401F"
+    pub codecs: Vec<String>,
+    
+    /// Widest playable video, in pixels
+    pub max_width: Option<u32>,
+    
+    /// Tallest playable video, in pixels
+    pub max_height: Option<u32>,
+    
+    /// Measured bandwidth, in kbps, used to pick the bitrate to aim for
+    pub bandwidth: Option<u32>,
+    
+    /// Highest playable bitrate, in kbps
+    pub max_bitrate: Option<u32>,
+    
+    /// Preferred delivery, "progressive" or "streaming"
+    pub delivery: Option<String>,
+    
+    /// Whether to prefer HLS and DASH files over single renditions
+    pub prefer_adaptive: bool,
+    
+    /// Whether VPAID and Flash files may be selected
+    pub allow_vpaid: bool,
+    
+    /// APIs the player offers to interactive creative files (e.g., "SIMID"), none if empty
+    pub interactive_apis: Vec<String>,
+}
+
+impl DeviceProfile {
+    /// A profile accepting every media file except VPAID
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the playable MIME types
+    pub fn with_mime_types<I, S>(mut self, mime_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.mime_types = mime_types.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// Set the playable codecs
+    pub fn with_codecs<I, S>(mut self, codecs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.codecs = codecs.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// Set the largest playable video
+    pub fn with_max_resolution(mut self, max_width: u32, max_height: u32) -> Self {
+        self.max_width = Some(max_width);
+        self.max_height = Some(max_height);
+        self
+    }
+    
+    /// Set the measured bandwidth, in kbps
+    pub fn with_bandwidth(mut self, bandwidth: u32) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+    
+    /// Set the highest playable bitrate, in kbps
+    pub fn with_max_bitrate(mut self, max_bitrate: u32) -> Self {
+        self.max_bitrate = Some(max_bitrate);
+        self
+    }
+    
+    /// Set the preferred delivery, "progressive" or "streaming"
+    pub fn with_delivery(mut self, delivery: impl Into<String>) -> Self {
+        self.delivery = Some(delivery.into());
+        self
+    }
+    
+    /// Set whether to prefer HLS and DASH files
+    pub fn with_prefer_adaptive(mut self, prefer_adaptive: bool) -> Self {
+        self.prefer_adaptive = prefer_adaptive;
+        self
+    }
+    
+    /// Set whether VPAID and Flash files may be selected
+    pub fn with_allow_vpaid(mut self, allow_vpaid: bool) -> Self {
+        self.allow_vpaid = allow_vpaid;
+        self
+    }
+    
+    /// Set the APIs the player offers to interactive creative files
+    pub fn with_interactive_apis<I, S>(mut self, interactive_apis: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.interactive_apis = interactive_apis.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// The bitrate to aim for, in kbps, if the bandwidth or a maximum is known
+    pub fn target_bitrate(&self) -> Option<u32> {
+        let from_bandwidth = self.bandwidth.map(|bandwidth| (f64::from(bandwidth) * BANDWIDTH_HEADROOM) as u32);
+        match (from_bandwidth, self.max_bitrate) {
+            (Some(target), Some(max_bitrate)) => Some(target.min(max_bitrate)),
+            (target, max_bitrate) => target.or(max_bitrate),
+        }
+    }
+}
+
+/// How well a media file suits a device profile
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaScore<'a> {
+    /// The media file
+    pub media_file: &'a MediaFile,
+    
+    /// The score, higher is better; only comparable between playable files
+    pub score: i64,
+    
+    /// Why the file can't be played on the device, empty if it can
+    pub rejections: Vec<String>,
+    
+    /// How the score was made up, one line per criterion
+    pub reasons: Vec<String>,
+}
+
+impl MediaScore<'_> {
+    /// Whether the device can play the file
+    pub fn is_playable(&self) -> bool {
+        self.rejections.is_empty()
+    }
+}
+
+/// Ranks the media files of a linear ad for a device
+/// 
+/// Files the device can't play are rejected with the reasons why. Playable files are scored
+/// on adaptive streaming (when preferred), delivery, how close the bitrate comes to the
+/// target bitrate without going over it, and resolution.
+/// 
+/// ```
+/// use vast_parser::media::{DeviceProfile, MediaSelector};
+/// use vast_parser::parser;
+/// 
+/// let xml = std::fs::read_to_string("samples/sample_vast.xml").unwrap();
+/// let vast = parser::parse_vast(&xml).unwrap();
+/// let linear = vast.ads[0].inline.as_ref().unwrap().creatives[0].linear.as_ref().unwrap();
+/// 
+/// let selector = MediaSelector::new(DeviceProfile::new().with_mime_types(["video/mp4"]));
+/// let best = selector.select(linear).unwrap();
+/// assert_eq!(best.mime_type, "video/mp4");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaSelector {
+    /// The device to select for
+    pub profile: DeviceProfile,
+}
+
+impl MediaSelector {
+    /// A selector for the given device
+    pub fn new(profile: DeviceProfile) -> Self {
+        MediaSelector { profile }
+    }
+    
+    /// Score every media file of a linear ad, best first, with rejected files last
+    pub fn rank<'a>(&self, linear: &'a Linear) -> Vec<MediaScore<'a>> {
+        self.rank_files(&linear.media_files)
+    }
+    
+    /// Score media files, best first, with rejected files last
+    /// 
+    /// Files with the same score, and rejected files, keep document order.
+    pub fn rank_files<'a>(&self, media_files: &'a [MediaFile]) -> Vec<MediaScore<'a>> {
+        let mut scores: Vec<MediaScore<'a>> = media_files.iter()
+            .map(|media_file| self.score(media_file))
+            .collect();
+        scores.sort_by_key(|score| {
+            let playable = score.is_playable();
+            (!playable, Reverse(if playable { score.score } else { 0 }))
+        });
+        scores
+    }
+    
+    /// The best playable media file of a linear ad
+    pub fn select<'a>(&self, linear: &'a Linear) -> Option<&'a MediaFile> {
+        self.rank(linear).into_iter()
+            .find(MediaScore::is_playable)
+            .map(|score| score.media_file)
+    }
+    
+    /// The interactive creative file to run over the selected media file, if the player
+    /// offers its API
+    pub fn select_interactive<'a>(&self, linear: &'a Linear) -> Option<&'a InteractiveCreativeFile> {
+        linear.interactive_creative_files.iter().find(|file| {
+            file.api_framework.as_deref().is_some_and(|api| {
+                self.profile.interactive_apis.iter().any(|supported| supported.eq_ignore_ascii_case(api.trim()))
+            })
+        })
+    }
+    
+    /// The mezzanine to hand to a transcoder, the one with the highest resolution
+    /// 
+    /// Mezzanines are never played directly, so the device profile doesn't apply. Mezzanines
+    /// with the same or an unknown resolution keep document order.
+    pub fn select_mezzanine<'a>(&self, linear: &'a Linear) -> Option<&'a Mezzanine> {
+        linear.mezzanines.iter()
+            .rev()
+            .max_by_key(|mezzanine| u64::from(mezzanine.width.unwrap_or(0)) * u64::from(mezzanine.height.unwrap_or(0)))
+    }
+    
+    /// Score a single media file
+    pub fn score<'a>(&self, media_file: &'a MediaFile) -> MediaScore<'a> {
+        let profile = &self.profile;
+        let mut result = MediaScore {
+            media_file,
+            score: 0,
+            rejections: Vec::new(),
+            reasons: Vec::new(),
+        };
+        let mime_type = media_file.mime_type.trim().to_ascii_lowercase();
+        
+        // Playability
+        if !profile.mime_types.is_empty()
+            && !profile.mime_types.iter().any(|supported| supported.eq_ignore_ascii_case(&mime_type))
+        {
+            result.rejections.push(format!("unsupported MIME type {}", media_file.mime_type));
+        }
+        if let Some(codec) = &media_file.codec
+            && !profile.codecs.is_empty()
+        {
+            let unsupported: Vec<&str> = codec.split(',')
+                .map(str::trim)
+                .filter(|codec| !profile.codecs.iter().any(|supported| codec.starts_with(supported.as_str())))
+                .collect();
+            if !unsupported.is_empty() {
+                result.rejections.push(format!("unsupported codec {}", unsupported.join(", ")));
+            }
+        }
+        if !profile.allow_vpaid && is_vpaid(media_file, &mime_type) {
+            result.rejections.push("VPAID is not allowed".to_string());
+        }
+        if let (Some(max_width), Some(width)) = (profile.max_width, media_file.width)
+            && width > max_width
+        {
+            result.rejections.push(format!("width {} is above {}", width, max_width));
+        }
+        if let (Some(max_height), Some(height)) = (profile.max_height, media_file.height)
+            && height > max_height
+        {
+            result.rejections.push(format!("height {} is above {}", height, max_height));
+        }
+        if let (Some(max_bitrate), Some(bitrate)) = (profile.max_bitrate, lowest_bitrate(media_file))
+            && bitrate > max_bitrate
+        {
+            result.rejections.push(format!("bitrate {} kbps is above {} kbps", bitrate, max_bitrate));
+        }
+        
+        // Preferences
+        let adaptive = ADAPTIVE_MIME_TYPES.contains(&mime_type.as_str());
+        if profile.prefer_adaptive && adaptive {
+            result.add(ADAPTIVE_POINTS, format!("adaptive stream ({})", media_file.mime_type));
+        }
+        
+        if let (Some(preferred), Some(delivery)) = (&profile.delivery, &media_file.delivery)
+            && preferred.eq_ignore_ascii_case(delivery.trim())
+        {
+            result.add(DELIVERY_POINTS, format!("{} delivery", preferred));
+        }
+        
+        // Adaptive streams switch bitrate themselves, so only renditions are fitted to the target
+        if !adaptive
+            && let (Some(target), Some(bitrate)) = (profile.target_bitrate(), representative_bitrate(media_file))
+            && target > 0
+        {
+            let ratio = f64::from(bitrate) / f64::from(target);
+            if ratio <= 1.0 {
+                let points = (ratio * BITRATE_POINTS).round() as i64;
+                result.add(points, format!("bitrate {} kbps fits the {} kbps target", bitrate, target));
+            } else {
+                let points = -((ratio - 1.0) * BITRATE_POINTS).round() as i64;
+                result.add(points, format!("bitrate {} kbps is over the {} kbps target", bitrate, target));
+            }
+        }
+        
+        if let (Some(width), Some(height)) = (media_file.width, media_file.height) {
+            let full_quality = match (profile.max_width, profile.max_height) {
+                (Some(max_width), Some(max_height)) => f64::from(max_width) * f64::from(max_height),
+                _ => FULL_HD_PIXELS,
+            };
+            let coverage = (f64::from(width) * f64::from(height) / full_quality).min(1.0);
+            let points = (coverage * RESOLUTION_POINTS).round() as i64;
+            result.add(points, format!("resolution {}x{}", width, height));
+        }
+        
+        result
+    }
+}
+
+impl MediaScore<'_> {
+    /// Add points for a criterion
+    fn add(&mut self, points: i64, reason: String) {
+        self.score += points;
+        self.reasons.push(format!("{:+} {}", points, reason));
+    }
+}
+
+/// Whether a media file is a VPAID or Flash creative
+fn is_vpaid(media_file: &MediaFile, mime_type: &str) -> bool {
+    let framework = media_file.api_framework.as_deref().unwrap_or_default();
+    framework.trim().to_ascii_uppercase().starts_with("VPAID")
+        || EXECUTABLE_MIME_TYPES.contains(&mime_type)
+}
+
+/// The lowest bitrate a media file can play at, in kbps
+fn lowest_bitrate(media_file: &MediaFile) -> Option<u32> {
+    media_file.bitrate.or(media_file.min_bitrate)
+}
+
+/// The bitrate a media file is expected to play at, in kbps
+fn representative_bitrate(media_file: &MediaFile) -> Option<u32> {
+    match (media_file.bitrate, media_file.min_bitrate, media_file.max_bitrate) {
+        (Some(bitrate), _, _) => Some(bitrate),
+        (None, Some(min_bitrate), Some(max_bitrate)) => Some(min_bitrate / 2 + max_bitrate / 2),
+        (None, min_bitrate, max_bitrate) => min_bitrate.or(max_bitrate),
+    }
+}
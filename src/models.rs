@@ -1,4 +1,6 @@
+use crate::time::{VastDuration, VastOffset};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Represents a VAST document (Video Ad Serving Template)
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -11,6 +13,10 @@ pub struct Vast {
     
     /// Any error information if present
     pub error: Option<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents an Ad within a VAST document
@@ -30,6 +36,10 @@ pub struct Ad {
     
     /// The wrapper ad details
     pub wrapper: Option<Wrapper>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents an InLine ad, which contains all the media files and tracking information
@@ -41,9 +51,18 @@ pub struct InLine {
     /// The ad title
     pub ad_title: String,
     
+    /// The ad server's ID for this ad response, shared by every party serving it
+    pub ad_serving_id: Option<String>,
+    
     /// Impression tracking URLs
     pub impressions: Vec<Impression>,
     
+    /// Viewability tracking URLs
+    pub viewable_impression: Option<ViewableImpression>,
+    
+    /// The categories of the ad's content
+    pub categories: Vec<Category>,
+    
     /// The description of the ad
     pub description: Option<String>,
     
@@ -53,22 +72,42 @@ pub struct InLine {
     /// The survey URL
     pub survey: Option<String>,
     
+    /// How long the ad may be cached, in seconds
+    pub expires: Option<u64>,
+    
     /// Error tracking URLs
-    pub error: Option<String>,
+    pub error: Vec<String>,
     
     /// Pricing information
     pub pricing: Option<Pricing>,
     
+    /// Ad verification (Open Measurement) resources from `<AdVerifications>`; see
+    /// `verifications` for ones in VAST 3 extensions too
+    pub ad_verifications: Vec<Verification>,
+    
     /// Extensions
     pub extensions: Vec<Extension>,
     
     /// Creative elements
     pub creatives: Vec<Creative>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a Wrapper ad, which references another VAST document
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Wrapper {
+    /// Whether the requested VAST response may itself contain wrappers
+    pub follow_additional_wrappers: Option<bool>,
+    
+    /// Whether the requested VAST response may contain ad pods or multiple ads
+    pub allow_multiple_ads: Option<bool>,
+    
+    /// Whether fallback ads may be used when the requested VAST response has no ads
+    pub fallback_on_no_ad: Option<bool>,
+    
     /// The ad system name and version
     pub ad_system: AdSystem,
     
@@ -78,14 +117,49 @@ pub struct Wrapper {
     /// Impression tracking URLs
     pub impressions: Vec<Impression>,
     
+    /// Viewability tracking URLs
+    pub viewable_impression: Option<ViewableImpression>,
+    
     /// Error tracking URLs
-    pub error: Option<String>,
+    pub error: Vec<String>,
+    
+    /// Categories of ads the wrapper must not resolve to
+    pub blocked_ad_categories: Vec<Category>,
+    
+    /// Ad verification (Open Measurement) resources from `<AdVerifications>`; see
+    /// `verifications` for ones in VAST 3 extensions too
+    pub ad_verifications: Vec<Verification>,
     
     /// Extensions
     pub extensions: Vec<Extension>,
     
     /// Creative elements
     pub creatives: Vec<Creative>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a Category or BlockedAdCategories element
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Category {
+    /// The URL of the taxonomy the codes come from
+    pub authority: Option<String>,
+    
+    /// The category codes, comma separated in BlockedAdCategories
+    pub value: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+impl Category {
+    /// The individual category codes
+    pub fn codes(&self) -> impl Iterator<Item = &str> {
+        self.value.split(',').map(str::trim).filter(|code| !code.is_empty())
+    }
 }
 
 /// Represents the ad system information
@@ -96,6 +170,10 @@ pub struct AdSystem {
     
     /// The ad system version
     pub version: Option<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents an impression tracking URL
@@ -106,6 +184,37 @@ pub struct Impression {
     
     /// The impression tracking URL
     pub url: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents the viewability tracking URLs of an ad
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct ViewableImpression {
+    /// The viewable impression ID
+    pub id: Option<String>,
+    
+    /// URLs to request when the ad meets the viewability criteria
+    pub viewable: Vec<String>,
+    
+    /// URLs to request when the ad was measured and didn't meet the viewability criteria
+    pub not_viewable: Vec<String>,
+    
+    /// URLs to request when viewability couldn't be measured
+    pub view_undetermined: Vec<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+impl ViewableImpression {
+    /// Whether there are no viewability URLs
+    pub fn is_empty(&self) -> bool {
+        self.viewable.is_empty() && self.not_viewable.is_empty() && self.view_undetermined.is_empty()
+    }
 }
 
 /// Represents pricing information
@@ -119,15 +228,82 @@ pub struct Pricing {
     
     /// The price value
     pub value: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
-/// Represents an extension
+/// Represents an ad verification vendor entry (Open Measurement)
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Verification {
+    /// The verification vendor (e.g., "company.com-omid")
+    pub vendor: Option<String>,
+    
+    /// JavaScript verification resources
+    pub javascript_resources: Vec<JavaScriptResource>,
+    
+    /// Executable verification resources
+    pub executable_resources: Vec<ExecutableResource>,
+    
+    /// Parameters passed to the verification script
+    pub verification_parameters: Option<String>,
+    
+    /// Verification tracking events (e.g., "verificationNotExecuted")
+    pub tracking_events: Vec<TrackingEvent>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a JavaScript verification resource
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct JavaScriptResource {
+    /// The API framework (e.g., "omid")
+    pub api_framework: Option<String>,
+    
+    /// Whether the script may be skipped in non-browser environments
+    pub browser_optional: Option<bool>,
+    
+    /// The script URL
+    pub url: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents an executable verification resource
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ExecutableResource {
+    /// The API framework
+    pub api_framework: Option<String>,
+    
+    /// The executable type
+    pub r#type: Option<String>,
+    
+    /// The executable URL
+    pub url: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents an extension (`<Extension>` or `<CreativeExtension>`)
+/// 
+/// Use `Extension::decode` with a `VastExtension` type to read known extensions.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Extension {
     /// The extension type
     pub r#type: Option<String>,
     
-    /// The extension content
+    /// Attributes other than type, as (name, unescaped value) pairs
+    #[serde(default)]
+    pub attributes: Vec<(String, String)>,
+    
+    /// The raw inner XML of the extension
     pub content: String,
 }
 
@@ -146,6 +322,12 @@ pub struct Creative {
     /// The creative API framework
     pub api_framework: Option<String>,
     
+    /// Identifiers of the creative in ad-ID registries
+    pub universal_ad_ids: Vec<UniversalAdId>,
+    
+    /// Creative-level extensions
+    pub creative_extensions: Vec<Extension>,
+    
     /// Linear ad details
     pub linear: Option<Linear>,
     
@@ -154,27 +336,148 @@ pub struct Creative {
     
     /// NonLinearAds details
     pub non_linear_ads: Option<NonLinearAds>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a creative identifier in an ad-ID registry (e.g., "Ad-ID", "clearcast")
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct UniversalAdId {
+    /// The registry the identifier belongs to
+    pub id_registry: String,
+    
+    /// The identifier (the element content, or the VAST 4.0 idValue attribute)
+    pub id: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a linear ad
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Linear {
+    /// When the ad may be skipped, for skippable ads
+    pub skip_offset: Option<VastOffset>,
+    
     /// The duration of the ad
-    pub duration: Option<String>,
+    pub duration: Option<VastDuration>,
     
     /// Media files
     pub media_files: Vec<MediaFile>,
     
+    /// High quality source files for transcoding (VAST 4)
+    pub mezzanines: Vec<Mezzanine>,
+    
+    /// Interactive files run alongside the media file, e.g. SIMID (VAST 4)
+    pub interactive_creative_files: Vec<InteractiveCreativeFile>,
+    
+    /// Caption files for the media files (VAST 4)
+    pub closed_caption_files: Vec<ClosedCaptionFile>,
+    
     /// Video clicks
     pub video_clicks: Option<VideoClicks>,
     
     /// Tracking events
     pub tracking_events: Vec<TrackingEvent>,
+    
+    /// Industry icons (e.g., AdChoices)
+    pub icons: Vec<Icon>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents an industry icon (e.g., AdChoices) displayed over the ad
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Icon {
+    /// The industry initiative the icon supports (e.g., "AdChoices")
+    pub program: Option<String>,
+    
+    /// The icon width
+    pub width: u32,
+    
+    /// The icon height
+    pub height: u32,
+    
+    /// The horizontal position ("left", "right" or a pixel offset)
+    pub x_position: Option<String>,
+    
+    /// The vertical position ("top", "bottom" or a pixel offset)
+    pub y_position: Option<String>,
+    
+    /// How long the icon should be displayed
+    pub duration: Option<VastDuration>,
+    
+    /// When the icon should be displayed, relative to the start of the ad
+    pub offset: Option<VastDuration>,
+    
+    /// The icon API framework
+    pub api_framework: Option<String>,
+    
+    /// The pixel ratio the icon was designed for
+    pub pxratio: Option<String>,
+    
+    /// The icon resources (StaticResource, IFrameResource and/or HTMLResource)
+    pub resources: Vec<Resource>,
+    
+    /// Icon click-through and click-tracking URLs
+    pub icon_clicks: Option<IconClicks>,
+    
+    /// Icon view tracking URLs
+    pub view_tracking: Vec<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents icon click-through and click-tracking URLs
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct IconClicks {
+    /// The icon click-through URL
+    pub click_through: Option<String>,
+    
+    /// Icon click tracking URLs
+    pub click_tracking: Vec<String>,
+    
+    /// Fallback images shown when the click-through can't be opened
+    pub fallback_images: Vec<IconClickFallbackImage>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a fallback image for an icon click
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct IconClickFallbackImage {
+    /// The image width
+    pub width: Option<u32>,
+    
+    /// The image height
+    pub height: Option<u32>,
+    
+    /// Alternative text for the image
+    pub alt_text: Option<String>,
+    
+    /// The image resource
+    pub static_resource: Option<Resource>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a media file
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct MediaFile {
+    /// The media file ID
+    pub id: Option<String>,
+    
     /// The media file URL
     pub url: String,
     
@@ -184,9 +487,15 @@ pub struct MediaFile {
     /// The media file codec
     pub codec: Option<String>,
     
-    /// The media file bitrate
+    /// The media file bitrate, in kbps
     pub bitrate: Option<u32>,
     
+    /// The minimum bitrate of a streaming media file, in kbps
+    pub min_bitrate: Option<u32>,
+    
+    /// The maximum bitrate of a streaming media file, in kbps
+    pub max_bitrate: Option<u32>,
+    
     /// The media file width
     pub width: Option<u32>,
     
@@ -198,6 +507,94 @@ pub struct MediaFile {
     
     /// The media file type (video or audio)
     pub r#type: Option<String>,
+    
+    /// The API the media file needs (e.g., "VPAID")
+    pub api_framework: Option<String>,
+    
+    /// Whether the media file may be scaled to a different size
+    pub scalable: Option<bool>,
+    
+    /// Whether the aspect ratio must be kept when scaling
+    pub maintain_aspect_ratio: Option<bool>,
+    
+    /// The size of the media file, in bytes
+    pub file_size: Option<u64>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a mezzanine file, the raw high quality source of the ad for transcoding
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Mezzanine {
+    /// The mezzanine ID
+    pub id: Option<String>,
+    
+    /// The mezzanine URL
+    pub url: String,
+    
+    /// The mezzanine MIME type
+    pub mime_type: String,
+    
+    /// The mezzanine delivery type (progressive or streaming)
+    pub delivery: Option<String>,
+    
+    /// The mezzanine width
+    pub width: Option<u32>,
+    
+    /// The mezzanine height
+    pub height: Option<u32>,
+    
+    /// The mezzanine codec
+    pub codec: Option<String>,
+    
+    /// The size of the mezzanine, in bytes
+    pub file_size: Option<u64>,
+    
+    /// The mezzanine type (video or audio)
+    pub r#type: Option<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents an interactive file run alongside the media file (e.g., a SIMID creative)
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct InteractiveCreativeFile {
+    /// The interactive file URL
+    pub url: String,
+    
+    /// The interactive file MIME type
+    pub mime_type: Option<String>,
+    
+    /// The API used to talk to the player (e.g., "SIMID")
+    pub api_framework: Option<String>,
+    
+    /// Whether the interactive file may change the duration of the ad
+    pub variable_duration: Option<bool>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a closed caption file
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ClosedCaptionFile {
+    /// The caption file URL
+    pub url: String,
+    
+    /// The caption file MIME type (e.g., "text/vtt")
+    pub mime_type: Option<String>,
+    
+    /// The caption language (e.g., "en")
+    pub language: Option<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents video click-through and click-tracking URLs
@@ -211,23 +608,275 @@ pub struct VideoClicks {
     
     /// Custom click URLs
     pub custom_click: Vec<String>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a tracking event
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct TrackingEvent {
-    /// The event type (e.g., "start", "firstQuartile", "midpoint", "thirdQuartile", "complete", etc.)
-    pub event: String,
+    /// The event type
+    pub event: TrackingEventKind,
     
     /// The tracking URL
     pub url: String,
+    
+    /// When a progress event fires
+    pub offset: Option<VastOffset>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// The kind of a tracking event, covering the VAST 2, 3 and 4 vocabularies
+/// 
+/// Kinds are ordered as listed, which is the order the stitcher groups events in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum TrackingEventKind {
+    /// The creative was first displayed (`creativeView`)
+    CreativeView,
+    /// The ad started playing (`start`)
+    Start,
+    /// 25% of the ad was played (`firstQuartile`)
+    FirstQuartile,
+    /// 50% of the ad was played (`midpoint`)
+    Midpoint,
+    /// 75% of the ad was played (`thirdQuartile`)
+    ThirdQuartile,
+    /// The ad played to the end (`complete`)
+    Complete,
+    /// Playback reached the event's offset (`progress`)
+    Progress,
+    /// The ad was loaded and buffered (`loaded`)
+    Loaded,
+    /// The user muted the ad (`mute`)
+    Mute,
+    /// The user unmuted the ad (`unmute`)
+    Unmute,
+    /// The user paused the ad (`pause`)
+    Pause,
+    /// The user resumed the ad (`resume`)
+    Resume,
+    /// The user rewound the ad (`rewind`)
+    Rewind,
+    /// The user skipped the ad (`skip`)
+    Skip,
+    /// The user entered fullscreen, replaced by playerExpand in VAST 4 (`fullscreen`)
+    Fullscreen,
+    /// The user left fullscreen, replaced by playerCollapse in VAST 4 (`exitFullscreen`)
+    ExitFullscreen,
+    /// The player was expanded (`playerExpand`)
+    PlayerExpand,
+    /// The player was collapsed (`playerCollapse`)
+    PlayerCollapse,
+    /// The user expanded the player, replaced by playerExpand in VAST 4 (`expand`)
+    Expand,
+    /// The user collapsed the player, replaced by playerCollapse in VAST 4 (`collapse`)
+    Collapse,
+    /// The user expanded the ad (`adExpand`)
+    AdExpand,
+    /// The user collapsed the ad (`adCollapse`)
+    AdCollapse,
+    /// The user minimized the non-linear ad (`minimize`)
+    Minimize,
+    /// The user closed the non-linear ad (`close`)
+    Close,
+    /// The user closed the linear ad (`closeLinear`)
+    CloseLinear,
+    /// The non-linear ad was displayed for its offset (`overlayViewDuration`)
+    OverlayViewDuration,
+    /// The user accepted the ad's invitation (`acceptInvitation`)
+    AcceptInvitation,
+    /// The user accepted the linear ad's invitation (`acceptInvitationLinear`)
+    AcceptInvitationLinear,
+    /// Any other user interaction with the ad (`otherAdInteraction`)
+    OtherAdInteraction,
+    /// The interactive creative started (`interactiveStart`)
+    InteractiveStart,
+    /// The ad was not played, e.g. it was prefetched but never shown (`notUsed`)
+    NotUsed,
+    /// The verification script was not executed (`verificationNotExecuted`)
+    VerificationNotExecuted,
+    /// An event outside the VAST vocabulary, with its name as found
+    Unknown(String),
+}
+
+impl TrackingEventKind {
+    /// Every kind in the VAST vocabulary
+    pub const ALL: [TrackingEventKind; 32] = [
+        TrackingEventKind::CreativeView,
+        TrackingEventKind::Start,
+        TrackingEventKind::FirstQuartile,
+        TrackingEventKind::Midpoint,
+        TrackingEventKind::ThirdQuartile,
+        TrackingEventKind::Complete,
+        TrackingEventKind::Progress,
+        TrackingEventKind::Loaded,
+        TrackingEventKind::Mute,
+        TrackingEventKind::Unmute,
+        TrackingEventKind::Pause,
+        TrackingEventKind::Resume,
+        TrackingEventKind::Rewind,
+        TrackingEventKind::Skip,
+        TrackingEventKind::Fullscreen,
+        TrackingEventKind::ExitFullscreen,
+        TrackingEventKind::PlayerExpand,
+        TrackingEventKind::PlayerCollapse,
+        TrackingEventKind::Expand,
+        TrackingEventKind::Collapse,
+        TrackingEventKind::AdExpand,
+        TrackingEventKind::AdCollapse,
+        TrackingEventKind::Minimize,
+        TrackingEventKind::Close,
+        TrackingEventKind::CloseLinear,
+        TrackingEventKind::OverlayViewDuration,
+        TrackingEventKind::AcceptInvitation,
+        TrackingEventKind::AcceptInvitationLinear,
+        TrackingEventKind::OtherAdInteraction,
+        TrackingEventKind::InteractiveStart,
+        TrackingEventKind::NotUsed,
+        TrackingEventKind::VerificationNotExecuted,
+    ];
+    
+    /// The event name as written in VAST
+    pub fn as_str(&self) -> &str {
+        match self {
+            TrackingEventKind::CreativeView => "creativeView",
+            TrackingEventKind::Start => "start",
+            TrackingEventKind::FirstQuartile => "firstQuartile",
+            TrackingEventKind::Midpoint => "midpoint",
+            TrackingEventKind::ThirdQuartile => "thirdQuartile",
+            TrackingEventKind::Complete => "complete",
+            TrackingEventKind::Progress => "progress",
+            TrackingEventKind::Loaded => "loaded",
+            TrackingEventKind::Mute => "mute",
+            TrackingEventKind::Unmute => "unmute",
+            TrackingEventKind::Pause => "pause",
+            TrackingEventKind::Resume => "resume",
+            TrackingEventKind::Rewind => "rewind",
+            TrackingEventKind::Skip => "skip",
+            TrackingEventKind::Fullscreen => "fullscreen",
+            TrackingEventKind::ExitFullscreen => "exitFullscreen",
+            TrackingEventKind::PlayerExpand => "playerExpand",
+            TrackingEventKind::PlayerCollapse => "playerCollapse",
+            TrackingEventKind::Expand => "expand",
+            TrackingEventKind::Collapse => "collapse",
+            TrackingEventKind::AdExpand => "adExpand",
+            TrackingEventKind::AdCollapse => "adCollapse",
+            TrackingEventKind::Minimize => "minimize",
+            TrackingEventKind::Close => "close",
+            TrackingEventKind::CloseLinear => "closeLinear",
+            TrackingEventKind::OverlayViewDuration => "overlayViewDuration",
+            TrackingEventKind::AcceptInvitation => "acceptInvitation",
+            TrackingEventKind::AcceptInvitationLinear => "acceptInvitationLinear",
+            TrackingEventKind::OtherAdInteraction => "otherAdInteraction",
+            TrackingEventKind::InteractiveStart => "interactiveStart",
+            TrackingEventKind::NotUsed => "notUsed",
+            TrackingEventKind::VerificationNotExecuted => "verificationNotExecuted",
+            TrackingEventKind::Unknown(name) => name,
+        }
+    }
+    
+    /// The VAST versions that define the event: from the first one, up to but not
+    /// including the second one if the event was later dropped
+    fn versions(&self) -> Option<(VersionNumber, Option<VersionNumber>)> {
+        let versions = match self {
+            TrackingEventKind::CreativeView => ((2, 0), None),
+            TrackingEventKind::Start => ((2, 0), None),
+            TrackingEventKind::FirstQuartile => ((2, 0), None),
+            TrackingEventKind::Midpoint => ((2, 0), None),
+            TrackingEventKind::ThirdQuartile => ((2, 0), None),
+            TrackingEventKind::Complete => ((2, 0), None),
+            TrackingEventKind::Progress => ((3, 0), None),
+            TrackingEventKind::Loaded => ((4, 0), None),
+            TrackingEventKind::Mute => ((2, 0), None),
+            TrackingEventKind::Unmute => ((2, 0), None),
+            TrackingEventKind::Pause => ((2, 0), None),
+            TrackingEventKind::Resume => ((2, 0), None),
+            TrackingEventKind::Rewind => ((2, 0), None),
+            TrackingEventKind::Skip => ((3, 0), None),
+            TrackingEventKind::Fullscreen => ((2, 0), Some((4, 0))),
+            TrackingEventKind::ExitFullscreen => ((3, 0), Some((4, 0))),
+            TrackingEventKind::PlayerExpand => ((4, 0), None),
+            TrackingEventKind::PlayerCollapse => ((4, 0), None),
+            TrackingEventKind::Expand => ((2, 0), Some((4, 0))),
+            TrackingEventKind::Collapse => ((2, 0), Some((4, 0))),
+            TrackingEventKind::AdExpand => ((3, 0), None),
+            TrackingEventKind::AdCollapse => ((3, 0), None),
+            TrackingEventKind::Minimize => ((3, 0), None),
+            TrackingEventKind::Close => ((2, 0), None),
+            TrackingEventKind::CloseLinear => ((3, 0), None),
+            TrackingEventKind::OverlayViewDuration => ((3, 0), None),
+            TrackingEventKind::AcceptInvitation => ((2, 0), None),
+            TrackingEventKind::AcceptInvitationLinear => ((3, 0), Some((4, 0))),
+            TrackingEventKind::OtherAdInteraction => ((4, 0), None),
+            TrackingEventKind::InteractiveStart => ((4, 0), None),
+            TrackingEventKind::NotUsed => ((4, 0), None),
+            TrackingEventKind::VerificationNotExecuted => ((4, 1), None),
+            TrackingEventKind::Unknown(_) => return None,
+        };
+        Some(versions)
+    }
+    
+    /// Whether the event is defined by the given VAST version (e.g., "3.0")
+    pub fn is_valid_in(&self, version: &str) -> bool {
+        let mut parts = version.trim().split('.');
+        let major = parts.next().and_then(|part| part.parse().ok());
+        let minor = parts.next().map_or(Some(0), |part| part.parse().ok());
+        let (Some(major), Some(minor), Some((since, until))) = (major, minor, self.versions()) else {
+            return false;
+        };
+        
+        since <= (major, minor) && until.is_none_or(|until| (major, minor) < until)
+    }
+}
+
+/// A VAST version as (major, minor)
+type VersionNumber = (u32, u32);
+
+impl From<&str> for TrackingEventKind {
+    fn from(name: &str) -> Self {
+        TrackingEventKind::ALL.iter()
+            .find(|kind| kind.as_str() == name)
+            .cloned()
+            .unwrap_or_else(|| TrackingEventKind::Unknown(name.to_string()))
+    }
+}
+
+impl From<String> for TrackingEventKind {
+    fn from(name: String) -> Self {
+        TrackingEventKind::from(name.as_str())
+    }
+}
+
+impl From<TrackingEventKind> for String {
+    fn from(kind: TrackingEventKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for TrackingEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Represents companion ads
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct CompanionAds {
+    /// Which companions the player must display ("all", "any" or "none")
+    pub required: Option<String>,
+    
     /// The companion ads
     pub companions: Vec<Companion>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a companion ad
@@ -242,17 +891,70 @@ pub struct Companion {
     /// The companion height
     pub height: u32,
     
-    /// The companion asset type (StaticResource, IFrameResource, or HTMLResource)
-    pub resource_type: String,
+    /// The width of the creative asset
+    pub asset_width: Option<u32>,
+    
+    /// The height of the creative asset
+    pub asset_height: Option<u32>,
+    
+    /// The ad slot ID used by the publisher page
+    pub ad_slot_id: Option<String>,
+    
+    /// The companion API framework
+    pub api_framework: Option<String>,
     
-    /// The companion resource URL or HTML content
-    pub resource: String,
+    /// The companion resources (StaticResource, IFrameResource and/or HTMLResource)
+    pub resources: Vec<Resource>,
     
     /// The companion click-through URL
     pub click_through: Option<String>,
     
+    /// Companion click tracking URLs
+    pub click_tracking: Vec<String>,
+    
+    /// Alternative text for the companion
+    pub alt_text: Option<String>,
+    
+    /// Ad parameters passed to the companion
+    pub ad_parameters: Option<AdParameters>,
+    
     /// Companion tracking events
     pub tracking_events: Vec<TrackingEvent>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents a creative resource (StaticResource, IFrameResource or HTMLResource)
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Resource {
+    /// The resource type (StaticResource, IFrameResource, or HTMLResource)
+    pub resource_type: String,
+    
+    /// The MIME type of a StaticResource (e.g., "image/png")
+    pub creative_type: Option<String>,
+    
+    /// The resource URL or HTML content
+    pub content: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// Represents ad parameters passed to a creative
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct AdParameters {
+    /// Whether the parameters are XML-encoded
+    pub xml_encoded: Option<bool>,
+    
+    /// The parameter data
+    pub content: String,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents non-linear ads
@@ -260,6 +962,13 @@ pub struct Companion {
 pub struct NonLinearAds {
     /// The non-linear ads
     pub non_linears: Vec<NonLinear>,
+    
+    /// Tracking events shared by the non-linear ads
+    pub tracking_events: Vec<TrackingEvent>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
 }
 
 /// Represents a non-linear ad
@@ -274,11 +983,11 @@ pub struct NonLinear {
     /// The non-linear height
     pub height: u32,
     
-    /// The non-linear expandable width
-    pub expand_width: Option<u32>,
+    /// The width of the non-linear when expanded
+    pub expanded_width: Option<u32>,
     
-    /// The non-linear expandable height
-    pub expand_height: Option<u32>,
+    /// The height of the non-linear when expanded
+    pub expanded_height: Option<u32>,
     
     /// The non-linear scalable flag
     pub scalable: Option<bool>,
@@ -286,12 +995,117 @@ pub struct NonLinear {
     /// The non-linear maintain aspect ratio flag
     pub maintain_aspect_ratio: Option<bool>,
     
-    /// The non-linear asset type (StaticResource, IFrameResource, or HTMLResource)
-    pub resource_type: String,
+    /// The suggested minimum display duration
+    pub min_suggested_duration: Option<VastDuration>,
+    
+    /// The non-linear API framework
+    pub api_framework: Option<String>,
     
-    /// The non-linear resource URL or HTML content
-    pub resource: String,
+    /// The non-linear resources (StaticResource, IFrameResource and/or HTMLResource)
+    pub resources: Vec<Resource>,
     
     /// The non-linear click-through URL
     pub click_through: Option<String>,
-} 
\ No newline at end of file
+    
+    /// Non-linear click tracking URLs
+    pub click_tracking: Vec<String>,
+    
+    /// Ad parameters passed to the non-linear creative
+    pub ad_parameters: Option<AdParameters>,
+    
+    /// Industry icons (e.g., AdChoices)
+    pub icons: Vec<Icon>,
+    
+    /// Unrecognized attributes and child elements, kept when parsing in lossless mode
+    #[serde(default, skip_serializing_if = "UnknownXml::is_empty")]
+    pub unknown: UnknownXml,
+}
+
+/// XML the parser doesn't model, kept as raw fragments so it can be written back out
+/// 
+/// Only filled in when parsing with `ParseOptions::with_lossless`. The serializer writes
+/// the attributes after the modeled ones and the elements after the modeled children.
+/// Unrecognized children of list elements such as `<MediaFiles>` or `<TrackingEvents>`
+/// are kept by the model owning the list, under the list's name, and written back inside
+/// the list at the place they were read from.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct UnknownXml {
+    /// Unrecognized attributes as (name, unescaped value) pairs
+    pub attributes: Vec<(String, String)>,
+    
+    /// Unrecognized child elements, each as a raw XML fragment
+    pub elements: Vec<String>,
+    
+    /// Unrecognized children of list elements, keyed by the list element's name
+    /// 
+    /// Each fragment is paired with the number of modeled children that came before it in
+    /// the list.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lists: BTreeMap<String, Vec<(usize, String)>>,
+}
+
+impl UnknownXml {
+    /// Whether nothing unrecognized was kept
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty() && self.lists.is_empty()
+    }
+    
+    /// The unrecognized children kept for a list element, with the number of modeled
+    /// children before each
+    pub fn list_elements(&self, list: &str) -> &[(usize, String)] {
+        self.lists.get(list).map_or(&[], Vec::as_slice)
+    }
+    
+    /// Keep an unrecognized child of a list element that came after `index` modeled children
+    pub fn push_list_element(&mut self, list: &str, index: usize, fragment: String) {
+        self.lists.entry(list.to_string()).or_default().push((index, fragment));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn tracking_event_versions_follow_the_spec_tables() {
+        use TrackingEventKind::*;
+        
+        // (kind, valid in 2.0, valid in 3.0, valid in 4.x)
+        let cases = [
+            (CreativeView, true, true, true),
+            (Start, true, true, true),
+            (Complete, true, true, true),
+            (Progress, false, true, true),
+            (Loaded, false, false, true),
+            (Skip, false, true, true),
+            (Fullscreen, true, true, false),
+            (ExitFullscreen, false, true, false),
+            (Expand, true, true, false),
+            (Collapse, true, true, false),
+            (PlayerExpand, false, false, true),
+            (PlayerCollapse, false, false, true),
+            (AdExpand, false, true, true),
+            (AdCollapse, false, true, true),
+            (Minimize, false, true, true),
+            (Close, true, true, true),
+            (CloseLinear, false, true, true),
+            (AcceptInvitation, true, true, true),
+            (AcceptInvitationLinear, false, true, false),
+            (OtherAdInteraction, false, false, true),
+            (Unknown("acme".to_string()), false, false, false),
+        ];
+        
+        for (kind, in_2, in_3, in_4) in cases {
+            assert_eq!(kind.is_valid_in("2.0"), in_2, "{} in VAST 2.0", kind);
+            assert_eq!(kind.is_valid_in("3.0"), in_3, "{} in VAST 3.0", kind);
+            for version in ["4.0", "4.1", "4.2", "4.3"] {
+                assert_eq!(kind.is_valid_in(version), in_4, "{} in VAST {}", kind, version);
+            }
+        }
+        
+        assert!(!VerificationNotExecuted.is_valid_in("4.0"));
+        assert!(VerificationNotExecuted.is_valid_in("4.1"));
+        assert!(Expand.is_valid_in("3"));
+        assert!(!Expand.is_valid_in("three"));
+    }
+}
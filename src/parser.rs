@@ -1,19 +1,183 @@
 use crate::error::{Result, VastError};
 use crate::models::*;
+use crate::time::TimeParseError;
+use log::warn;
+use quick_xml::events::attributes::Attribute;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
-use std::str::from_utf8;
+use std::ops::{Deref, DerefMut};
+use std::str::{from_utf8, FromStr};
+
+/// Options controlling how VAST XML is parsed
+/// 
+/// ```
+/// use vast_parser::parser::{self, ParseOptions};
+/// 
+/// // Keep vendor-specific elements and attributes so they survive serialization
+/// let options = ParseOptions::new().with_lossless(true);
+/// let xml = r#"<VAST version="4.0" xmlns:acme="urn:acme"><acme:Floor>1.50</acme:Floor></VAST>"#;
+/// let vast = parser::parse_vast_with_options(xml, &options).unwrap();
+/// 
+/// assert_eq!(vast.unknown.attributes, vec![("xmlns:acme".to_string(), "urn:acme".to_string())]);
+/// assert_eq!(vast.unknown.elements, vec!["<acme:Floor>1.50</acme:Floor>".to_string()]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Keep elements and attributes the parser doesn't model as raw XML
+    pub lossless: bool,
+    
+    /// Fail on malformed time and other values instead of leaving the field unset with a warning
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    /// Options with lossless and strict mode off
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set whether to keep elements and attributes the parser doesn't model
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+    
+    /// Set whether malformed time and other values fail the parse
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// XML reader over a VAST document that knows the parse options
+struct VastReader<'a> {
+    reader: Reader<&'a [u8]>,
+    
+    /// The document being read, for copying out raw fragments
+    source: &'a str,
+    
+    /// Whether unrecognized XML is kept
+    lossless: bool,
+    
+    /// Whether malformed values fail the parse
+    strict: bool,
+    
+    /// Malformed values that were dropped
+    warnings: Vec<VastError>,
+}
+
+impl<'a> VastReader<'a> {
+    fn new(source: &'a str, options: &ParseOptions) -> Self {
+        let mut reader = Reader::from_str(source);
+        reader.trim_text(true);
+        VastReader {
+            reader,
+            source,
+            lossless: options.lossless,
+            strict: options.strict,
+            warnings: Vec::new(),
+        }
+    }
+    
+    /// The line and column the reader has reached, both starting at 1
+    fn line_and_column(&self) -> (usize, usize) {
+        let position = self.reader.buffer_position().min(self.source.len());
+        let read = self.source.get(..position).unwrap_or(self.source);
+        let line_start = read.rfind('\n').map_or(0, |index| index + 1);
+        (read.matches('\n').count() + 1, read[line_start..].chars().count() + 1)
+    }
+    
+    /// Report a malformed time value: an error in strict mode, otherwise a warning
+    fn invalid_time(&mut self, location: &str, source: TimeParseError) -> Result<()> {
+        let (line, column) = self.line_and_column();
+        self.malformed(VastError::InvalidTime {
+            location: location.to_string(),
+            line,
+            column,
+            source,
+        })
+    }
+    
+    /// Report a malformed value that isn't a time: an error in strict mode, otherwise a warning
+    fn invalid_value(&mut self, location: &str, message: String) -> Result<()> {
+        let (line, column) = self.line_and_column();
+        self.malformed(VastError::InvalidValue {
+            location: location.to_string(),
+            line,
+            column,
+            message,
+        })
+    }
+    
+    /// Fail with a malformed value error in strict mode, otherwise log and keep it as a warning
+    fn malformed(&mut self, error: VastError) -> Result<()> {
+        if self.strict {
+            return Err(error);
+        }
+        warn!("{}, leaving it unset", error);
+        self.warnings.push(error);
+        Ok(())
+    }
+}
+
+impl<'a> Deref for VastReader<'a> {
+    type Target = Reader<&'a [u8]>;
+    
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl DerefMut for VastReader<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+/// Parse a VAST time value, reporting where in the document a malformed one was found
+/// 
+/// A malformed value fails the parse in strict mode; otherwise it is None.
+fn parse_time<T: FromStr<Err = TimeParseError>>(reader: &mut VastReader<'_>, value: &str, location: &str) -> Result<Option<T>> {
+    match value.parse() {
+        Ok(time) => Ok(Some(time)),
+        Err(source) => reader.invalid_time(location, source).map(|()| None),
+    }
+}
 
 /// Parse a VAST XML string into a Vast struct
 pub fn parse_vast(xml: &str) -> Result<Vast> {
-    let mut reader = Reader::from_str(xml);
-    reader.trim_text(true);
+    parse_vast_with_options(xml, &ParseOptions::default())
+}
+
+/// Parse a VAST XML string like `parse_vast`, with the given options
+/// 
+/// Malformed time and other values are left unset and logged, unless the options are strict.
+pub fn parse_vast_with_options(xml: &str, options: &ParseOptions) -> Result<Vast> {
+    parse_vast_with_warnings(xml, options).map(|(vast, _)| vast)
+}
+
+/// Parse a VAST XML string like `parse_vast_with_options`, also returning the malformed
+/// values that were left unset
+/// 
+/// ```
+/// use vast_parser::error::VastError;
+/// use vast_parser::parser::{self, ParseOptions};
+/// 
+/// let xml = r#"<VAST version="4.0"><Ad><InLine><Expires>soon</Expires></InLine></Ad></VAST>"#;
+/// let (vast, warnings) = parser::parse_vast_with_warnings(xml, &ParseOptions::new()).unwrap();
+/// 
+/// assert_eq!(vast.ads[0].inline.as_ref().unwrap().expires, None);
+/// assert!(matches!(&warnings[0], VastError::InvalidValue { location, .. } if location == "InLine/Expires"));
+/// ```
+pub fn parse_vast_with_warnings(xml: &str, options: &ParseOptions) -> Result<(Vast, Vec<VastError>)> {
+    let mut reader = VastReader::new(xml, options);
     
     let mut buf = Vec::new();
     let mut vast = Vast {
         version: String::new(),
         ads: Vec::new(),
         error: None,
+        unknown: UnknownXml::default(),
     };
     
     // Look for the VAST element
@@ -21,13 +185,14 @@ pub fn parse_vast(xml: &str) -> Result<Vast> {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) if e.name().as_ref() == b"VAST" => {
                 // Extract version from attributes
-                for attr in e.attributes() {
-                    if let Ok(attr) = attr {
-                        if attr.key.as_ref() == b"version" {
-                            if let Ok(value) = from_utf8(&attr.value) {
+                for attr in e.attributes().flatten() {
+                    match attr.key.as_ref() {
+                        b"version" => {
+                            if let Ok(value) = attr.unescape_value() {
                                 vast.version = value.to_string();
                             }
-                        }
+                        },
+                        _ => keep_unknown_attribute(&reader, &attr, &mut vast.unknown),
                     }
                 }
                 
@@ -37,7 +202,7 @@ pub fn parse_vast(xml: &str) -> Result<Vast> {
                 }
                 
                 // Parse Ad elements
-                vast.ads = parse_ads(&mut reader)?;
+                vast.ads = parse_ads(&mut reader, &mut vast.unknown)?;
                 break;
             }
             Ok(Event::Eof) => break,
@@ -47,11 +212,11 @@ pub fn parse_vast(xml: &str) -> Result<Vast> {
         buf.clear();
     }
     
-    Ok(vast)
+    Ok((vast, reader.warnings))
 }
 
 /// Parse Ad elements from the VAST XML
-fn parse_ads(reader: &mut Reader<&[u8]>) -> Result<Vec<Ad>> {
+fn parse_ads(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Ad>> {
     let mut ads = Vec::new();
     let mut buf = Vec::new();
     
@@ -62,6 +227,8 @@ fn parse_ads(reader: &mut Reader<&[u8]>) -> Result<Vec<Ad>> {
                 let ad = parse_ad_element(reader, e)?;
                 ads.push(ad);
             }
+            Ok(Event::Start(ref e)) => skip_unknown_element(reader, e, unknown)?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, unknown),
             Ok(Event::End(ref e)) if e.name().as_ref() == b"VAST" => break,
             Ok(Event::Eof) => break,
             Err(e) => return Err(VastError::XmlParseError(e)),
@@ -74,38 +241,36 @@ fn parse_ads(reader: &mut Reader<&[u8]>) -> Result<Vec<Ad>> {
 }
 
 /// Parse a single Ad element
-fn parse_ad_element(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Ad> {
+fn parse_ad_element(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Ad> {
     let mut ad = Ad {
         id: None,
         sequence: None,
         conditional_ad: None,
         inline: None,
         wrapper: None,
+        unknown: UnknownXml::default(),
     };
     
     // Extract attributes
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            match attr.key.as_ref() {
-                b"id" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        ad.id = Some(value.to_string());
-                    }
-                },
-                b"sequence" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        if let Ok(seq) = value.parse::<u32>() {
-                            ad.sequence = Some(seq);
-                        }
-                    }
-                },
-                b"conditionalAd" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        ad.conditional_ad = Some(value.to_lowercase() == "true");
-                    }
-                },
-                _ => (),
-            }
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    ad.id = Some(value.to_string());
+                }
+            },
+            b"sequence" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(seq) = value.parse::<u32>() {
+                    ad.sequence = Some(seq);
+                }
+            },
+            b"conditionalAd" => {
+                if let Ok(value) = attr.unescape_value() {
+                    ad.conditional_ad = Some(value.to_lowercase() == "true");
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut ad.unknown),
         }
     }
     
@@ -117,17 +282,18 @@ fn parse_ad_element(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Ad
             Ok(Event::Start(ref e)) => {
                 match e.name().as_ref() {
                     b"InLine" => {
-                        ad.inline = Some(parse_inline_element(reader)?);
+                        ad.inline = Some(parse_inline_element(reader, e)?);
                     },
                     b"Wrapper" => {
-                        ad.wrapper = Some(parse_wrapper_element(reader)?);
+                        ad.wrapper = Some(parse_wrapper_element(reader, e)?);
                     },
                     _ => {
                         // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
+                        skip_unknown_element(reader, e, &mut ad.unknown)?;
                     }
                 }
             },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut ad.unknown),
             Ok(Event::End(ref e)) if e.name().as_ref() == b"Ad" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
@@ -142,23 +308,35 @@ fn parse_ad_element(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Ad
 }
 
 /// Parse an InLine element
-fn parse_inline_element(reader: &mut Reader<&[u8]>) -> Result<InLine> {
+fn parse_inline_element(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<InLine> {
     let mut inline = InLine {
         ad_system: AdSystem {
             name: String::new(),
             version: None,
+            unknown: UnknownXml::default(),
         },
         ad_title: String::new(),
+        ad_serving_id: None,
         impressions: Vec::new(),
+        viewable_impression: None,
+        categories: Vec::new(),
         description: None,
         advertiser: None,
         survey: None,
-        error: None,
+        expires: None,
+        error: Vec::new(),
         pricing: None,
+        ad_verifications: Vec::new(),
         extensions: Vec::new(),
         creatives: Vec::new(),
+        unknown: UnknownXml::default(),
     };
     
+    // InLine has no attributes of its own
+    for attr in start.attributes().flatten() {
+        keep_unknown_attribute(reader, &attr, &mut inline.unknown);
+    }
+    
     let mut buf = Vec::new();
     
     loop {
@@ -171,10 +349,19 @@ fn parse_inline_element(reader: &mut Reader<&[u8]>) -> Result<InLine> {
                     b"AdTitle" => {
                         inline.ad_title = read_text_element(reader)?;
                     },
+                    b"AdServingId" => {
+                        inline.ad_serving_id = Some(read_text_element(reader)?);
+                    },
+                    b"Category" => {
+                        inline.categories.push(parse_category(reader, e)?);
+                    },
                     b"Impression" => {
                         let impression = parse_impression(reader, e)?;
                         inline.impressions.push(impression);
                     },
+                    b"ViewableImpression" => {
+                        inline.viewable_impression = Some(parse_viewable_impression(reader, e)?);
+                    },
                     b"Description" => {
                         inline.description = Some(read_text_element(reader)?);
                     },
@@ -184,24 +371,38 @@ fn parse_inline_element(reader: &mut Reader<&[u8]>) -> Result<InLine> {
                     b"Survey" => {
                         inline.survey = Some(read_text_element(reader)?);
                     },
+                    b"Expires" => {
+                        let value = read_text_element(reader)?;
+                        match value.trim().parse::<u64>() {
+                            Ok(expires) => inline.expires = Some(expires),
+                            Err(_) => reader.invalid_value(
+                                "InLine/Expires",
+                                format!("expected a number of seconds, found {:?}", value),
+                            )?,
+                        }
+                    },
                     b"Error" => {
-                        inline.error = Some(read_text_element(reader)?);
+                        inline.error.push(read_text_element(reader)?);
                     },
                     b"Pricing" => {
                         inline.pricing = Some(parse_pricing(reader, e)?);
                     },
+                    b"AdVerifications" => {
+                        inline.ad_verifications.extend(parse_ad_verifications(reader, &mut inline.unknown)?);
+                    },
                     b"Extensions" => {
-                        inline.extensions = parse_extensions(reader)?;
+                        inline.extensions = parse_extensions(reader, &mut inline.unknown)?;
                     },
                     b"Creatives" => {
-                        inline.creatives = parse_creatives(reader)?;
+                        inline.creatives = parse_creatives(reader, &mut inline.unknown)?;
                     },
                     _ => {
                         // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
+                        skip_unknown_element(reader, e, &mut inline.unknown)?;
                     }
                 }
             },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut inline.unknown),
             Ok(Event::End(ref e)) if e.name().as_ref() == b"InLine" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
@@ -216,19 +417,49 @@ fn parse_inline_element(reader: &mut Reader<&[u8]>) -> Result<InLine> {
 }
 
 /// Parse a Wrapper element
-fn parse_wrapper_element(reader: &mut Reader<&[u8]>) -> Result<Wrapper> {
+fn parse_wrapper_element(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Wrapper> {
     let mut wrapper = Wrapper {
+        follow_additional_wrappers: None,
+        allow_multiple_ads: None,
+        fallback_on_no_ad: None,
         ad_system: AdSystem {
             name: String::new(),
             version: None,
+            unknown: UnknownXml::default(),
         },
         vast_ad_tag_uri: String::new(),
         impressions: Vec::new(),
-        error: None,
+        viewable_impression: None,
+        error: Vec::new(),
+        blocked_ad_categories: Vec::new(),
+        ad_verifications: Vec::new(),
         extensions: Vec::new(),
         creatives: Vec::new(),
+        unknown: UnknownXml::default(),
     };
     
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"followAdditionalWrappers" => {
+                if let Ok(value) = attr.unescape_value() {
+                    wrapper.follow_additional_wrappers = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"allowMultipleAds" => {
+                if let Ok(value) = attr.unescape_value() {
+                    wrapper.allow_multiple_ads = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"fallbackOnNoAd" => {
+                if let Ok(value) = attr.unescape_value() {
+                    wrapper.fallback_on_no_ad = Some(value.to_lowercase() == "true");
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut wrapper.unknown),
+        }
+    }
+    
     let mut buf = Vec::new();
     
     loop {
@@ -245,21 +476,31 @@ fn parse_wrapper_element(reader: &mut Reader<&[u8]>) -> Result<Wrapper> {
                         let impression = parse_impression(reader, e)?;
                         wrapper.impressions.push(impression);
                     },
+                    b"ViewableImpression" => {
+                        wrapper.viewable_impression = Some(parse_viewable_impression(reader, e)?);
+                    },
                     b"Error" => {
-                        wrapper.error = Some(read_text_element(reader)?);
+                        wrapper.error.push(read_text_element(reader)?);
+                    },
+                    b"BlockedAdCategories" => {
+                        wrapper.blocked_ad_categories.push(parse_category(reader, e)?);
+                    },
+                    b"AdVerifications" => {
+                        wrapper.ad_verifications.extend(parse_ad_verifications(reader, &mut wrapper.unknown)?);
                     },
                     b"Extensions" => {
-                        wrapper.extensions = parse_extensions(reader)?;
+                        wrapper.extensions = parse_extensions(reader, &mut wrapper.unknown)?;
                     },
                     b"Creatives" => {
-                        wrapper.creatives = parse_creatives(reader)?;
+                        wrapper.creatives = parse_creatives(reader, &mut wrapper.unknown)?;
                     },
                     _ => {
                         // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
+                        skip_unknown_element(reader, e, &mut wrapper.unknown)?;
                     }
                 }
             },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut wrapper.unknown),
             Ok(Event::End(ref e)) if e.name().as_ref() == b"Wrapper" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
@@ -274,18 +515,19 @@ fn parse_wrapper_element(reader: &mut Reader<&[u8]>) -> Result<Wrapper> {
 }
 
 /// Helper function to read the text content of an XML element
-fn read_text_element(reader: &mut Reader<&[u8]>) -> Result<String> {
+fn read_text_element(reader: &mut VastReader<'_>) -> Result<String> {
     let mut text = String::new();
     let mut buf = Vec::new();
     
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Text(e)) => {
-                text = e.unescape()?.into_owned();
+                text.push_str(&e.unescape()?);
             },
             Ok(Event::CData(e)) => {
+                // A value may be split over several CDATA sections (e.g. around "]]>")
                 if let Ok(value) = from_utf8(&e) {
-                    text = value.to_string();
+                    text.push_str(value);
                 }
             },
             Ok(Event::End(_)) => break,
@@ -302,33 +544,15 @@ fn read_text_element(reader: &mut Reader<&[u8]>) -> Result<String> {
 }
 
 /// Helper function to skip an XML element and all its children
-fn skip_element(reader: &mut Reader<&[u8]>, name: &[u8]) -> Result<()> {
+fn skip_element(reader: &mut VastReader<'_>, name: &[u8]) -> Result<()> {
     let mut buf = Vec::new();
     let mut depth = 0;
     
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                if e.name().as_ref() == name && depth == 0 {
-                    depth += 1;
-                } else if depth > 0 {
-                    depth += 1;
-                }
-            },
-            Ok(Event::End(ref e)) => {
-                if e.name().as_ref() == name {
-                    if depth == 0 {
-                        break;
-                    } else {
-                        depth -= 1;
-                        if depth == 0 {
-                            break;
-                        }
-                    }
-                } else if depth > 0 {
-                    depth -= 1;
-                }
-            },
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::End(ref e)) if depth == 0 && e.name().as_ref() == name => break,
+            Ok(Event::End(_)) => depth -= 1,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -341,21 +565,92 @@ fn skip_element(reader: &mut Reader<&[u8]>, name: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Skip an unrecognized element, keeping it as a raw XML fragment in lossless mode
+fn skip_unknown_element(reader: &mut VastReader<'_>, start: &BytesStart, unknown: &mut UnknownXml) -> Result<()> {
+    unknown.elements.extend(read_unknown_element(reader, start)?);
+    Ok(())
+}
+
+/// Keep an unrecognized empty element as a raw XML fragment in lossless mode
+fn keep_unknown_empty_element(reader: &VastReader<'_>, element: &BytesStart, unknown: &mut UnknownXml) {
+    unknown.elements.extend(unknown_empty_element(reader, element));
+}
+
+/// Skip an unrecognized child of a list element, keeping it in lossless mode along with
+/// the number of modeled children before it
+fn skip_unknown_list_element(
+    reader: &mut VastReader<'_>,
+    start: &BytesStart,
+    unknown: &mut UnknownXml,
+    list: &str,
+    index: usize,
+) -> Result<()> {
+    if let Some(fragment) = read_unknown_element(reader, start)? {
+        unknown.push_list_element(list, index, fragment);
+    }
+    Ok(())
+}
+
+/// Keep an unrecognized empty child of a list element in lossless mode along with the
+/// number of modeled children before it
+fn keep_unknown_empty_list_element(
+    reader: &VastReader<'_>,
+    element: &BytesStart,
+    unknown: &mut UnknownXml,
+    list: &str,
+    index: usize,
+) {
+    if let Some(fragment) = unknown_empty_element(reader, element) {
+        unknown.push_list_element(list, index, fragment);
+    }
+}
+
+/// Read past an unrecognized element, returning it as a raw XML fragment in lossless mode
+fn read_unknown_element(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Option<String>> {
+    if !reader.lossless {
+        skip_element(reader, start.name().as_ref())?;
+        return Ok(None);
+    }
+    
+    let span = reader.read_to_end(start.name())?;
+    Ok(Some(format!(
+        "<{}>{}</{}>",
+        String::from_utf8_lossy(start),
+        &reader.source[span],
+        String::from_utf8_lossy(start.name().as_ref()),
+    )))
+}
+
+/// An unrecognized empty element as a raw XML fragment, in lossless mode
+fn unknown_empty_element(reader: &VastReader<'_>, element: &BytesStart) -> Option<String> {
+    reader.lossless.then(|| format!("<{}/>", String::from_utf8_lossy(element)))
+}
+
+/// Keep an unrecognized attribute in lossless mode
+fn keep_unknown_attribute(reader: &VastReader<'_>, attr: &Attribute, unknown: &mut UnknownXml) {
+    if reader.lossless
+        && let Ok(value) = attr.unescape_value() {
+        unknown.attributes.push((String::from_utf8_lossy(attr.key.as_ref()).into_owned(), value.into_owned()));
+    }
+}
+
 /// Parse AdSystem element
-fn parse_ad_system(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<AdSystem> {
+fn parse_ad_system(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<AdSystem> {
     let mut ad_system = AdSystem {
         name: String::new(),
         version: None,
+        unknown: UnknownXml::default(),
     };
     
     // Extract version attribute
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            if attr.key.as_ref() == b"version" {
-                if let Ok(value) = from_utf8(&attr.value) {
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"version" => {
+                if let Ok(value) = attr.unescape_value() {
                     ad_system.version = Some(value.to_string());
                 }
-            }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut ad_system.unknown),
         }
     }
     
@@ -366,20 +661,22 @@ fn parse_ad_system(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<AdS
 }
 
 /// Parse Impression element
-fn parse_impression(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Impression> {
+fn parse_impression(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Impression> {
     let mut impression = Impression {
         id: None,
         url: String::new(),
+        unknown: UnknownXml::default(),
     };
     
     // Extract id attribute
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            if attr.key.as_ref() == b"id" {
-                if let Ok(value) = from_utf8(&attr.value) {
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
                     impression.id = Some(value.to_string());
                 }
-            }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut impression.unknown),
         }
     }
     
@@ -389,30 +686,77 @@ fn parse_impression(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Im
     Ok(impression)
 }
 
+/// Parse ViewableImpression element
+fn parse_viewable_impression(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<ViewableImpression> {
+    let mut viewable_impression = ViewableImpression::default();
+    
+    // Extract id attribute
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    viewable_impression.id = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut viewable_impression.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"Viewable" => {
+                        viewable_impression.viewable.push(read_text_element(reader)?);
+                    },
+                    b"NotViewable" => {
+                        viewable_impression.not_viewable.push(read_text_element(reader)?);
+                    },
+                    b"ViewUndetermined" => {
+                        viewable_impression.view_undetermined.push(read_text_element(reader)?);
+                    },
+                    _ => skip_unknown_element(reader, e, &mut viewable_impression.unknown)?,
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut viewable_impression.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"ViewableImpression" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(viewable_impression)
+}
+
 /// Parse Pricing element
-fn parse_pricing(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Pricing> {
+fn parse_pricing(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Pricing> {
     let mut pricing = Pricing {
         model: String::new(),
         currency: String::new(),
         value: String::new(),
+        unknown: UnknownXml::default(),
     };
     
     // Extract attributes
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            match attr.key.as_ref() {
-                b"model" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        pricing.model = value.to_string();
-                    }
-                },
-                b"currency" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        pricing.currency = value.to_string();
-                    }
-                },
-                _ => (),
-            }
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"model" => {
+                if let Ok(value) = attr.unescape_value() {
+                    pricing.model = value.to_string();
+                }
+            },
+            b"currency" => {
+                if let Ok(value) = attr.unescape_value() {
+                    pricing.currency = value.to_string();
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut pricing.unknown),
         }
     }
     
@@ -422,8 +766,34 @@ fn parse_pricing(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Prici
     Ok(pricing)
 }
 
+/// Parse a Category or BlockedAdCategories element
+fn parse_category(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Category> {
+    let mut category = Category {
+        authority: None,
+        value: String::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"authority" => {
+                if let Ok(value) = attr.unescape_value() {
+                    category.authority = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut category.unknown),
+        }
+    }
+    
+    // Read the Category value
+    category.value = read_text_element(reader)?;
+    
+    Ok(category)
+}
+
 /// Parse Extensions element
-fn parse_extensions(reader: &mut Reader<&[u8]>) -> Result<Vec<Extension>> {
+fn parse_extensions(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Extension>> {
     let mut extensions = Vec::new();
     let mut buf = Vec::new();
     
@@ -433,6 +803,11 @@ fn parse_extensions(reader: &mut Reader<&[u8]>) -> Result<Vec<Extension>> {
                 let extension = parse_extension(reader, e)?;
                 extensions.push(extension);
             },
+            Ok(Event::Empty(ref e)) if e.name().as_ref() == b"Extension" => {
+                extensions.push(extension_from_start(e));
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "Extensions", extensions.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "Extensions", extensions.len()),
             Ok(Event::End(ref e)) if e.name().as_ref() == b"Extensions" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
@@ -446,43 +821,26 @@ fn parse_extensions(reader: &mut Reader<&[u8]>) -> Result<Vec<Extension>> {
     Ok(extensions)
 }
 
-/// Parse Extension element
-fn parse_extension(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Extension> {
-    let mut extension = Extension {
-        r#type: None,
-        content: String::new(),
-    };
-    
-    // Extract type attribute
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            if attr.key.as_ref() == b"type" {
-                if let Ok(value) = from_utf8(&attr.value) {
-                    extension.r#type = Some(value.to_string());
-                }
-            }
-        }
-    }
-    
-    // For simplicity, we're just capturing the text content
-    // In a real implementation, we might want to capture the entire XML subtree
-    extension.content = read_text_element(reader)?;
-    
-    Ok(extension)
-}
-
-/// Parse Creatives element
-fn parse_creatives(reader: &mut Reader<&[u8]>) -> Result<Vec<Creative>> {
-    let mut creatives = Vec::new();
+/// Parse the verifications inside an `<Extension type="AdVerifications">` element
+fn parse_ad_verifications_extension(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Verification>> {
+    let mut verifications = Vec::new();
     let mut buf = Vec::new();
     
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Creative" => {
-                let creative = parse_creative(reader, e)?;
-                creatives.push(creative);
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"AdVerifications" => {
+                        verifications.extend(parse_ad_verifications(reader, unknown)?);
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, unknown)?;
+                    }
+                }
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"Creatives" => break,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Extension" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -492,75 +850,23 @@ fn parse_creatives(reader: &mut Reader<&[u8]>) -> Result<Vec<Creative>> {
         buf.clear();
     }
     
-    Ok(creatives)
+    Ok(verifications)
 }
 
-/// Parse Creative element
-fn parse_creative(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Creative> {
-    let mut creative = Creative {
-        id: None,
-        sequence: None,
-        ad_id: None,
-        api_framework: None,
-        linear: None,
-        companion_ads: None,
-        non_linear_ads: None,
-    };
-    
-    // Extract attributes
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            match attr.key.as_ref() {
-                b"id" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        creative.id = Some(value.to_string());
-                    }
-                },
-                b"sequence" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        if let Ok(seq) = value.parse::<u32>() {
-                            creative.sequence = Some(seq);
-                        }
-                    }
-                },
-                b"adId" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        creative.ad_id = Some(value.to_string());
-                    }
-                },
-                b"apiFramework" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        creative.api_framework = Some(value.to_string());
-                    }
-                },
-                _ => (),
-            }
-        }
-    }
-    
+/// Parse AdVerifications element
+fn parse_ad_verifications(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Verification>> {
+    let mut verifications = Vec::new();
     let mut buf = Vec::new();
     
-    // Parse Linear, CompanionAds, or NonLinearAds
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                match e.name().as_ref() {
-                    b"Linear" => {
-                        creative.linear = Some(parse_linear(reader)?);
-                    },
-                    b"CompanionAds" => {
-                        creative.companion_ads = Some(parse_companion_ads(reader)?);
-                    },
-                    b"NonLinearAds" => {
-                        creative.non_linear_ads = Some(parse_non_linear_ads(reader)?);
-                    },
-                    _ => {
-                        // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
-                    }
-                }
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Verification" => {
+                let verification = parse_verification(reader, e)?;
+                verifications.push(verification);
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"Creative" => break,
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "AdVerifications", verifications.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "AdVerifications", verifications.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"AdVerifications" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -570,43 +876,60 @@ fn parse_creative(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Crea
         buf.clear();
     }
     
-    Ok(creative)
+    Ok(verifications)
 }
 
-/// Parse Linear element
-fn parse_linear(reader: &mut Reader<&[u8]>) -> Result<Linear> {
-    let mut linear = Linear {
-        duration: None,
-        media_files: Vec::new(),
-        video_clicks: None,
+/// Parse Verification element
+fn parse_verification(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Verification> {
+    let mut verification = Verification {
+        vendor: None,
+        javascript_resources: Vec::new(),
+        executable_resources: Vec::new(),
+        verification_parameters: None,
         tracking_events: Vec::new(),
+        unknown: UnknownXml::default(),
     };
     
+    // Extract vendor attribute
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"vendor" => {
+                if let Ok(value) = attr.unescape_value() {
+                    verification.vendor = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut verification.unknown),
+        }
+    }
+    
     let mut buf = Vec::new();
     
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) => {
                 match e.name().as_ref() {
-                    b"Duration" => {
-                        linear.duration = Some(read_text_element(reader)?);
+                    b"JavaScriptResource" => {
+                        let resource = parse_javascript_resource(reader, e)?;
+                        verification.javascript_resources.push(resource);
                     },
-                    b"MediaFiles" => {
-                        linear.media_files = parse_media_files(reader)?;
+                    b"ExecutableResource" => {
+                        let resource = parse_executable_resource(reader, e)?;
+                        verification.executable_resources.push(resource);
                     },
-                    b"VideoClicks" => {
-                        linear.video_clicks = Some(parse_video_clicks(reader)?);
+                    b"VerificationParameters" => {
+                        verification.verification_parameters = Some(read_text_element(reader)?);
                     },
                     b"TrackingEvents" => {
-                        linear.tracking_events = parse_tracking_events(reader)?;
+                        verification.tracking_events = parse_tracking_events(reader, &mut verification.unknown)?;
                     },
                     _ => {
                         // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
+                        skip_unknown_element(reader, e, &mut verification.unknown)?;
                     }
                 }
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"Linear" => break,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut verification.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Verification" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -616,108 +939,1324 @@ fn parse_linear(reader: &mut Reader<&[u8]>) -> Result<Linear> {
         buf.clear();
     }
     
-    Ok(linear)
+    Ok(verification)
 }
 
-/// Parse MediaFiles element
-fn parse_media_files(reader: &mut Reader<&[u8]>) -> Result<Vec<MediaFile>> {
-    let mut media_files = Vec::new();
-    let mut buf = Vec::new();
+/// Parse JavaScriptResource element
+fn parse_javascript_resource(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<JavaScriptResource> {
+    let mut resource = JavaScriptResource {
+        api_framework: None,
+        browser_optional: None,
+        url: String::new(),
+        unknown: UnknownXml::default(),
+    };
     
-    loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) if e.name().as_ref() == b"MediaFile" => {
-                let media_file = parse_media_file(reader, e)?;
-                media_files.push(media_file);
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    resource.api_framework = Some(value.to_string());
+                }
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"MediaFiles" => break,
-            Ok(Event::Eof) => {
-                return Err(VastError::Other("Unexpected end of file".to_string()));
+            b"browserOptional" => {
+                if let Ok(value) = attr.unescape_value() {
+                    resource.browser_optional = Some(value.to_lowercase() == "true");
+                }
             },
-            Err(e) => return Err(VastError::XmlParseError(e)),
-            _ => (),
+            _ => keep_unknown_attribute(reader, &attr, &mut resource.unknown),
         }
-        buf.clear();
     }
     
-    Ok(media_files)
+    // Read the script URL
+    resource.url = read_text_element(reader)?;
+    
+    Ok(resource)
 }
 
-/// Parse MediaFile element
-fn parse_media_file(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<MediaFile> {
-    let mut media_file = MediaFile {
+/// Parse ExecutableResource element
+fn parse_executable_resource(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<ExecutableResource> {
+    let mut resource = ExecutableResource {
+        api_framework: None,
+        r#type: None,
+        url: String::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    resource.api_framework = Some(value.to_string());
+                }
+            },
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    resource.r#type = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut resource.unknown),
+        }
+    }
+    
+    // Read the executable URL
+    resource.url = read_text_element(reader)?;
+    
+    Ok(resource)
+}
+
+/// Parse an Extension or CreativeExtension element, keeping its inner XML
+fn parse_extension(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Extension> {
+    let mut extension = extension_from_start(start);
+    
+    // Capture the entire subtree, including any nested vendor XML
+    let span = reader.read_to_end(start.name())?;
+    extension.content = reader.source[span].trim().to_string();
+    
+    Ok(extension)
+}
+
+/// Read the attributes of an Extension or CreativeExtension element
+fn extension_from_start(start: &BytesStart) -> Extension {
+    let mut extension = Extension {
+        r#type: None,
+        attributes: Vec::new(),
+        content: String::new(),
+    };
+    
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    extension.r#type = Some(value.to_string());
+                }
+            },
+            key => {
+                if let Ok(value) = attr.unescape_value() {
+                    extension.attributes.push((String::from_utf8_lossy(key).into_owned(), value.into_owned()));
+                }
+            },
+        }
+    }
+    
+    extension
+}
+
+/// Parse CreativeExtensions element
+fn parse_creative_extensions(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Extension>> {
+    let mut extensions = Vec::new();
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"CreativeExtension" => {
+                let extension = parse_extension(reader, e)?;
+                extensions.push(extension);
+            },
+            Ok(Event::Empty(ref e)) if e.name().as_ref() == b"CreativeExtension" => {
+                extensions.push(extension_from_start(e));
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "CreativeExtensions", extensions.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "CreativeExtensions", extensions.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"CreativeExtensions" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(extensions)
+}
+
+/// Parse the verifications in the content of an `<Extension type="AdVerifications">`
+pub(crate) fn parse_ad_verifications_extension_content(content: &str) -> Result<Vec<Verification>> {
+    let xml = format!("<Extension>{}</Extension>", content);
+    let mut reader = VastReader::new(&xml, &ParseOptions::default());
+    
+    // Step into the Extension element before reading its children
+    let mut buf = Vec::new();
+    reader.read_event_into(&mut buf)?;
+    
+    parse_ad_verifications_extension(&mut reader, &mut UnknownXml::default())
+}
+
+/// Parse Creatives element
+fn parse_creatives(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Creative>> {
+    let mut creatives = Vec::new();
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Creative" => {
+                let creative = parse_creative(reader, e)?;
+                creatives.push(creative);
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "Creatives", creatives.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "Creatives", creatives.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Creatives" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(creatives)
+}
+
+/// Parse Creative element
+fn parse_creative(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Creative> {
+    let mut creative = Creative {
+        id: None,
+        sequence: None,
+        ad_id: None,
+        api_framework: None,
+        universal_ad_ids: Vec::new(),
+        creative_extensions: Vec::new(),
+        linear: None,
+        companion_ads: None,
+        non_linear_ads: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    creative.id = Some(value.to_string());
+                }
+            },
+            b"sequence" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(seq) = value.parse::<u32>() {
+                    creative.sequence = Some(seq);
+                }
+            },
+            b"adId" => {
+                if let Ok(value) = attr.unescape_value() {
+                    creative.ad_id = Some(value.to_string());
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    creative.api_framework = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut creative.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    // Parse Linear, CompanionAds, or NonLinearAds
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"Linear" => {
+                        creative.linear = Some(parse_linear(reader, e)?);
+                    },
+                    b"CompanionAds" => {
+                        creative.companion_ads = Some(parse_companion_ads(reader, e)?);
+                    },
+                    b"NonLinearAds" => {
+                        creative.non_linear_ads = Some(parse_non_linear_ads(reader, e)?);
+                    },
+                    b"UniversalAdId" => {
+                        creative.universal_ad_ids.push(parse_universal_ad_id(reader, e)?);
+                    },
+                    b"CreativeExtensions" => {
+                        creative.creative_extensions = parse_creative_extensions(reader, &mut creative.unknown)?;
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut creative.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) if e.name().as_ref() == b"UniversalAdId" => {
+                creative.universal_ad_ids.push(universal_ad_id_from_start(reader, e));
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut creative.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Creative" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(creative)
+}
+
+/// Parse UniversalAdId element
+fn parse_universal_ad_id(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<UniversalAdId> {
+    let mut universal_ad_id = universal_ad_id_from_start(reader, start);
+    
+    // VAST 4.1+ carries the identifier in the content
+    let id = read_text_element(reader)?;
+    if !id.is_empty() {
+        universal_ad_id.id = id;
+    }
+    
+    Ok(universal_ad_id)
+}
+
+/// Build a UniversalAdId from the attributes of its start tag
+fn universal_ad_id_from_start(reader: &VastReader<'_>, start: &BytesStart) -> UniversalAdId {
+    let mut universal_ad_id = UniversalAdId {
+        id_registry: String::new(),
+        id: String::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"idRegistry" => {
+                if let Ok(value) = attr.unescape_value() {
+                    universal_ad_id.id_registry = value.to_string();
+                }
+            },
+            // VAST 4.0 carries the identifier in an attribute
+            b"idValue" => {
+                if let Ok(value) = attr.unescape_value() {
+                    universal_ad_id.id = value.to_string();
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut universal_ad_id.unknown),
+        }
+    }
+    
+    universal_ad_id
+}
+
+/// Parse Linear element
+fn parse_linear(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Linear> {
+    let mut linear = Linear {
+        skip_offset: None,
+        duration: None,
+        media_files: Vec::new(),
+        mezzanines: Vec::new(),
+        interactive_creative_files: Vec::new(),
+        closed_caption_files: Vec::new(),
+        video_clicks: None,
+        tracking_events: Vec::new(),
+        icons: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"skipoffset" => {
+                if let Ok(value) = attr.unescape_value() {
+                    linear.skip_offset = parse_time(reader, &value, "Linear@skipoffset")?;
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut linear.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"Duration" => {
+                        let value = read_text_element(reader)?;
+                        linear.duration = parse_time(reader, &value, "Linear/Duration")?;
+                    },
+                    b"MediaFiles" => {
+                        parse_media_files(reader, &mut linear)?;
+                    },
+                    b"VideoClicks" => {
+                        linear.video_clicks = Some(parse_video_clicks(reader)?);
+                    },
+                    b"TrackingEvents" => {
+                        linear.tracking_events = parse_tracking_events(reader, &mut linear.unknown)?;
+                    },
+                    b"Icons" => {
+                        linear.icons = parse_icons(reader, &mut linear.unknown)?;
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut linear.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut linear.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Linear" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(linear)
+}
+
+/// Parse MediaFiles element into the media, mezzanine, interactive and caption files of a Linear
+fn parse_media_files(reader: &mut VastReader<'_>, linear: &mut Linear) -> Result<()> {
+    let mut buf = Vec::new();
+    
+    // The number of modeled children read so far, to place unrecognized ones
+    let mut index = 0;
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"MediaFile" => {
+                        let media_file = parse_media_file(reader, e)?;
+                        linear.media_files.push(media_file);
+                        index += 1;
+                    },
+                    b"Mezzanine" => {
+                        let mezzanine = parse_mezzanine(reader, e)?;
+                        linear.mezzanines.push(mezzanine);
+                        index += 1;
+                    },
+                    b"InteractiveCreativeFile" => {
+                        let interactive_creative_file = parse_interactive_creative_file(reader, e)?;
+                        linear.interactive_creative_files.push(interactive_creative_file);
+                        index += 1;
+                    },
+                    b"ClosedCaptionFiles" => {
+                        linear.closed_caption_files = parse_closed_caption_files(reader, &mut linear.unknown)?;
+                        
+                        // The list is only written back if it holds anything
+                        if !linear.closed_caption_files.is_empty() || !linear.unknown.list_elements("ClosedCaptionFiles").is_empty() {
+                            index += 1;
+                        }
+                    },
+                    _ => skip_unknown_list_element(reader, e, &mut linear.unknown, "MediaFiles", index)?,
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, &mut linear.unknown, "MediaFiles", index),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"MediaFiles" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(())
+}
+
+/// Parse MediaFile element
+fn parse_media_file(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<MediaFile> {
+    let mut media_file = MediaFile {
+        id: None,
         url: String::new(),
         mime_type: String::new(),
         codec: None,
         bitrate: None,
+        min_bitrate: None,
+        max_bitrate: None,
         width: None,
         height: None,
         delivery: None,
         r#type: None,
+        api_framework: None,
+        scalable: None,
+        maintain_aspect_ratio: None,
+        file_size: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.mime_type = value.to_string();
+                }
+            },
+            b"codec" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.codec = Some(value.to_string());
+                }
+            },
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.id = Some(value.to_string());
+                }
+            },
+            b"bitrate" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(bitrate) = value.parse::<u32>() {
+                    media_file.bitrate = Some(bitrate);
+                }
+            },
+            b"minBitrate" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(bitrate) = value.parse::<u32>() {
+                    media_file.min_bitrate = Some(bitrate);
+                }
+            },
+            b"maxBitrate" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(bitrate) = value.parse::<u32>() {
+                    media_file.max_bitrate = Some(bitrate);
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.api_framework = Some(value.to_string());
+                }
+            },
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    media_file.width = Some(width);
+                }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    media_file.height = Some(height);
+                }
+            },
+            b"delivery" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.delivery = Some(value.to_string());
+                }
+            },
+            b"mediaType" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.r#type = Some(value.to_string());
+                }
+            },
+            b"scalable" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.scalable = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"maintainAspectRatio" => {
+                if let Ok(value) = attr.unescape_value() {
+                    media_file.maintain_aspect_ratio = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"fileSize" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(file_size) = value.parse::<u64>() {
+                    media_file.file_size = Some(file_size);
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut media_file.unknown),
+        }
+    }
+    
+    // Read the MediaFile URL
+    media_file.url = read_text_element(reader)?;
+    
+    Ok(media_file)
+}
+
+/// Parse Mezzanine element
+fn parse_mezzanine(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Mezzanine> {
+    let mut mezzanine = Mezzanine {
+        id: None,
+        url: String::new(),
+        mime_type: String::new(),
+        delivery: None,
+        width: None,
+        height: None,
+        codec: None,
+        file_size: None,
+        r#type: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    mezzanine.id = Some(value.to_string());
+                }
+            },
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    mezzanine.mime_type = value.to_string();
+                }
+            },
+            b"delivery" => {
+                if let Ok(value) = attr.unescape_value() {
+                    mezzanine.delivery = Some(value.to_string());
+                }
+            },
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    mezzanine.width = Some(width);
+                }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    mezzanine.height = Some(height);
+                }
+            },
+            b"codec" => {
+                if let Ok(value) = attr.unescape_value() {
+                    mezzanine.codec = Some(value.to_string());
+                }
+            },
+            b"fileSize" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(file_size) = value.parse::<u64>() {
+                    mezzanine.file_size = Some(file_size);
+                }
+            },
+            b"mediaType" => {
+                if let Ok(value) = attr.unescape_value() {
+                    mezzanine.r#type = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut mezzanine.unknown),
+        }
+    }
+    
+    // Read the Mezzanine URL
+    mezzanine.url = read_text_element(reader)?;
+    
+    Ok(mezzanine)
+}
+
+/// Parse InteractiveCreativeFile element
+fn parse_interactive_creative_file(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<InteractiveCreativeFile> {
+    let mut interactive_creative_file = InteractiveCreativeFile {
+        url: String::new(),
+        mime_type: None,
+        api_framework: None,
+        variable_duration: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    interactive_creative_file.mime_type = Some(value.to_string());
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    interactive_creative_file.api_framework = Some(value.to_string());
+                }
+            },
+            b"variableDuration" => {
+                if let Ok(value) = attr.unescape_value() {
+                    interactive_creative_file.variable_duration = Some(value.to_lowercase() == "true");
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut interactive_creative_file.unknown),
+        }
+    }
+    
+    // Read the InteractiveCreativeFile URL
+    interactive_creative_file.url = read_text_element(reader)?;
+    
+    Ok(interactive_creative_file)
+}
+
+/// Parse ClosedCaptionFiles element
+fn parse_closed_caption_files(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<ClosedCaptionFile>> {
+    let mut closed_caption_files = Vec::new();
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"ClosedCaptionFile" => {
+                let closed_caption_file = parse_closed_caption_file(reader, e)?;
+                closed_caption_files.push(closed_caption_file);
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "ClosedCaptionFiles", closed_caption_files.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "ClosedCaptionFiles", closed_caption_files.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"ClosedCaptionFiles" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(closed_caption_files)
+}
+
+/// Parse ClosedCaptionFile element
+fn parse_closed_caption_file(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<ClosedCaptionFile> {
+    let mut closed_caption_file = ClosedCaptionFile {
+        url: String::new(),
+        mime_type: None,
+        language: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"type" => {
+                if let Ok(value) = attr.unescape_value() {
+                    closed_caption_file.mime_type = Some(value.to_string());
+                }
+            },
+            b"language" => {
+                if let Ok(value) = attr.unescape_value() {
+                    closed_caption_file.language = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut closed_caption_file.unknown),
+        }
+    }
+    
+    // Read the ClosedCaptionFile URL
+    closed_caption_file.url = read_text_element(reader)?;
+    
+    Ok(closed_caption_file)
+}
+
+/// Parse VideoClicks element
+fn parse_video_clicks(reader: &mut VastReader<'_>) -> Result<VideoClicks> {
+    let mut video_clicks = VideoClicks {
+        click_through: None,
+        click_tracking: Vec::new(),
+        custom_click: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"ClickThrough" => {
+                        video_clicks.click_through = Some(read_text_element(reader)?);
+                    },
+                    b"ClickTracking" => {
+                        video_clicks.click_tracking.push(read_text_element(reader)?);
+                    },
+                    b"CustomClick" => {
+                        video_clicks.custom_click.push(read_text_element(reader)?);
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut video_clicks.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut video_clicks.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"VideoClicks" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(video_clicks)
+}
+
+/// Parse TrackingEvents element
+fn parse_tracking_events(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<TrackingEvent>> {
+    let mut tracking_events = Vec::new();
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Tracking" => {
+                let tracking_event = parse_tracking_event(reader, e)?;
+                tracking_events.push(tracking_event);
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "TrackingEvents", tracking_events.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "TrackingEvents", tracking_events.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"TrackingEvents" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(tracking_events)
+}
+
+/// Parse Tracking element
+fn parse_tracking_event(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<TrackingEvent> {
+    let mut tracking_event = TrackingEvent {
+        event: TrackingEventKind::Unknown(String::new()),
+        url: String::new(),
+        offset: None,
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"event" => {
+                if let Ok(value) = attr.unescape_value() {
+                    tracking_event.event = TrackingEventKind::from(value.as_ref());
+                }
+            },
+            b"offset" => {
+                if let Ok(value) = attr.unescape_value() {
+                    tracking_event.offset = parse_time(reader, &value, "Tracking@offset")?;
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut tracking_event.unknown),
+        }
+    }
+    
+    // Read the Tracking URL
+    tracking_event.url = read_text_element(reader)?;
+    
+    Ok(tracking_event)
+}
+
+/// Parse CompanionAds element
+fn parse_companion_ads(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<CompanionAds> {
+    let mut companion_ads = CompanionAds {
+        required: None,
+        companions: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract required attribute
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"required" => {
+                if let Ok(value) = attr.unescape_value() {
+                    companion_ads.required = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut companion_ads.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Companion" => {
+                let companion = parse_companion(reader, e)?;
+                companion_ads.companions.push(companion);
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_element(reader, e, &mut companion_ads.unknown)?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut companion_ads.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"CompanionAds" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(companion_ads)
+}
+
+/// Parse Companion element
+fn parse_companion(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Companion> {
+    let mut companion = Companion {
+        id: None,
+        width: 0,
+        height: 0,
+        asset_width: None,
+        asset_height: None,
+        ad_slot_id: None,
+        api_framework: None,
+        resources: Vec::new(),
+        click_through: None,
+        click_tracking: Vec::new(),
+        alt_text: None,
+        ad_parameters: None,
+        tracking_events: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    companion.id = Some(value.to_string());
+                }
+            },
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    companion.width = width;
+                }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    companion.height = height;
+                }
+            },
+            b"assetWidth" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    companion.asset_width = Some(width);
+                }
+            },
+            b"assetHeight" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    companion.asset_height = Some(height);
+                }
+            },
+            // VAST 3 spells it adSlotID, VAST 4 adSlotId
+            b"adSlotID" | b"adSlotId" => {
+                if let Ok(value) = attr.unescape_value() {
+                    companion.ad_slot_id = Some(value.to_string());
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    companion.api_framework = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut companion.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"StaticResource" | b"IFrameResource" | b"HTMLResource" => {
+                        companion.resources.push(parse_resource(reader, e)?);
+                    },
+                    b"CompanionClickThrough" => {
+                        companion.click_through = Some(read_text_element(reader)?);
+                    },
+                    b"CompanionClickTracking" => {
+                        companion.click_tracking.push(read_text_element(reader)?);
+                    },
+                    b"AltText" => {
+                        companion.alt_text = Some(read_text_element(reader)?);
+                    },
+                    b"AdParameters" => {
+                        companion.ad_parameters = Some(parse_ad_parameters(reader, e)?);
+                    },
+                    b"TrackingEvents" => {
+                        companion.tracking_events = parse_tracking_events(reader, &mut companion.unknown)?;
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut companion.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut companion.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Companion" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(companion)
+}
+
+/// Parse a StaticResource, IFrameResource or HTMLResource element
+fn parse_resource(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Resource> {
+    let mut resource = Resource {
+        resource_type: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        creative_type: None,
+        content: String::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract creativeType attribute
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"creativeType" => {
+                if let Ok(value) = attr.unescape_value() {
+                    resource.creative_type = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut resource.unknown),
+        }
+    }
+    
+    // Read the resource URL or markup
+    resource.content = read_text_element(reader)?;
+    
+    Ok(resource)
+}
+
+/// Parse AdParameters element
+fn parse_ad_parameters(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<AdParameters> {
+    let mut ad_parameters = AdParameters {
+        xml_encoded: None,
+        content: String::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract xmlEncoded attribute
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"xmlEncoded" => {
+                if let Ok(value) = attr.unescape_value() {
+                    ad_parameters.xml_encoded = Some(value.to_lowercase() == "true");
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut ad_parameters.unknown),
+        }
+    }
+    
+    // Read the parameter data
+    ad_parameters.content = read_text_element(reader)?;
+    
+    Ok(ad_parameters)
+}
+
+/// Parse NonLinearAds element
+fn parse_non_linear_ads(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<NonLinearAds> {
+    let mut non_linear_ads = NonLinearAds {
+        non_linears: Vec::new(),
+        tracking_events: Vec::new(),
+        unknown: UnknownXml::default(),
     };
     
-    // Extract attributes
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            match attr.key.as_ref() {
-                b"type" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        media_file.mime_type = value.to_string();
-                    }
-                },
-                b"codec" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        media_file.codec = Some(value.to_string());
-                    }
-                },
-                b"bitrate" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        if let Ok(bitrate) = value.parse::<u32>() {
-                            media_file.bitrate = Some(bitrate);
-                        }
-                    }
-                },
-                b"width" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        if let Ok(width) = value.parse::<u32>() {
-                            media_file.width = Some(width);
-                        }
-                    }
-                },
-                b"height" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        if let Ok(height) = value.parse::<u32>() {
-                            media_file.height = Some(height);
-                        }
-                    }
-                },
-                b"delivery" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        media_file.delivery = Some(value.to_string());
+    // NonLinearAds has no attributes of its own
+    for attr in start.attributes().flatten() {
+        keep_unknown_attribute(reader, &attr, &mut non_linear_ads.unknown);
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"NonLinear" => {
+                        let non_linear = parse_non_linear(reader, e)?;
+                        non_linear_ads.non_linears.push(non_linear);
+                    },
+                    b"TrackingEvents" => {
+                        non_linear_ads.tracking_events = parse_tracking_events(reader, &mut non_linear_ads.unknown)?;
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut non_linear_ads.unknown)?;
                     }
-                },
-                b"mediaType" => {
-                    if let Ok(value) = from_utf8(&attr.value) {
-                        media_file.r#type = Some(value.to_string());
+                }
+            },
+            Ok(Event::Empty(ref e)) if e.name().as_ref() == b"NonLinear" => {
+                non_linear_ads.non_linears.push(non_linear_from_start(reader, e)?);
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut non_linear_ads.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"NonLinearAds" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(non_linear_ads)
+}
+
+/// Parse NonLinear element
+fn parse_non_linear(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<NonLinear> {
+    let mut non_linear = non_linear_from_start(reader, start)?;
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"StaticResource" | b"IFrameResource" | b"HTMLResource" => {
+                        non_linear.resources.push(parse_resource(reader, e)?);
+                    },
+                    b"NonLinearClickThrough" => {
+                        non_linear.click_through = Some(read_text_element(reader)?);
+                    },
+                    b"NonLinearClickTracking" => {
+                        non_linear.click_tracking.push(read_text_element(reader)?);
+                    },
+                    b"AdParameters" => {
+                        non_linear.ad_parameters = Some(parse_ad_parameters(reader, e)?);
+                    },
+                    b"Icons" => {
+                        non_linear.icons = parse_icons(reader, &mut non_linear.unknown)?;
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut non_linear.unknown)?;
                     }
-                },
-                _ => (),
-            }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut non_linear.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"NonLinear" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
         }
+        buf.clear();
     }
     
-    // Read the MediaFile URL
-    media_file.url = read_text_element(reader)?;
+    Ok(non_linear)
+}
+
+/// Build a NonLinear from the attributes of its start tag
+fn non_linear_from_start(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<NonLinear> {
+    let mut non_linear = NonLinear {
+        id: None,
+        width: 0,
+        height: 0,
+        expanded_width: None,
+        expanded_height: None,
+        scalable: None,
+        maintain_aspect_ratio: None,
+        min_suggested_duration: None,
+        api_framework: None,
+        resources: Vec::new(),
+        click_through: None,
+        click_tracking: Vec::new(),
+        ad_parameters: None,
+        icons: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
     
-    Ok(media_file)
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"id" => {
+                if let Ok(value) = attr.unescape_value() {
+                    non_linear.id = Some(value.to_string());
+                }
+            },
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    non_linear.width = width;
+                }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    non_linear.height = height;
+                }
+            },
+            b"expandedWidth" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    non_linear.expanded_width = Some(width);
+                }
+            },
+            b"expandedHeight" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    non_linear.expanded_height = Some(height);
+                }
+            },
+            b"scalable" => {
+                if let Ok(value) = attr.unescape_value() {
+                    non_linear.scalable = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"maintainAspectRatio" => {
+                if let Ok(value) = attr.unescape_value() {
+                    non_linear.maintain_aspect_ratio = Some(value.to_lowercase() == "true");
+                }
+            },
+            b"minSuggestedDuration" => {
+                if let Ok(value) = attr.unescape_value() {
+                    non_linear.min_suggested_duration = parse_time(reader, &value, "NonLinear@minSuggestedDuration")?;
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    non_linear.api_framework = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut non_linear.unknown),
+        }
+    }
+    
+    Ok(non_linear)
 }
 
-/// Parse VideoClicks element
-fn parse_video_clicks(reader: &mut Reader<&[u8]>) -> Result<VideoClicks> {
-    let mut video_clicks = VideoClicks {
+/// Parse Icons element
+fn parse_icons(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<Icon>> {
+    let mut icons = Vec::new();
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Icon" => {
+                let icon = parse_icon(reader, e)?;
+                icons.push(icon);
+            },
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "Icons", icons.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "Icons", icons.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Icons" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(icons)
+}
+
+/// Parse Icon element
+fn parse_icon(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<Icon> {
+    let mut icon = Icon {
+        program: None,
+        width: 0,
+        height: 0,
+        x_position: None,
+        y_position: None,
+        duration: None,
+        offset: None,
+        api_framework: None,
+        pxratio: None,
+        resources: Vec::new(),
+        icon_clicks: None,
+        view_tracking: Vec::new(),
+        unknown: UnknownXml::default(),
+    };
+    
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"program" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.program = Some(value.to_string());
+                }
+            },
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    icon.width = width;
+                }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    icon.height = height;
+                }
+            },
+            b"xPosition" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.x_position = Some(value.to_string());
+                }
+            },
+            b"yPosition" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.y_position = Some(value.to_string());
+                }
+            },
+            b"duration" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.duration = parse_time(reader, &value, "Icon@duration")?;
+                }
+            },
+            b"offset" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.offset = parse_time(reader, &value, "Icon@offset")?;
+                }
+            },
+            b"apiFramework" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.api_framework = Some(value.to_string());
+                }
+            },
+            b"pxratio" => {
+                if let Ok(value) = attr.unescape_value() {
+                    icon.pxratio = Some(value.to_string());
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut icon.unknown),
+        }
+    }
+    
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"StaticResource" | b"IFrameResource" | b"HTMLResource" => {
+                        icon.resources.push(parse_resource(reader, e)?);
+                    },
+                    b"IconClicks" => {
+                        icon.icon_clicks = Some(parse_icon_clicks(reader)?);
+                    },
+                    b"IconViewTracking" => {
+                        icon.view_tracking.push(read_text_element(reader)?);
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut icon.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut icon.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Icon" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(icon)
+}
+
+/// Parse IconClicks element
+fn parse_icon_clicks(reader: &mut VastReader<'_>) -> Result<IconClicks> {
+    let mut icon_clicks = IconClicks {
         click_through: None,
         click_tracking: Vec::new(),
-        custom_click: Vec::new(),
+        fallback_images: Vec::new(),
+        unknown: UnknownXml::default(),
     };
     
     let mut buf = Vec::new();
@@ -726,22 +2265,23 @@ fn parse_video_clicks(reader: &mut Reader<&[u8]>) -> Result<VideoClicks> {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) => {
                 match e.name().as_ref() {
-                    b"ClickThrough" => {
-                        video_clicks.click_through = Some(read_text_element(reader)?);
+                    b"IconClickThrough" => {
+                        icon_clicks.click_through = Some(read_text_element(reader)?);
                     },
-                    b"ClickTracking" => {
-                        video_clicks.click_tracking.push(read_text_element(reader)?);
+                    b"IconClickTracking" => {
+                        icon_clicks.click_tracking.push(read_text_element(reader)?);
                     },
-                    b"CustomClick" => {
-                        video_clicks.custom_click.push(read_text_element(reader)?);
+                    b"IconClickFallbackImages" => {
+                        icon_clicks.fallback_images = parse_icon_click_fallback_images(reader, &mut icon_clicks.unknown)?;
                     },
                     _ => {
                         // Skip other elements
-                        skip_element(reader, e.name().as_ref())?;
+                        skip_unknown_element(reader, e, &mut icon_clicks.unknown)?;
                     }
                 }
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"VideoClicks" => break,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut icon_clicks.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"IconClicks" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -751,21 +2291,23 @@ fn parse_video_clicks(reader: &mut Reader<&[u8]>) -> Result<VideoClicks> {
         buf.clear();
     }
     
-    Ok(video_clicks)
+    Ok(icon_clicks)
 }
 
-/// Parse TrackingEvents element
-fn parse_tracking_events(reader: &mut Reader<&[u8]>) -> Result<Vec<TrackingEvent>> {
-    let mut tracking_events = Vec::new();
+/// Parse IconClickFallbackImages element
+fn parse_icon_click_fallback_images(reader: &mut VastReader<'_>, unknown: &mut UnknownXml) -> Result<Vec<IconClickFallbackImage>> {
+    let mut images = Vec::new();
     let mut buf = Vec::new();
     
     loop {
         match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Tracking" => {
-                let tracking_event = parse_tracking_event(reader, e)?;
-                tracking_events.push(tracking_event);
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"IconClickFallbackImage" => {
+                let image = parse_icon_click_fallback_image(reader, e)?;
+                images.push(image);
             },
-            Ok(Event::End(ref e)) if e.name().as_ref() == b"TrackingEvents" => break,
+            Ok(Event::Start(ref e)) => skip_unknown_list_element(reader, e, unknown, "IconClickFallbackImages", images.len())?,
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_list_element(reader, e, unknown, "IconClickFallbackImages", images.len()),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"IconClickFallbackImages" => break,
             Ok(Event::Eof) => {
                 return Err(VastError::Other("Unexpected end of file".to_string()));
             },
@@ -775,55 +2317,415 @@ fn parse_tracking_events(reader: &mut Reader<&[u8]>) -> Result<Vec<TrackingEvent
         buf.clear();
     }
     
-    Ok(tracking_events)
+    Ok(images)
 }
 
-/// Parse Tracking element
-fn parse_tracking_event(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<TrackingEvent> {
-    let mut tracking_event = TrackingEvent {
-        event: String::new(),
-        url: String::new(),
+/// Parse IconClickFallbackImage element
+fn parse_icon_click_fallback_image(reader: &mut VastReader<'_>, start: &BytesStart) -> Result<IconClickFallbackImage> {
+    let mut image = IconClickFallbackImage {
+        width: None,
+        height: None,
+        alt_text: None,
+        static_resource: None,
+        unknown: UnknownXml::default(),
     };
     
-    // Extract event attribute
-    for attr in start.attributes() {
-        if let Ok(attr) = attr {
-            if attr.key.as_ref() == b"event" {
-                if let Ok(value) = from_utf8(&attr.value) {
-                    tracking_event.event = value.to_string();
+    // Extract attributes
+    for attr in start.attributes().flatten() {
+        match attr.key.as_ref() {
+            b"width" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(width) = value.parse::<u32>() {
+                    image.width = Some(width);
                 }
-            }
+            },
+            b"height" => {
+                if let Ok(value) = attr.unescape_value()
+                    && let Ok(height) = value.parse::<u32>() {
+                    image.height = Some(height);
+                }
+            },
+            _ => keep_unknown_attribute(reader, &attr, &mut image.unknown),
         }
     }
     
-    // Read the Tracking URL
-    tracking_event.url = read_text_element(reader)?;
+    let mut buf = Vec::new();
     
-    Ok(tracking_event)
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                match e.name().as_ref() {
+                    b"AltText" => {
+                        image.alt_text = Some(read_text_element(reader)?);
+                    },
+                    b"StaticResource" => {
+                        image.static_resource = Some(parse_resource(reader, e)?);
+                    },
+                    _ => {
+                        // Skip other elements
+                        skip_unknown_element(reader, e, &mut image.unknown)?;
+                    }
+                }
+            },
+            Ok(Event::Empty(ref e)) => keep_unknown_empty_element(reader, e, &mut image.unknown),
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"IconClickFallbackImage" => break,
+            Ok(Event::Eof) => {
+                return Err(VastError::Other("Unexpected end of file".to_string()));
+            },
+            Err(e) => return Err(VastError::XmlParseError(e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    
+    Ok(image)
 }
 
-/// Parse CompanionAds element
-fn parse_companion_ads(reader: &mut Reader<&[u8]>) -> Result<CompanionAds> {
-    // Placeholder implementation
-    let companion_ads = CompanionAds {
-        companions: Vec::new(),
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::VastDuration;
     
-    // Skip to the end of the CompanionAds element
-    skip_element(reader, b"CompanionAds")?;
+    /// A VAST document with one inline ad holding a linear creative with the given duration
+    fn inline_with_duration(duration: &str, expires: &str) -> String {
+        format!(
+            r#"<VAST version="4.1"><Ad id="1"><InLine><Expires>{}</Expires><Creatives><Creative><Linear><Duration>{}</Duration></Linear></Creative></Creatives></InLine></Ad></VAST>"#,
+            expires, duration
+        )
+    }
     
-    Ok(companion_ads)
-}
-
-/// Parse NonLinearAds element
-fn parse_non_linear_ads(reader: &mut Reader<&[u8]>) -> Result<NonLinearAds> {
-    // Placeholder implementation
-    let non_linear_ads = NonLinearAds {
-        non_linears: Vec::new(),
-    };
+    fn linear(vast: &Vast) -> &Linear {
+        vast.ads[0].inline.as_ref().unwrap().creatives[0].linear.as_ref().unwrap()
+    }
     
-    // Skip to the end of the NonLinearAds element
-    skip_element(reader, b"NonLinearAds")?;
+    /// Parse a document, check it survives writing it back out, and return it
+    fn parse_and_round_trip(xml: &str) -> Vast {
+        let vast = parse_vast(xml).unwrap();
+        let written = vast.to_xml(&crate::serializer::WriteOptions::new()).unwrap();
+        assert_eq!(parse_vast(&written).unwrap(), vast, "round trip changed the document: {}", written);
+        vast
+    }
     
-    Ok(non_linear_ads)
-} 
\ No newline at end of file
+    fn creatives(vast: &Vast) -> &[Creative] {
+        &vast.ads[0].inline.as_ref().unwrap().creatives
+    }
+    
+    #[test]
+    fn malformed_values_are_left_unset_with_a_warning() {
+        let xml = inline_with_duration("", "tomorrow");
+        let (vast, warnings) = parse_vast_with_warnings(&xml, &ParseOptions::new()).unwrap();
+        
+        assert_eq!(linear(&vast).duration, None);
+        assert_eq!(vast.ads[0].inline.as_ref().unwrap().expires, None);
+        assert!(matches!(&warnings[0], VastError::InvalidValue { location, .. } if location == "InLine/Expires"));
+        assert!(matches!(&warnings[1], VastError::InvalidTime { location, .. } if location == "Linear/Duration"));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings[0].to_string(),
+            r#"Invalid value in InLine/Expires at line 1, column 67: expected a number of seconds, found "tomorrow""#
+        );
+    }
+    
+    #[test]
+    fn well_formed_times_parse_without_warnings() {
+        let xml = inline_with_duration("00:00:30", "3600");
+        let (vast, warnings) = parse_vast_with_warnings(&xml, &ParseOptions::new()).unwrap();
+        
+        assert!(warnings.is_empty());
+        assert_eq!(linear(&vast).duration, Some(VastDuration::from_millis(30_000)));
+        assert_eq!(vast.ads[0].inline.as_ref().unwrap().expires, Some(3600));
+    }
+    
+    #[test]
+    fn strict_mode_fails_on_malformed_values() {
+        let options = ParseOptions::new().with_strict(true);
+        
+        let error = parse_vast_with_options(&inline_with_duration("", "3600"), &options).unwrap_err();
+        assert!(matches!(error, VastError::InvalidTime { ref location, line: 1, .. } if location == "Linear/Duration"));
+        
+        let error = parse_vast_with_options(&inline_with_duration("00:00:30", "soon"), &options).unwrap_err();
+        assert!(matches!(error, VastError::InvalidValue { ref location, .. } if location == "InLine/Expires"));
+    }
+    
+    #[test]
+    fn parses_companion_ads() {
+        let vast = parse_and_round_trip(include_str!("../samples/sample_companions.xml"));
+        let companion_ads = creatives(&vast)[1].companion_ads.as_ref().unwrap();
+        assert_eq!(companion_ads.required.as_deref(), Some("any"));
+        
+        let [banner, leaderboard] = companion_ads.companions.as_slice() else {
+            panic!("expected two companions: {:?}", companion_ads.companions);
+        };
+        assert_eq!(banner.id.as_deref(), Some("banner-300x250"));
+        assert_eq!((banner.width, banner.height), (300, 250));
+        assert_eq!((banner.asset_width, banner.asset_height), (Some(300), Some(250)));
+        assert_eq!(banner.ad_slot_id.as_deref(), Some("sidebar"));
+        assert_eq!(banner.resources.len(), 1);
+        assert_eq!(banner.resources[0].resource_type, "StaticResource");
+        assert_eq!(banner.resources[0].creative_type.as_deref(), Some("image/png"));
+        assert_eq!(banner.resources[0].content, "https://example.com/companion/banner.png");
+        assert_eq!(banner.tracking_events.len(), 1);
+        assert_eq!(banner.tracking_events[0].event, TrackingEventKind::CreativeView);
+        assert_eq!(banner.click_through.as_deref(), Some("https://example.com/companion/landing"));
+        assert_eq!(banner.click_tracking, ["https://example.com/companion/click"]);
+        assert_eq!(banner.alt_text.as_deref(), Some("Sample companion banner"));
+        assert_eq!(banner.ad_parameters, None);
+        
+        assert_eq!((leaderboard.width, leaderboard.height), (728, 90));
+        assert_eq!(leaderboard.api_framework.as_deref(), Some("VPAID"));
+        let resources: Vec<_> = leaderboard.resources.iter()
+            .map(|resource| (resource.resource_type.as_str(), resource.content.as_str()))
+            .collect();
+        assert_eq!(resources, [
+            ("IFrameResource", "https://example.com/companion/leaderboard.html"),
+            ("HTMLResource", r#"<a href="https://example.com/companion/landing">Visit us</a>"#),
+        ]);
+        let ad_parameters = leaderboard.ad_parameters.as_ref().unwrap();
+        assert_eq!(ad_parameters.xml_encoded, Some(false));
+        assert_eq!(ad_parameters.content, r#"{"theme":"dark"}"#);
+        assert!(leaderboard.click_tracking.is_empty() && leaderboard.tracking_events.is_empty());
+    }
+    
+    #[test]
+    fn parses_non_linear_ads() {
+        let vast = parse_and_round_trip(include_str!("../samples/sample_nonlinear.xml"));
+        let non_linear_ads = creatives(&vast)[0].non_linear_ads.as_ref().unwrap();
+        let events: Vec<_> = non_linear_ads.tracking_events.iter().map(|event| event.event.as_str()).collect();
+        assert_eq!(events, ["creativeView", "close"]);
+        
+        let [non_linear] = non_linear_ads.non_linears.as_slice() else {
+            panic!("expected one non-linear: {:?}", non_linear_ads.non_linears);
+        };
+        assert_eq!(non_linear.id.as_deref(), Some("overlay-480x70"));
+        assert_eq!((non_linear.width, non_linear.height), (480, 70));
+        assert_eq!((non_linear.expanded_width, non_linear.expanded_height), (Some(640), Some(360)));
+        assert_eq!(non_linear.scalable, Some(true));
+        assert_eq!(non_linear.maintain_aspect_ratio, Some(true));
+        assert_eq!(non_linear.min_suggested_duration, Some(VastDuration::from_millis(10_000)));
+        assert_eq!(non_linear.resources[0].content, "https://example.com/overlay/banner.png");
+        assert_eq!(non_linear.click_through.as_deref(), Some("https://example.com/overlay/landing"));
+        assert_eq!(non_linear.click_tracking, ["https://example.com/overlay/click"]);
+    }
+    
+    #[test]
+    fn parses_wrapper_non_linear_tracking() {
+        let vast = parse_vast(include_str!("../samples/sample_nonlinear_wrapper.xml")).unwrap();
+        let wrapper = vast.ads[0].wrapper.as_ref().unwrap();
+        let non_linear_ads = wrapper.creatives[0].non_linear_ads.as_ref().unwrap();
+        
+        assert_eq!(non_linear_ads.tracking_events.len(), 1);
+        assert_eq!(non_linear_ads.tracking_events[0].event, TrackingEventKind::CreativeView);
+        assert_eq!(non_linear_ads.tracking_events[0].url, "https://example.com/overlay-wrapper/creativeView");
+        
+        // A wrapper NonLinear only carries click tracking, without a size or resources
+        let non_linear = &non_linear_ads.non_linears[0];
+        assert_eq!((non_linear.id.as_deref(), non_linear.width, non_linear.height), (None, 0, 0));
+        assert!(non_linear.resources.is_empty());
+        assert_eq!(non_linear.click_tracking, ["https://example.com/overlay-wrapper/click"]);
+    }
+    
+    #[test]
+    fn lossless_mode_keeps_non_linear_ads_attributes() {
+        let xml = r#"<VAST version="4.1" xmlns:acme="urn:acme"><Ad><InLine><Creatives><Creative>
+            <NonLinearAds acme:slot="lower-third"><NonLinear width="480" height="70"/></NonLinearAds>
+        </Creative></Creatives></InLine></Ad></VAST>"#;
+        let vast = parse_vast_with_options(xml, &ParseOptions::new().with_lossless(true)).unwrap();
+        let non_linear_ads = creatives(&vast)[0].non_linear_ads.as_ref().unwrap();
+        
+        assert_eq!(non_linear_ads.unknown.attributes, [("acme:slot".to_string(), "lower-third".to_string())]);
+        assert_eq!((non_linear_ads.non_linears[0].width, non_linear_ads.non_linears[0].height), (480, 70));
+        let written = vast.to_xml(&crate::serializer::WriteOptions::minified()).unwrap();
+        assert!(written.contains(r#"<NonLinearAds acme:slot="lower-third">"#), "{}", written);
+    }
+    
+    #[test]
+    fn parses_icons() {
+        let xml = r#"<VAST version="4.1"><Ad><InLine><Creatives><Creative>
+            <Linear>
+                <Duration>00:00:30</Duration>
+                <Icons>
+                    <Icon program="AdChoices" width="77" height="15" xPosition="right" yPosition="top" duration="00:00:10" offset="00:00:05" apiFramework="omid" pxratio="2">
+                        <StaticResource creativeType="image/png"><![CDATA[https://example.com/adchoices.png]]></StaticResource>
+                        <IconClicks>
+                            <IconClickFallbackImages>
+                                <IconClickFallbackImage width="300" height="250">
+                                    <AltText>Why this ad?</AltText>
+                                    <StaticResource creativeType="image/png"><![CDATA[https://example.com/why.png]]></StaticResource>
+                                </IconClickFallbackImage>
+                            </IconClickFallbackImages>
+                            <IconClickThrough><![CDATA[https://example.com/adchoices]]></IconClickThrough>
+                            <IconClickTracking><![CDATA[https://example.com/adchoices/click]]></IconClickTracking>
+                        </IconClicks>
+                        <IconViewTracking><![CDATA[https://example.com/adchoices/view]]></IconViewTracking>
+                    </Icon>
+                    <Icon program="Brand" width="40" height="40" xPosition="10" yPosition="20">
+                        <HTMLResource><![CDATA[<img src="https://example.com/brand.png">]]></HTMLResource>
+                    </Icon>
+                </Icons>
+            </Linear>
+        </Creative><Creative>
+            <NonLinearAds><NonLinear width="480" height="70">
+                <StaticResource creativeType="image/png">https://example.com/overlay.png</StaticResource>
+                <Icons><Icon program="AdChoices" width="20" height="20"><IFrameResource>https://example.com/adchoices.html</IFrameResource></Icon></Icons>
+            </NonLinear></NonLinearAds>
+        </Creative></Creatives></InLine></Ad></VAST>"#;
+        let vast = parse_and_round_trip(xml);
+        
+        let [adchoices, brand] = linear(&vast).icons.as_slice() else {
+            panic!("expected two icons: {:?}", linear(&vast).icons);
+        };
+        assert_eq!(adchoices.program.as_deref(), Some("AdChoices"));
+        assert_eq!((adchoices.width, adchoices.height), (77, 15));
+        assert_eq!((adchoices.x_position.as_deref(), adchoices.y_position.as_deref()), (Some("right"), Some("top")));
+        assert_eq!(adchoices.duration, Some(VastDuration::from_millis(10_000)));
+        assert_eq!(adchoices.offset, Some(VastDuration::from_millis(5_000)));
+        assert_eq!(adchoices.api_framework.as_deref(), Some("omid"));
+        assert_eq!(adchoices.pxratio.as_deref(), Some("2"));
+        assert_eq!(adchoices.resources[0].resource_type, "StaticResource");
+        assert_eq!(adchoices.resources[0].content, "https://example.com/adchoices.png");
+        assert_eq!(adchoices.view_tracking, ["https://example.com/adchoices/view"]);
+        
+        let icon_clicks = adchoices.icon_clicks.as_ref().unwrap();
+        assert_eq!(icon_clicks.click_through.as_deref(), Some("https://example.com/adchoices"));
+        assert_eq!(icon_clicks.click_tracking, ["https://example.com/adchoices/click"]);
+        let [fallback_image] = icon_clicks.fallback_images.as_slice() else {
+            panic!("expected one fallback image: {:?}", icon_clicks.fallback_images);
+        };
+        assert_eq!((fallback_image.width, fallback_image.height), (Some(300), Some(250)));
+        assert_eq!(fallback_image.alt_text.as_deref(), Some("Why this ad?"));
+        assert_eq!(fallback_image.static_resource.as_ref().unwrap().content, "https://example.com/why.png");
+        
+        assert_eq!(brand.resources[0].resource_type, "HTMLResource");
+        assert_eq!(brand.resources[0].content, r#"<img src="https://example.com/brand.png">"#);
+        assert!(brand.icon_clicks.is_none() && brand.view_tracking.is_empty());
+        
+        let non_linear = &creatives(&vast)[1].non_linear_ads.as_ref().unwrap().non_linears[0];
+        assert_eq!(non_linear.icons[0].resources[0].resource_type, "IFrameResource");
+    }
+    
+    #[test]
+    fn parses_vast_4_media_files() {
+        let xml = r#"<VAST version="4.1"><Ad><InLine><Creatives><Creative><Linear>
+            <Duration>00:00:30</Duration>
+            <MediaFiles>
+                <MediaFile id="hd" delivery="progressive" type="video/mp4" width="1920" height="1080" codec="avc1.64002a" bitrate="4000" minBitrate="3000" maxBitrate="5000" scalable="true" maintainAspectRatio="false" fileSize="15000000" mediaType="2D" apiFramework="VPAID">
+                    <![CDATA[https://example.com/hd.mp4]]>
+                </MediaFile>
+                <Mezzanine id="master" delivery="progressive" type="video/mp4" width="3840" height="2160" codec="hvc1" fileSize="900000000" mediaType="2D">
+                    <![CDATA[https://example.com/master.mp4]]>
+                </Mezzanine>
+                <InteractiveCreativeFile type="text/html" apiFramework="SIMID" variableDuration="true">
+                    <![CDATA[https://example.com/simid.html]]>
+                </InteractiveCreativeFile>
+                <ClosedCaptionFiles>
+                    <ClosedCaptionFile type="text/vtt" language="en"><![CDATA[https://example.com/en.vtt]]></ClosedCaptionFile>
+                    <ClosedCaptionFile type="application/ttml+xml" language="fr"><![CDATA[https://example.com/fr.ttml]]></ClosedCaptionFile>
+                </ClosedCaptionFiles>
+            </MediaFiles>
+        </Linear></Creative></Creatives></InLine></Ad></VAST>"#;
+        let vast = parse_and_round_trip(xml);
+        let linear = linear(&vast);
+        
+        assert_eq!(linear.media_files, [MediaFile {
+            id: Some("hd".to_string()),
+            url: "https://example.com/hd.mp4".to_string(),
+            mime_type: "video/mp4".to_string(),
+            codec: Some("avc1.64002a".to_string()),
+            bitrate: Some(4000),
+            min_bitrate: Some(3000),
+            max_bitrate: Some(5000),
+            width: Some(1920),
+            height: Some(1080),
+            delivery: Some("progressive".to_string()),
+            r#type: Some("2D".to_string()),
+            api_framework: Some("VPAID".to_string()),
+            scalable: Some(true),
+            maintain_aspect_ratio: Some(false),
+            file_size: Some(15_000_000),
+            unknown: UnknownXml::default(),
+        }]);
+        assert_eq!(linear.mezzanines, [Mezzanine {
+            id: Some("master".to_string()),
+            url: "https://example.com/master.mp4".to_string(),
+            mime_type: "video/mp4".to_string(),
+            delivery: Some("progressive".to_string()),
+            width: Some(3840),
+            height: Some(2160),
+            codec: Some("hvc1".to_string()),
+            file_size: Some(900_000_000),
+            r#type: Some("2D".to_string()),
+            unknown: UnknownXml::default(),
+        }]);
+        assert_eq!(linear.interactive_creative_files, [InteractiveCreativeFile {
+            url: "https://example.com/simid.html".to_string(),
+            mime_type: Some("text/html".to_string()),
+            api_framework: Some("SIMID".to_string()),
+            variable_duration: Some(true),
+            unknown: UnknownXml::default(),
+        }]);
+        let captions: Vec<_> = linear.closed_caption_files.iter()
+            .map(|file| (file.url.as_str(), file.mime_type.as_deref(), file.language.as_deref()))
+            .collect();
+        assert_eq!(captions, [
+            ("https://example.com/en.vtt", Some("text/vtt"), Some("en")),
+            ("https://example.com/fr.ttml", Some("application/ttml+xml"), Some("fr")),
+        ]);
+    }
+    
+    #[test]
+    fn parses_vast_4_ad_fields() {
+        let xml = r#"<VAST version="4.1"><Ad><InLine>
+            <AdSystem>Acme</AdSystem>
+            <AdTitle>Title</AdTitle>
+            <AdServingId>a532d16d-4d7f-4440-bd29-2ec0e693fc80</AdServingId>
+            <Category authority="https://iabtechlab.com/categoryauthority">IAB1-1</Category>
+            <Category authority="https://example.com/taxonomy">sports</Category>
+            <Expires>86400</Expires>
+            <Creatives><Creative>
+                <UniversalAdId idRegistry="ad-id.org">CNPA0484000H</UniversalAdId>
+                <UniversalAdId idRegistry="clearcast.co.uk">AAA/BBBB123/030</UniversalAdId>
+                <UniversalAdId idRegistry="unknown" idValue="legacy-4.0"/>
+                <Linear><Duration>00:00:15</Duration></Linear>
+            </Creative></Creatives>
+        </InLine></Ad></VAST>"#;
+        let vast = parse_and_round_trip(xml);
+        let inline = vast.ads[0].inline.as_ref().unwrap();
+        
+        assert_eq!(inline.ad_serving_id.as_deref(), Some("a532d16d-4d7f-4440-bd29-2ec0e693fc80"));
+        assert_eq!(inline.expires, Some(86400));
+        let categories: Vec<_> = inline.categories.iter()
+            .map(|category| (category.authority.as_deref(), category.value.as_str()))
+            .collect();
+        assert_eq!(categories, [
+            (Some("https://iabtechlab.com/categoryauthority"), "IAB1-1"),
+            (Some("https://example.com/taxonomy"), "sports"),
+        ]);
+        
+        // VAST 4.1 allows several UniversalAdIds; 4.0 put the identifier in idValue
+        let universal_ad_ids: Vec<_> = inline.creatives[0].universal_ad_ids.iter()
+            .map(|universal_ad_id| (universal_ad_id.id_registry.as_str(), universal_ad_id.id.as_str()))
+            .collect();
+        assert_eq!(universal_ad_ids, [
+            ("ad-id.org", "CNPA0484000H"),
+            ("clearcast.co.uk", "AAA/BBBB123/030"),
+            ("unknown", "legacy-4.0"),
+        ]);
+    }
+    
+    #[test]
+    fn parses_blocked_ad_categories() {
+        let xml = r#"<VAST version="4.1"><Ad><Wrapper>
+            <AdSystem>Acme</AdSystem>
+            <VASTAdTagURI>https://example.com/inline</VASTAdTagURI>
+            <BlockedAdCategories authority="https://iabtechlab.com/categoryauthority">IAB25, IAB26</BlockedAdCategories>
+            <BlockedAdCategories>IAB7-39</BlockedAdCategories>
+        </Wrapper></Ad></VAST>"#;
+        let vast = parse_and_round_trip(xml);
+        let blocked = &vast.ads[0].wrapper.as_ref().unwrap().blocked_ad_categories;
+        
+        assert_eq!(blocked.len(), 2);
+        assert_eq!(blocked[0].authority.as_deref(), Some("https://iabtechlab.com/categoryauthority"));
+        assert_eq!(blocked[0].codes().collect::<Vec<_>>(), ["IAB25", "IAB26"]);
+        assert_eq!((blocked[1].authority.as_deref(), blocked[1].value.as_str()), (None, "IAB7-39"));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::models::{Linear, TrackingEvent, TrackingEventKind};
+use crate::time::VastOffset;
+use std::time::Duration;
+
+impl Linear {
+    /// Whether the ad can be skipped
+    pub fn is_skippable(&self) -> bool {
+        self.skip_offset.is_some()
+    }
+    
+    /// When the ad may be skipped, for an ad of the given duration
+    pub fn skip_time(&self, duration: Duration) -> Option<Duration> {
+        self.skip_offset.map(|offset| offset.resolve(duration))
+    }
+    
+    /// The time each timed tracking event is due, for an ad of the given duration, in time order
+    /// 
+    /// Timed events are creativeView and start at the beginning, the quartiles, complete at the
+    /// end and progress events at their offset. Events due at the same time keep document order.
+    pub fn timed_events(&self, duration: Duration) -> Vec<(Duration, &TrackingEvent)> {
+        let mut events: Vec<(Duration, &TrackingEvent)> = self.tracking_events.iter()
+            .filter_map(|event| event_offset(event).map(|offset| (offset.resolve(duration), event)))
+            .collect();
+        events.sort_by_key(|(time, _)| *time);
+        events
+    }
+}
+
+/// When a tracking event is due, if it is a timed event
+fn event_offset(event: &TrackingEvent) -> Option<VastOffset> {
+    match event.event {
+        TrackingEventKind::CreativeView | TrackingEventKind::Start => Some(VastOffset::Percent(0.0)),
+        TrackingEventKind::FirstQuartile => Some(VastOffset::Percent(25.0)),
+        TrackingEventKind::Midpoint => Some(VastOffset::Percent(50.0)),
+        TrackingEventKind::ThirdQuartile => Some(VastOffset::Percent(75.0)),
+        TrackingEventKind::Complete => Some(VastOffset::Percent(100.0)),
+        TrackingEventKind::Progress => event.offset,
+        _ => None,
+    }
+}
+
+/// What became due when the playhead moved
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackUpdate<'a> {
+    /// Tracking events to fire, in time order
+    pub tracking_events: Vec<&'a TrackingEvent>,
+    
+    /// Whether the ad just became skippable, so the skip button should be shown
+    pub skip_unlocked: bool,
+}
+
+/// Tracks the playhead of a linear ad, reporting each timed tracking event and
+/// the skip offset once, when the playhead reaches them
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::models::TrackingEventKind;
+/// use vast_parser::parser;
+/// use vast_parser::playback::PlaybackTracker;
+/// 
+/// let xml = std::fs::read_to_string("samples/sample_vast.xml").unwrap();
+/// let vast = parser::parse_vast(&xml).unwrap();
+/// let linear = vast.ads[0].inline.as_ref().unwrap().creatives[0].linear.as_ref().unwrap();
+/// 
+/// let mut tracker = PlaybackTracker::new(linear).unwrap();
+/// let update = tracker.advance(Duration::from_secs(2));
+/// assert_eq!(update.tracking_events[0].event, TrackingEventKind::Start);
+/// 
+/// // Quartiles of the 30 second ad are due at 7.5, 15 and 22.5 seconds
+/// let update = tracker.advance(Duration::from_secs(16));
+/// assert_eq!(update.tracking_events.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlaybackTracker<'a> {
+    /// Timed tracking events in time order
+    schedule: Vec<(Duration, &'a TrackingEvent)>,
+    
+    /// Index of the first event not yet reported
+    next: usize,
+    
+    /// When the ad may be skipped, if skippable
+    skip_time: Option<Duration>,
+    
+    /// Whether the skip offset was reached
+    skip_unlocked: bool,
+}
+
+impl<'a> PlaybackTracker<'a> {
+    /// Track a linear ad using its Duration, or None if it has none
+    pub fn new(linear: &'a Linear) -> Option<Self> {
+        let duration = linear.duration?.as_duration();
+        Some(Self::with_duration(linear, duration))
+    }
+    
+    /// Track a linear ad using the given duration, e.g. that of the media file being played
+    pub fn with_duration(linear: &'a Linear, duration: Duration) -> Self {
+        PlaybackTracker {
+            schedule: linear.timed_events(duration),
+            next: 0,
+            skip_time: linear.skip_time(duration),
+            skip_unlocked: false,
+        }
+    }
+    
+    /// Move the playhead, returning the events that became due
+    /// 
+    /// Events are reported once; moving the playhead back doesn't report them again.
+    pub fn advance(&mut self, playhead: Duration) -> PlaybackUpdate<'a> {
+        let mut tracking_events = Vec::new();
+        while let Some((time, event)) = self.schedule.get(self.next)
+            && *time <= playhead
+        {
+            tracking_events.push(*event);
+            self.next += 1;
+        }
+        
+        let skip_unlocked = !self.skip_unlocked && self.skip_time.is_some_and(|time| time <= playhead);
+        if skip_unlocked {
+            self.skip_unlocked = true;
+        }
+        
+        PlaybackUpdate {
+            tracking_events,
+            skip_unlocked,
+        }
+    }
+    
+    /// Whether the skip offset has been reached
+    pub fn can_skip(&self) -> bool {
+        self.skip_unlocked
+    }
+    
+    /// The timed events not yet reported
+    pub fn remaining(&self) -> impl Iterator<Item = &'a TrackingEvent> + '_ {
+        self.schedule[self.next..].iter().map(|(_, event)| *event)
+    }
+}
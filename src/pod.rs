@@ -0,0 +1,162 @@
+use crate::models::{Ad, Vast};
+use crate::unwrap::UnwrapReport;
+use log::debug;
+use std::time::Duration;
+
+/// Limits applied when planning an ad break from a pod
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::pod::PodRules;
+/// 
+/// let rules = PodRules::new()
+///     .with_max_duration(Duration::from_secs(60))
+///     .with_max_ads(3);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PodRules {
+    /// Longest total duration of the pod ads, if limited
+    pub max_duration: Option<Duration>,
+    
+    /// Maximum number of pod ads, if limited
+    pub max_ads: Option<usize>,
+}
+
+impl PodRules {
+    /// Rules without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the longest total duration of the pod ads
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+    
+    /// Set the maximum number of pod ads
+    pub fn with_max_ads(mut self, max_ads: usize) -> Self {
+        self.max_ads = Some(max_ads);
+        self
+    }
+}
+
+/// An ad of a pod with its position in the break
+#[derive(Debug, Clone, PartialEq)]
+pub struct PodAd<'a> {
+    /// The ad
+    pub ad: &'a Ad,
+    
+    /// The sequence numbers placing the ad in the pod, outermost wrapper first,
+    /// see `UnwrapReport::pod_positions`
+    pub position: Vec<u32>,
+}
+
+impl PodAd<'_> {
+    /// The duration of the ad's first Linear creative, if known
+    pub fn duration(&self) -> Option<Duration> {
+        ad_duration(self.ad)
+    }
+}
+
+/// The ads of a VAST response split into a pod, played in sequence, and a buffet of
+/// stand-alone ads the player may use in place of pod ads it can't play
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdPod<'a> {
+    /// The sequenced ads, ordered by position
+    pub pod: Vec<PodAd<'a>>,
+    
+    /// The stand-alone ads, in document order
+    pub buffet: Vec<&'a Ad>,
+}
+
+impl<'a> AdPod<'a> {
+    /// Split ads into a pod and a buffet by their pod positions
+    /// 
+    /// Ads with the same position keep their document order.
+    pub fn from_positions(ads: &'a [Ad], positions: &[Vec<u32>]) -> Self {
+        let mut pod = Vec::new();
+        let mut buffet = Vec::new();
+        
+        for (ad, position) in ads.iter().zip(positions) {
+            if position.is_empty() {
+                buffet.push(ad);
+            } else {
+                pod.push(PodAd { ad, position: position.clone() });
+            }
+        }
+        pod.sort_by(|a, b| a.position.cmp(&b.position));
+        
+        AdPod { pod, buffet }
+    }
+    
+    /// Keep the leading pod ads that fit the rules
+    /// 
+    /// The pod is cut at the first ad that would break a limit, so the sequence is never
+    /// reordered. When a duration limit is set, an ad without a known duration also ends the pod.
+    pub fn with_rules(mut self, rules: &PodRules) -> Self {
+        let mut total = Duration::ZERO;
+        let mut kept = 0;
+        
+        for pod_ad in &self.pod {
+            if rules.max_ads.is_some_and(|max_ads| kept >= max_ads) {
+                break;
+            }
+            
+            if let Some(max_duration) = rules.max_duration {
+                let Some(duration) = pod_ad.duration() else {
+                    debug!(ad_id = pod_ad.ad.id.as_deref(); "Pod ad has no known duration, ending the pod");
+                    break;
+                };
+                if total + duration > max_duration {
+                    break;
+                }
+                total += duration;
+            }
+            
+            kept += 1;
+        }
+        
+        if kept < self.pod.len() {
+            debug!(kept = kept, dropped = self.pod.len() - kept; "Pod cut to fit the pod rules");
+            self.pod.truncate(kept);
+        }
+        self
+    }
+    
+    /// Whether the response holds a pod rather than only stand-alone ads
+    pub fn is_pod(&self) -> bool {
+        !self.pod.is_empty()
+    }
+    
+    /// The total duration of the pod ads, if every one of them has a known duration
+    pub fn duration(&self) -> Option<Duration> {
+        self.pod.iter().map(PodAd::duration).sum()
+    }
+}
+
+impl Vast {
+    /// Split the ads of the document into a pod, ordered by `sequence`, and a buffet
+    pub fn ad_pod(&self) -> AdPod<'_> {
+        let positions: Vec<Vec<u32>> = self.ads.iter()
+            .map(|ad| ad.sequence.into_iter().collect())
+            .collect();
+        AdPod::from_positions(&self.ads, &positions)
+    }
+}
+
+impl UnwrapReport {
+    /// Split the unwrapped ads into a pod and a buffet, keeping the pod structure of the wrapper chain
+    pub fn ad_pod(&self) -> AdPod<'_> {
+        AdPod::from_positions(&self.vast.ads, &self.pod_positions)
+    }
+}
+
+/// The duration of an ad's first Linear creative
+fn ad_duration(ad: &Ad) -> Option<Duration> {
+    let inline = ad.inline.as_ref()?;
+    let duration = inline.creatives.iter()
+        .find_map(|creative| creative.linear.as_ref())?
+        .duration?;
+    Some(duration.as_duration())
+}
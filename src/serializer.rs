@@ -0,0 +1,955 @@
+use crate::error::{Result, VastError};
+use crate::models::*;
+use quick_xml::events::{BytesCData, BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use std::fmt::Display;
+use std::io::Write;
+use std::ops::RangeBounds;
+
+/// Default number of spaces per indentation level
+pub const DEFAULT_INDENT: usize = 2;
+
+/// Options controlling how a VAST document is written as XML
+/// 
+/// ```
+/// use vast_parser::serializer::WriteOptions;
+/// 
+/// // Single line output without the XML declaration
+/// let options = WriteOptions::minified()
+///     .with_xml_declaration(false);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Spaces per indentation level, or None to write everything on one line
+    pub indent: Option<usize>,
+    
+    /// Whether to start the document with `<?xml version="1.0" encoding="UTF-8"?>`
+    pub xml_declaration: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            indent: Some(DEFAULT_INDENT),
+            xml_declaration: true,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Indented output with an XML declaration
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Output without any indentation or line breaks
+    pub fn minified() -> Self {
+        Self::default().with_minify()
+    }
+    
+    /// Set the number of spaces per indentation level
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+    
+    /// Write everything on one line
+    pub fn with_minify(mut self) -> Self {
+        self.indent = None;
+        self
+    }
+    
+    /// Set whether to write the XML declaration
+    pub fn with_xml_declaration(mut self, xml_declaration: bool) -> Self {
+        self.xml_declaration = xml_declaration;
+        self
+    }
+}
+
+impl Vast {
+    /// Serialize the document as VAST XML
+    pub fn to_xml(&self, options: &WriteOptions) -> Result<String> {
+        let mut buffer = Vec::new();
+        write_vast(&mut buffer, self, options)?;
+        
+        String::from_utf8(buffer).map_err(|e| VastError::Other(format!("Serialized VAST is not UTF-8: {}", e)))
+    }
+}
+
+/// Write a VAST document as XML to the given writer
+pub fn write_vast<W: Write>(inner: W, vast: &Vast, options: &WriteOptions) -> Result<()> {
+    let writer = match options.indent {
+        Some(indent) => Writer::new_with_indent(inner, b' ', indent),
+        None => Writer::new(inner),
+    };
+    let mut xml = XmlWriter { writer };
+    
+    // XML declaration
+    if options.xml_declaration {
+        xml.writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    }
+    
+    // VAST root element
+    let mut root = BytesStart::new("VAST");
+    root.push_attribute(("version", vast.version.as_str()));
+    push_unknown(&mut root, &vast.unknown);
+    xml.start(root)?;
+    
+    // Error URL if present
+    if let Some(error) = &vast.error {
+        xml.cdata_element(BytesStart::new("Error"), error)?;
+    }
+    
+    // Add ads
+    for ad in &vast.ads {
+        write_ad(&mut xml, ad)?;
+    }
+    
+    xml.unknown_elements(&vast.unknown)?;
+    xml.end("VAST")
+}
+
+/// Thin layer over the quick-xml writer for the element shapes VAST uses
+struct XmlWriter<W: Write> {
+    writer: Writer<W>,
+}
+
+impl<W: Write> XmlWriter<W> {
+    /// Open an element
+    fn start(&mut self, element: BytesStart<'_>) -> Result<()> {
+        self.writer.write_event(Event::Start(element))?;
+        Ok(())
+    }
+    
+    /// Close an element
+    fn end(&mut self, name: &str) -> Result<()> {
+        self.writer.write_event(Event::End(BytesEnd::new(name)))?;
+        Ok(())
+    }
+    
+    /// Write an element whose content is escaped text
+    fn text_element(&mut self, element: BytesStart<'_>, text: &str) -> Result<()> {
+        let end = element.to_end().into_owned();
+        self.start(element)?;
+        self.writer.write_event(Event::Text(BytesText::new(text)))?;
+        self.writer.write_event(Event::End(end))?;
+        Ok(())
+    }
+    
+    /// Write an element whose content is wrapped in CDATA
+    fn cdata_element(&mut self, element: BytesStart<'_>, content: &str) -> Result<()> {
+        let end = element.to_end().into_owned();
+        self.start(element)?;
+        self.cdata(content)?;
+        self.writer.write_event(Event::End(end))?;
+        Ok(())
+    }
+    
+    /// Write the raw XML fragments kept in lossless mode
+    fn unknown_elements(&mut self, unknown: &UnknownXml) -> Result<()> {
+        for fragment in &unknown.elements {
+            self.raw(fragment)?;
+        }
+        Ok(())
+    }
+    
+    /// Write the unrecognized children of a list element kept in lossless mode whose
+    /// number of modeled children before them is in `indexes`
+    fn unknown_list_elements(&mut self, unknown: &UnknownXml, list: &str, indexes: impl RangeBounds<usize>) -> Result<()> {
+        for (_, fragment) in unknown.list_elements(list).iter().filter(|(index, _)| indexes.contains(index)) {
+            self.raw(fragment)?;
+        }
+        Ok(())
+    }
+    
+    /// Write a list element such as `<TrackingEvents>`, putting the unrecognized children
+    /// kept in lossless mode back between the modeled ones
+    /// 
+    /// Nothing is written for a list without any children.
+    fn list<T>(
+        &mut self,
+        name: &str,
+        items: &[T],
+        unknown: &UnknownXml,
+        mut write_item: impl FnMut(&mut Self, &T) -> Result<()>,
+    ) -> Result<()> {
+        if items.is_empty() && unknown.list_elements(name).is_empty() {
+            return Ok(());
+        }
+        
+        self.start(BytesStart::new(name))?;
+        for (index, item) in items.iter().enumerate() {
+            self.unknown_list_elements(unknown, name, index..=index)?;
+            write_item(self, item)?;
+        }
+        self.unknown_list_elements(unknown, name, items.len()..)?;
+        self.end(name)
+    }
+    
+    /// Write a raw XML fragment, re-indented to fit the document
+    fn raw(&mut self, fragment: &str) -> Result<()> {
+        let mut reader = Reader::from_str(fragment);
+        reader.trim_text(true);
+        loop {
+            match reader.read_event()? {
+                Event::Eof => break,
+                event => self.writer.write_event(event)?,
+            }
+        }
+        Ok(())
+    }
+    
+    /// Write content as CDATA, splitting the section wherever the content contains "]]>"
+    fn cdata(&mut self, content: &str) -> Result<()> {
+        let mut rest = content;
+        while let Some(position) = rest.find("]]>") {
+            // End the section between "]]" and ">" so neither half contains the terminator
+            let (head, tail) = rest.split_at(position + 2);
+            self.writer.write_event(Event::CData(BytesCData::new(head)))?;
+            rest = tail;
+        }
+        self.writer.write_event(Event::CData(BytesCData::new(rest)))?;
+        Ok(())
+    }
+}
+
+/// Add an attribute to an element if the value is present
+fn push_optional<T: Display>(element: &mut BytesStart<'_>, name: &str, value: &Option<T>) {
+    if let Some(value) = value {
+        element.push_attribute((name, value.to_string().as_str()));
+    }
+}
+
+/// Add the attributes kept in lossless mode to an element
+fn push_unknown(element: &mut BytesStart<'_>, unknown: &UnknownXml) {
+    for (name, value) in &unknown.attributes {
+        element.push_attribute((name.as_str(), value.as_str()));
+    }
+}
+
+/// Write an Ad
+fn write_ad<W: Write>(xml: &mut XmlWriter<W>, ad: &Ad) -> Result<()> {
+    // Open Ad element with attributes
+    let mut element = BytesStart::new("Ad");
+    push_optional(&mut element, "id", &ad.id);
+    push_optional(&mut element, "sequence", &ad.sequence);
+    push_optional(&mut element, "conditionalAd", &ad.conditional_ad);
+    push_unknown(&mut element, &ad.unknown);
+    xml.start(element)?;
+    
+    // Add InLine or Wrapper
+    if let Some(inline) = &ad.inline {
+        write_inline(xml, inline)?;
+    } else if let Some(wrapper) = &ad.wrapper {
+        write_wrapper(xml, wrapper)?;
+    }
+    
+    xml.unknown_elements(&ad.unknown)?;
+    xml.end("Ad")
+}
+
+/// Write an InLine
+fn write_inline<W: Write>(xml: &mut XmlWriter<W>, inline: &InLine) -> Result<()> {
+    let mut element = BytesStart::new("InLine");
+    push_unknown(&mut element, &inline.unknown);
+    xml.start(element)?;
+    
+    write_ad_system(xml, &inline.ad_system)?;
+    xml.text_element(BytesStart::new("AdTitle"), &inline.ad_title)?;
+    
+    // Add AdServingId if present
+    if let Some(ad_serving_id) = &inline.ad_serving_id {
+        xml.text_element(BytesStart::new("AdServingId"), ad_serving_id)?;
+    }
+    
+    // Add Description if present
+    if let Some(description) = &inline.description {
+        xml.text_element(BytesStart::new("Description"), description)?;
+    }
+    
+    // Add Advertiser if present
+    if let Some(advertiser) = &inline.advertiser {
+        xml.text_element(BytesStart::new("Advertiser"), advertiser)?;
+    }
+    
+    // Add Categories
+    write_categories(xml, "Category", &inline.categories)?;
+    
+    // Add Survey if present
+    if let Some(survey) = &inline.survey {
+        xml.cdata_element(BytesStart::new("Survey"), survey)?;
+    }
+    
+    // Add Expires if present
+    if let Some(expires) = &inline.expires {
+        xml.text_element(BytesStart::new("Expires"), &expires.to_string())?;
+    }
+    
+    write_impressions(xml, &inline.impressions)?;
+    
+    // Add ViewableImpression if present
+    if let Some(viewable_impression) = &inline.viewable_impression {
+        write_viewable_impression(xml, viewable_impression)?;
+    }
+    
+    // Add Errors
+    for error in &inline.error {
+        xml.cdata_element(BytesStart::new("Error"), error)?;
+    }
+    
+    // Add Pricing if present
+    if let Some(pricing) = &inline.pricing {
+        let mut element = BytesStart::new("Pricing");
+        element.push_attribute(("model", pricing.model.as_str()));
+        element.push_attribute(("currency", pricing.currency.as_str()));
+        push_unknown(&mut element, &pricing.unknown);
+        xml.text_element(element, &pricing.value)?;
+    }
+    
+    // Add AdVerifications if present
+    write_ad_verifications(xml, &inline.ad_verifications, &inline.unknown)?;
+    
+    // Add Extensions if present
+    write_extensions(xml, "Extensions", "Extension", &inline.extensions, &inline.unknown)?;
+    
+    // Add Creatives
+    write_creatives(xml, &inline.creatives, &inline.unknown)?;
+    
+    xml.unknown_elements(&inline.unknown)?;
+    xml.end("InLine")
+}
+
+/// Write a Wrapper
+fn write_wrapper<W: Write>(xml: &mut XmlWriter<W>, wrapper: &Wrapper) -> Result<()> {
+    // Open Wrapper element with attributes
+    let mut element = BytesStart::new("Wrapper");
+    push_optional(&mut element, "followAdditionalWrappers", &wrapper.follow_additional_wrappers);
+    push_optional(&mut element, "allowMultipleAds", &wrapper.allow_multiple_ads);
+    push_optional(&mut element, "fallbackOnNoAd", &wrapper.fallback_on_no_ad);
+    push_unknown(&mut element, &wrapper.unknown);
+    xml.start(element)?;
+    
+    write_ad_system(xml, &wrapper.ad_system)?;
+    xml.cdata_element(BytesStart::new("VASTAdTagURI"), &wrapper.vast_ad_tag_uri)?;
+    write_impressions(xml, &wrapper.impressions)?;
+    
+    // Add ViewableImpression if present
+    if let Some(viewable_impression) = &wrapper.viewable_impression {
+        write_viewable_impression(xml, viewable_impression)?;
+    }
+    
+    // Add Errors
+    for error in &wrapper.error {
+        xml.cdata_element(BytesStart::new("Error"), error)?;
+    }
+    
+    // Add BlockedAdCategories
+    write_categories(xml, "BlockedAdCategories", &wrapper.blocked_ad_categories)?;
+    
+    // Add AdVerifications if present
+    write_ad_verifications(xml, &wrapper.ad_verifications, &wrapper.unknown)?;
+    
+    // Add Extensions if present
+    write_extensions(xml, "Extensions", "Extension", &wrapper.extensions, &wrapper.unknown)?;
+    
+    // Add Creatives
+    write_creatives(xml, &wrapper.creatives, &wrapper.unknown)?;
+    
+    xml.unknown_elements(&wrapper.unknown)?;
+    xml.end("Wrapper")
+}
+
+/// Write an AdSystem
+fn write_ad_system<W: Write>(xml: &mut XmlWriter<W>, ad_system: &AdSystem) -> Result<()> {
+    let mut element = BytesStart::new("AdSystem");
+    push_optional(&mut element, "version", &ad_system.version);
+    push_unknown(&mut element, &ad_system.unknown);
+    xml.text_element(element, &ad_system.name)
+}
+
+/// Write a ViewableImpression
+fn write_viewable_impression<W: Write>(xml: &mut XmlWriter<W>, viewable_impression: &ViewableImpression) -> Result<()> {
+    let mut element = BytesStart::new("ViewableImpression");
+    push_optional(&mut element, "id", &viewable_impression.id);
+    push_unknown(&mut element, &viewable_impression.unknown);
+    xml.start(element)?;
+    
+    for url in &viewable_impression.viewable {
+        xml.cdata_element(BytesStart::new("Viewable"), url)?;
+    }
+    for url in &viewable_impression.not_viewable {
+        xml.cdata_element(BytesStart::new("NotViewable"), url)?;
+    }
+    for url in &viewable_impression.view_undetermined {
+        xml.cdata_element(BytesStart::new("ViewUndetermined"), url)?;
+    }
+    
+    xml.unknown_elements(&viewable_impression.unknown)?;
+    xml.end("ViewableImpression")
+}
+
+/// Write Category or BlockedAdCategories elements
+fn write_categories<W: Write>(xml: &mut XmlWriter<W>, name: &str, categories: &[Category]) -> Result<()> {
+    for category in categories {
+        let mut element = BytesStart::new(name);
+        push_optional(&mut element, "authority", &category.authority);
+        push_unknown(&mut element, &category.unknown);
+        xml.text_element(element, &category.value)?;
+    }
+    Ok(())
+}
+
+/// Write Impressions
+fn write_impressions<W: Write>(xml: &mut XmlWriter<W>, impressions: &[Impression]) -> Result<()> {
+    for impression in impressions {
+        let mut element = BytesStart::new("Impression");
+        push_optional(&mut element, "id", &impression.id);
+        push_unknown(&mut element, &impression.unknown);
+        xml.cdata_element(element, &impression.url)?;
+    }
+    Ok(())
+}
+
+/// Write Extensions or CreativeExtensions
+fn write_extensions<W: Write>(
+    xml: &mut XmlWriter<W>,
+    list_name: &str,
+    name: &str,
+    extensions: &[Extension],
+    unknown: &UnknownXml,
+) -> Result<()> {
+    xml.list(list_name, extensions, unknown, |xml, extension| {
+        let mut element = BytesStart::new(name);
+        push_optional(&mut element, "type", &extension.r#type);
+        for (attribute, value) in &extension.attributes {
+            element.push_attribute((attribute.as_str(), value.as_str()));
+        }
+        
+        if extension.content.is_empty() {
+            xml.writer.write_event(Event::Empty(element))?;
+            Ok(())
+        } else {
+            xml.start(element)?;
+            xml.raw(&extension.content)?;
+            xml.end(name)
+        }
+    })
+}
+
+/// Write AdVerifications
+fn write_ad_verifications<W: Write>(xml: &mut XmlWriter<W>, verifications: &[Verification], unknown: &UnknownXml) -> Result<()> {
+    xml.list("AdVerifications", verifications, unknown, |xml, verification| {
+        let mut element = BytesStart::new("Verification");
+        push_optional(&mut element, "vendor", &verification.vendor);
+        push_unknown(&mut element, &verification.unknown);
+        xml.start(element)?;
+        
+        // Add JavaScriptResources
+        for resource in &verification.javascript_resources {
+            let mut element = BytesStart::new("JavaScriptResource");
+            push_optional(&mut element, "apiFramework", &resource.api_framework);
+            push_optional(&mut element, "browserOptional", &resource.browser_optional);
+            push_unknown(&mut element, &resource.unknown);
+            xml.cdata_element(element, &resource.url)?;
+        }
+        
+        // Add ExecutableResources
+        for resource in &verification.executable_resources {
+            let mut element = BytesStart::new("ExecutableResource");
+            push_optional(&mut element, "apiFramework", &resource.api_framework);
+            push_optional(&mut element, "type", &resource.r#type);
+            push_unknown(&mut element, &resource.unknown);
+            xml.cdata_element(element, &resource.url)?;
+        }
+        
+        // Add TrackingEvents if present
+        write_tracking_events(xml, &verification.tracking_events, &verification.unknown)?;
+        
+        // Add VerificationParameters if present
+        if let Some(parameters) = &verification.verification_parameters {
+            xml.cdata_element(BytesStart::new("VerificationParameters"), parameters)?;
+        }
+        
+        xml.unknown_elements(&verification.unknown)?;
+        xml.end("Verification")
+    })
+}
+
+/// Write Creatives
+fn write_creatives<W: Write>(xml: &mut XmlWriter<W>, creatives: &[Creative], unknown: &UnknownXml) -> Result<()> {
+    xml.list("Creatives", creatives, unknown, write_creative)
+}
+
+/// Write a Creative
+fn write_creative<W: Write>(xml: &mut XmlWriter<W>, creative: &Creative) -> Result<()> {
+    // Open Creative element with attributes
+    let mut element = BytesStart::new("Creative");
+    push_optional(&mut element, "id", &creative.id);
+    push_optional(&mut element, "sequence", &creative.sequence);
+    push_optional(&mut element, "adId", &creative.ad_id);
+    push_optional(&mut element, "apiFramework", &creative.api_framework);
+    push_unknown(&mut element, &creative.unknown);
+    xml.start(element)?;
+    
+    // Add UniversalAdIds
+    for universal_ad_id in &creative.universal_ad_ids {
+        let mut element = BytesStart::new("UniversalAdId");
+        element.push_attribute(("idRegistry", universal_ad_id.id_registry.as_str()));
+        push_unknown(&mut element, &universal_ad_id.unknown);
+        xml.text_element(element, &universal_ad_id.id)?;
+    }
+    
+    // Add CreativeExtensions if present
+    write_extensions(xml, "CreativeExtensions", "CreativeExtension", &creative.creative_extensions, &creative.unknown)?;
+    
+    // Add Linear if present
+    if let Some(linear) = &creative.linear {
+        write_linear(xml, linear)?;
+    }
+    
+    // Add CompanionAds if present
+    if let Some(companion_ads) = &creative.companion_ads {
+        write_companion_ads(xml, companion_ads)?;
+    }
+    
+    // Add NonLinearAds if present
+    if let Some(non_linear_ads) = &creative.non_linear_ads {
+        write_non_linear_ads(xml, non_linear_ads)?;
+    }
+    
+    xml.unknown_elements(&creative.unknown)?;
+    xml.end("Creative")
+}
+
+/// Write a Linear
+fn write_linear<W: Write>(xml: &mut XmlWriter<W>, linear: &Linear) -> Result<()> {
+    let mut element = BytesStart::new("Linear");
+    push_optional(&mut element, "skipoffset", &linear.skip_offset);
+    push_unknown(&mut element, &linear.unknown);
+    xml.start(element)?;
+    
+    // Add Duration if present
+    if let Some(duration) = &linear.duration {
+        xml.text_element(BytesStart::new("Duration"), &duration.to_string())?;
+    }
+    
+    // Add TrackingEvents if present
+    write_tracking_events(xml, &linear.tracking_events, &linear.unknown)?;
+    
+    // Add VideoClicks if present
+    if let Some(video_clicks) = &linear.video_clicks {
+        xml.start(BytesStart::new("VideoClicks"))?;
+        
+        if let Some(click_through) = &video_clicks.click_through {
+            xml.cdata_element(BytesStart::new("ClickThrough"), click_through)?;
+        }
+        
+        for url in &video_clicks.click_tracking {
+            xml.cdata_element(BytesStart::new("ClickTracking"), url)?;
+        }
+        
+        for url in &video_clicks.custom_click {
+            xml.cdata_element(BytesStart::new("CustomClick"), url)?;
+        }
+        
+        xml.unknown_elements(&video_clicks.unknown)?;
+        xml.end("VideoClicks")?;
+    }
+    
+    // Add MediaFiles if present
+    write_media_files(xml, linear)?;
+    
+    // Add Icons if present
+    write_icons(xml, &linear.icons, &linear.unknown)?;
+    
+    xml.unknown_elements(&linear.unknown)?;
+    xml.end("Linear")
+}
+
+/// Write the MediaFiles of a Linear
+fn write_media_files<W: Write>(xml: &mut XmlWriter<W>, linear: &Linear) -> Result<()> {
+    let has_closed_caption_files = !linear.closed_caption_files.is_empty()
+        || !linear.unknown.list_elements("ClosedCaptionFiles").is_empty();
+    let has_media_files = !linear.media_files.is_empty()
+        || !linear.mezzanines.is_empty()
+        || !linear.interactive_creative_files.is_empty()
+        || has_closed_caption_files
+        || !linear.unknown.list_elements("MediaFiles").is_empty();
+    if !has_media_files {
+        return Ok(());
+    }
+    
+    xml.start(BytesStart::new("MediaFiles"))?;
+    
+    // The number of modeled children written so far, to place unrecognized ones
+    let mut index = 0;
+    
+    for media_file in &linear.media_files {
+        xml.unknown_list_elements(&linear.unknown, "MediaFiles", index..=index)?;
+        index += 1;
+        
+        let mut element = BytesStart::new("MediaFile");
+        push_optional(&mut element, "id", &media_file.id);
+        element.push_attribute(("type", media_file.mime_type.as_str()));
+        push_optional(&mut element, "delivery", &media_file.delivery);
+        push_optional(&mut element, "width", &media_file.width);
+        push_optional(&mut element, "height", &media_file.height);
+        push_optional(&mut element, "codec", &media_file.codec);
+        push_optional(&mut element, "bitrate", &media_file.bitrate);
+        push_optional(&mut element, "minBitrate", &media_file.min_bitrate);
+        push_optional(&mut element, "maxBitrate", &media_file.max_bitrate);
+        push_optional(&mut element, "mediaType", &media_file.r#type);
+        push_optional(&mut element, "apiFramework", &media_file.api_framework);
+        push_optional(&mut element, "scalable", &media_file.scalable);
+        push_optional(&mut element, "maintainAspectRatio", &media_file.maintain_aspect_ratio);
+        push_optional(&mut element, "fileSize", &media_file.file_size);
+        push_unknown(&mut element, &media_file.unknown);
+        xml.cdata_element(element, &media_file.url)?;
+    }
+    
+    for mezzanine in &linear.mezzanines {
+        xml.unknown_list_elements(&linear.unknown, "MediaFiles", index..=index)?;
+        index += 1;
+        
+        let mut element = BytesStart::new("Mezzanine");
+        push_optional(&mut element, "id", &mezzanine.id);
+        push_optional(&mut element, "delivery", &mezzanine.delivery);
+        element.push_attribute(("type", mezzanine.mime_type.as_str()));
+        push_optional(&mut element, "width", &mezzanine.width);
+        push_optional(&mut element, "height", &mezzanine.height);
+        push_optional(&mut element, "codec", &mezzanine.codec);
+        push_optional(&mut element, "fileSize", &mezzanine.file_size);
+        push_optional(&mut element, "mediaType", &mezzanine.r#type);
+        push_unknown(&mut element, &mezzanine.unknown);
+        xml.cdata_element(element, &mezzanine.url)?;
+    }
+    
+    for interactive_creative_file in &linear.interactive_creative_files {
+        xml.unknown_list_elements(&linear.unknown, "MediaFiles", index..=index)?;
+        index += 1;
+        
+        let mut element = BytesStart::new("InteractiveCreativeFile");
+        push_optional(&mut element, "type", &interactive_creative_file.mime_type);
+        push_optional(&mut element, "apiFramework", &interactive_creative_file.api_framework);
+        push_optional(&mut element, "variableDuration", &interactive_creative_file.variable_duration);
+        push_unknown(&mut element, &interactive_creative_file.unknown);
+        xml.cdata_element(element, &interactive_creative_file.url)?;
+    }
+    
+    if has_closed_caption_files {
+        xml.unknown_list_elements(&linear.unknown, "MediaFiles", index..=index)?;
+        index += 1;
+        
+        xml.list("ClosedCaptionFiles", &linear.closed_caption_files, &linear.unknown, |xml, closed_caption_file| {
+            let mut element = BytesStart::new("ClosedCaptionFile");
+            push_optional(&mut element, "type", &closed_caption_file.mime_type);
+            push_optional(&mut element, "language", &closed_caption_file.language);
+            push_unknown(&mut element, &closed_caption_file.unknown);
+            xml.cdata_element(element, &closed_caption_file.url)
+        })?;
+    }
+    
+    xml.unknown_list_elements(&linear.unknown, "MediaFiles", index..)?;
+    xml.end("MediaFiles")
+}
+
+/// Write TrackingEvents
+fn write_tracking_events<W: Write>(xml: &mut XmlWriter<W>, tracking_events: &[TrackingEvent], unknown: &UnknownXml) -> Result<()> {
+    xml.list("TrackingEvents", tracking_events, unknown, |xml, event| {
+        let mut element = BytesStart::new("Tracking");
+        element.push_attribute(("event", event.event.as_str()));
+        push_optional(&mut element, "offset", &event.offset);
+        push_unknown(&mut element, &event.unknown);
+        xml.cdata_element(element, &event.url)
+    })
+}
+
+/// Write CompanionAds
+fn write_companion_ads<W: Write>(xml: &mut XmlWriter<W>, companion_ads: &CompanionAds) -> Result<()> {
+    let mut element = BytesStart::new("CompanionAds");
+    push_optional(&mut element, "required", &companion_ads.required);
+    push_unknown(&mut element, &companion_ads.unknown);
+    xml.start(element)?;
+    
+    for companion in &companion_ads.companions {
+        let mut element = BytesStart::new("Companion");
+        push_optional(&mut element, "id", &companion.id);
+        element.push_attribute(("width", companion.width.to_string().as_str()));
+        element.push_attribute(("height", companion.height.to_string().as_str()));
+        push_optional(&mut element, "assetWidth", &companion.asset_width);
+        push_optional(&mut element, "assetHeight", &companion.asset_height);
+        push_optional(&mut element, "adSlotID", &companion.ad_slot_id);
+        push_optional(&mut element, "apiFramework", &companion.api_framework);
+        push_unknown(&mut element, &companion.unknown);
+        xml.start(element)?;
+        
+        // Add resources
+        for resource in &companion.resources {
+            write_resource(xml, resource)?;
+        }
+        
+        // Add AdParameters if present
+        if let Some(ad_parameters) = &companion.ad_parameters {
+            write_ad_parameters(xml, ad_parameters)?;
+        }
+        
+        // Add AltText if present
+        if let Some(alt_text) = &companion.alt_text {
+            xml.text_element(BytesStart::new("AltText"), alt_text)?;
+        }
+        
+        // Add ClickThrough if present
+        if let Some(click_through) = &companion.click_through {
+            xml.cdata_element(BytesStart::new("CompanionClickThrough"), click_through)?;
+        }
+        
+        // Add ClickTracking
+        for url in &companion.click_tracking {
+            xml.cdata_element(BytesStart::new("CompanionClickTracking"), url)?;
+        }
+        
+        // Add TrackingEvents if present
+        write_tracking_events(xml, &companion.tracking_events, &companion.unknown)?;
+        
+        xml.unknown_elements(&companion.unknown)?;
+        xml.end("Companion")?;
+    }
+    
+    xml.unknown_elements(&companion_ads.unknown)?;
+    xml.end("CompanionAds")
+}
+
+/// Write NonLinearAds
+fn write_non_linear_ads<W: Write>(xml: &mut XmlWriter<W>, non_linear_ads: &NonLinearAds) -> Result<()> {
+    let mut element = BytesStart::new("NonLinearAds");
+    push_unknown(&mut element, &non_linear_ads.unknown);
+    xml.start(element)?;
+    
+    // Add TrackingEvents if present
+    write_tracking_events(xml, &non_linear_ads.tracking_events, &non_linear_ads.unknown)?;
+    
+    for non_linear in &non_linear_ads.non_linears {
+        let mut element = BytesStart::new("NonLinear");
+        push_optional(&mut element, "id", &non_linear.id);
+        element.push_attribute(("width", non_linear.width.to_string().as_str()));
+        element.push_attribute(("height", non_linear.height.to_string().as_str()));
+        push_optional(&mut element, "expandedWidth", &non_linear.expanded_width);
+        push_optional(&mut element, "expandedHeight", &non_linear.expanded_height);
+        push_optional(&mut element, "scalable", &non_linear.scalable);
+        push_optional(&mut element, "maintainAspectRatio", &non_linear.maintain_aspect_ratio);
+        push_optional(&mut element, "minSuggestedDuration", &non_linear.min_suggested_duration);
+        push_optional(&mut element, "apiFramework", &non_linear.api_framework);
+        push_unknown(&mut element, &non_linear.unknown);
+        xml.start(element)?;
+        
+        // Add resources
+        for resource in &non_linear.resources {
+            write_resource(xml, resource)?;
+        }
+        
+        // Add AdParameters if present
+        if let Some(ad_parameters) = &non_linear.ad_parameters {
+            write_ad_parameters(xml, ad_parameters)?;
+        }
+        
+        // Add ClickThrough if present
+        if let Some(click_through) = &non_linear.click_through {
+            xml.cdata_element(BytesStart::new("NonLinearClickThrough"), click_through)?;
+        }
+        
+        // Add ClickTracking
+        for url in &non_linear.click_tracking {
+            xml.cdata_element(BytesStart::new("NonLinearClickTracking"), url)?;
+        }
+        
+        // Add Icons if present
+        write_icons(xml, &non_linear.icons, &non_linear.unknown)?;
+        
+        xml.unknown_elements(&non_linear.unknown)?;
+        xml.end("NonLinear")?;
+    }
+    
+    xml.unknown_elements(&non_linear_ads.unknown)?;
+    xml.end("NonLinearAds")
+}
+
+/// Write Icons
+fn write_icons<W: Write>(xml: &mut XmlWriter<W>, icons: &[Icon], unknown: &UnknownXml) -> Result<()> {
+    xml.list("Icons", icons, unknown, |xml, icon| {
+        let mut element = BytesStart::new("Icon");
+        push_optional(&mut element, "program", &icon.program);
+        element.push_attribute(("width", icon.width.to_string().as_str()));
+        element.push_attribute(("height", icon.height.to_string().as_str()));
+        push_optional(&mut element, "xPosition", &icon.x_position);
+        push_optional(&mut element, "yPosition", &icon.y_position);
+        push_optional(&mut element, "duration", &icon.duration);
+        push_optional(&mut element, "offset", &icon.offset);
+        push_optional(&mut element, "apiFramework", &icon.api_framework);
+        push_optional(&mut element, "pxratio", &icon.pxratio);
+        push_unknown(&mut element, &icon.unknown);
+        xml.start(element)?;
+        
+        // Add resources
+        for resource in &icon.resources {
+            write_resource(xml, resource)?;
+        }
+        
+        // Add IconClicks if present
+        if let Some(icon_clicks) = &icon.icon_clicks {
+            xml.start(BytesStart::new("IconClicks"))?;
+            
+            xml.list("IconClickFallbackImages", &icon_clicks.fallback_images, &icon_clicks.unknown, |xml, image| {
+                let mut element = BytesStart::new("IconClickFallbackImage");
+                push_optional(&mut element, "width", &image.width);
+                push_optional(&mut element, "height", &image.height);
+                push_unknown(&mut element, &image.unknown);
+                xml.start(element)?;
+                if let Some(alt_text) = &image.alt_text {
+                    xml.text_element(BytesStart::new("AltText"), alt_text)?;
+                }
+                if let Some(static_resource) = &image.static_resource {
+                    write_resource(xml, static_resource)?;
+                }
+                xml.unknown_elements(&image.unknown)?;
+                xml.end("IconClickFallbackImage")
+            })?;
+            
+            if let Some(click_through) = &icon_clicks.click_through {
+                xml.cdata_element(BytesStart::new("IconClickThrough"), click_through)?;
+            }
+            
+            for url in &icon_clicks.click_tracking {
+                xml.cdata_element(BytesStart::new("IconClickTracking"), url)?;
+            }
+            
+            xml.unknown_elements(&icon_clicks.unknown)?;
+            xml.end("IconClicks")?;
+        }
+        
+        // Add IconViewTracking
+        for url in &icon.view_tracking {
+            xml.cdata_element(BytesStart::new("IconViewTracking"), url)?;
+        }
+        
+        xml.unknown_elements(&icon.unknown)?;
+        xml.end("Icon")
+    })
+}
+
+/// Write a StaticResource, IFrameResource or HTMLResource
+fn write_resource<W: Write>(xml: &mut XmlWriter<W>, resource: &Resource) -> Result<()> {
+    let mut element = BytesStart::new(resource.resource_type.as_str());
+    push_optional(&mut element, "creativeType", &resource.creative_type);
+    push_unknown(&mut element, &resource.unknown);
+    xml.cdata_element(element, &resource.content)
+}
+
+/// Write AdParameters
+fn write_ad_parameters<W: Write>(xml: &mut XmlWriter<W>, ad_parameters: &AdParameters) -> Result<()> {
+    let mut element = BytesStart::new("AdParameters");
+    push_optional(&mut element, "xmlEncoded", &ad_parameters.xml_encoded);
+    push_unknown(&mut element, &ad_parameters.unknown);
+    xml.cdata_element(element, &ad_parameters.content)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{self, ParseOptions};
+    
+    /// A document with a vendor attribute on each element and a vendor child in each container
+    const VENDOR_XML: &str = r#"<VAST version="4.1" xmlns:acme="urn:acme" acme:at="vast">
+        <Ad id="1" acme:at="ad">
+            <InLine acme:at="inline">
+                <AdSystem version="1" acme:at="ad-system">Acme</AdSystem>
+                <AdTitle>Title</AdTitle>
+                <Impression id="i" acme:at="impression"><![CDATA[https://example.com/imp]]></Impression>
+                <ViewableImpression acme:at="viewable-impression">
+                    <Viewable>https://example.com/viewable</Viewable>
+                    <acme:In at="viewable-impression"/>
+                </ViewableImpression>
+                <Category authority="https://iabtechlab.com" acme:at="category">IAB1</Category>
+                <Pricing model="CPM" currency="USD" acme:at="pricing">1.00</Pricing>
+                <AdVerifications>
+                    <Verification vendor="v">
+                        <JavaScriptResource apiFramework="omid" acme:at="javascript-resource">https://example.com/v.js</JavaScriptResource>
+                        <ExecutableResource type="exe" acme:at="executable-resource">https://example.com/v.exe</ExecutableResource>
+                    </Verification>
+                    <acme:In at="ad-verifications"/>
+                </AdVerifications>
+                <Extensions>
+                    <Extension type="acme">data</Extension>
+                    <acme:In at="extensions"/>
+                </Extensions>
+                <Creatives>
+                    <Creative id="c">
+                        <UniversalAdId idRegistry="ad-id.org" acme:at="universal-ad-id">ABC</UniversalAdId>
+                        <CreativeExtensions><acme:In at="creative-extensions"/></CreativeExtensions>
+                        <Linear>
+                            <Duration>00:00:15</Duration>
+                            <AdParameters acme:at="ad-parameters">params</AdParameters>
+                            <TrackingEvents>
+                                <Tracking event="start" acme:at="tracking">https://example.com/start</Tracking>
+                                <acme:In at="tracking-events"/>
+                            </TrackingEvents>
+                            <MediaFiles>
+                                <MediaFile delivery="progressive" type="video/mp4" acme:at="media-file">https://example.com/ad.mp4</MediaFile>
+                                <ClosedCaptionFiles><acme:In at="closed-caption-files"/></ClosedCaptionFiles>
+                                <acme:In at="media-files"/>
+                            </MediaFiles>
+                            <Icons>
+                                <Icon program="p">
+                                    <StaticResource creativeType="image/png" acme:at="resource">https://example.com/i.png</StaticResource>
+                                    <IconClicks>
+                                        <IconClickFallbackImages><acme:In at="icon-click-fallback-images"/></IconClickFallbackImages>
+                                    </IconClicks>
+                                </Icon>
+                                <acme:In at="icons"/>
+                            </Icons>
+                        </Linear>
+                    </Creative>
+                    <acme:In at="creatives"/>
+                </Creatives>
+            </InLine>
+        </Ad>
+    </VAST>"#;
+    
+    #[test]
+    fn lossless_mode_round_trips_vendor_xml_at_each_level() {
+        let options = ParseOptions::new().with_lossless(true);
+        let vast = parser::parse_vast_with_options(VENDOR_XML, &options).unwrap();
+        let xml = vast.to_xml(&super::WriteOptions::minified()).unwrap();
+        
+        for level in [
+            "vast", "ad", "inline", "ad-system", "impression", "viewable-impression", "category",
+            "pricing", "javascript-resource", "executable-resource", "ad-verifications", "extensions",
+            "universal-ad-id", "creative-extensions", "ad-parameters", "tracking", "tracking-events",
+            "media-file", "closed-caption-files", "media-files", "resource",
+            "icon-click-fallback-images", "icons", "creatives",
+        ] {
+            assert!(xml.contains(&format!("at=\"{}\"", level)), "vendor XML at {} was dropped: {}", level, xml);
+        }
+        
+        // Writing the document back out is stable
+        assert_eq!(parser::parse_vast_with_options(&xml, &options).unwrap(), vast);
+    }
+    
+    #[test]
+    fn lossless_mode_writes_unknown_list_children_back_in_place() {
+        let xml = r#"<VAST version="4.1"><Ad><InLine><AdSystem>Acme</AdSystem><AdTitle>Title</AdTitle><Creatives><Creative><Linear><TrackingEvents><acme:In xmlns:acme="urn:a">x</acme:In><Tracking event="start">https://example.com/start</Tracking><acme:Mid xmlns:acme="urn:a"/><Tracking event="complete">https://example.com/complete</Tracking></TrackingEvents><MediaFiles><MediaFile type="video/mp4">https://example.com/ad.mp4</MediaFile><acme:File xmlns:acme="urn:a">https://example.com/ad.acme</acme:File><ClosedCaptionFiles><acme:Captions xmlns:acme="urn:a"/></ClosedCaptionFiles></MediaFiles></Linear></Creative><acme:Last xmlns:acme="urn:a"/></Creatives></InLine></Ad></VAST>"#;
+        let options = ParseOptions::new().with_lossless(true);
+        let vast = parser::parse_vast_with_options(xml, &options).unwrap();
+        let written = vast.to_xml(&super::WriteOptions::minified().with_xml_declaration(false)).unwrap();
+        
+        let expected = concat!(
+            r#"<VAST version="4.1"><Ad><InLine><AdSystem>Acme</AdSystem><AdTitle>Title</AdTitle><Creatives><Creative><Linear>"#,
+            r#"<TrackingEvents><acme:In xmlns:acme="urn:a">x</acme:In>"#,
+            r#"<Tracking event="start"><![CDATA[https://example.com/start]]></Tracking><acme:Mid xmlns:acme="urn:a"/>"#,
+            r#"<Tracking event="complete"><![CDATA[https://example.com/complete]]></Tracking></TrackingEvents>"#,
+            r#"<MediaFiles><MediaFile type="video/mp4"><![CDATA[https://example.com/ad.mp4]]></MediaFile>"#,
+            r#"<acme:File xmlns:acme="urn:a">https://example.com/ad.acme</acme:File>"#,
+            r#"<ClosedCaptionFiles><acme:Captions xmlns:acme="urn:a"/></ClosedCaptionFiles></MediaFiles>"#,
+            r#"</Linear></Creative><acme:Last xmlns:acme="urn:a"/></Creatives></InLine></Ad></VAST>"#,
+        );
+        assert_eq!(written, expected);
+    }
+}
@@ -1,657 +1,834 @@
-use crate::error::Result;
+use crate::error::{Result, VastError};
+use crate::fetcher::{AsyncVastFetcher, DefaultFetcher, VastFetcher};
 use crate::models::*;
-use crate::unwrap;
-use std::collections::{HashMap, VecDeque};
+use crate::parser;
+use crate::serializer::WriteOptions;
+use crate::unwrap::{self, UnwrapOptions, UnwrapReport};
+use log::warn;
+use std::collections::HashSet;
+use std::str::FromStr;
+use url::Url;
 
 /// Stitch together a new VAST XML that combines the InLine ad with all the wrapper chain elements
 pub fn stitch_vast(xml_content: &str) -> Result<String> {
-    // First, collect all wrapper tracking elements
-    let wrapper_tracking = collect_wrapper_tracking(xml_content)?;
-    
-    // Then, unwrap the VAST to find the InLine ad
-    let unwrapped_vast = unwrap::unwrap_vast(xml_content)?;
-    
-    // Now stitch together the final VAST
-    let stitched_vast = stitch_vast_from_unwrapped(unwrapped_vast, wrapper_tracking)?;
-    
-    // Convert to XML
-    vast_to_xml(&stitched_vast)
+    stitch_vast_with_options(xml_content, &UnwrapOptions::default())
 }
 
 /// Async version of stitch_vast that combines the InLine ad with all the wrapper chain elements
 pub async fn stitch_vast_async(xml_content: &str) -> Result<String> {
-    // First, collect all wrapper tracking elements asynchronously
-    let wrapper_tracking = collect_wrapper_tracking_async(xml_content).await?;
+    stitch_vast_async_with_options(xml_content, &UnwrapOptions::default()).await
+}
+
+/// Stitch a VAST document like `stitch_vast`, following the chain within the limits of the given options
+pub fn stitch_vast_with_options(xml_content: &str, options: &UnwrapOptions) -> Result<String> {
+    stitch_vast_with_fetcher(xml_content, &DefaultFetcher, options)
+}
+
+/// Async version of stitch_vast_with_options
+pub async fn stitch_vast_async_with_options(xml_content: &str, options: &UnwrapOptions) -> Result<String> {
+    stitch_vast_async_with_fetcher(xml_content, &DefaultFetcher, options).await
+}
+
+/// Stitch a VAST document like `stitch_vast_with_options`, resolving VASTAdTagURIs with the given fetcher
+pub fn stitch_vast_with_fetcher(xml_content: &str, fetcher: &dyn VastFetcher, options: &UnwrapOptions) -> Result<String> {
+    // Make sure the initial document is valid before following the chain
+    parser::parse_vast(xml_content)?;
     
-    // Then, unwrap the VAST to find the InLine ad asynchronously
-    let unwrapped_vast = unwrap::unwrap_vast_async(xml_content).await?;
+    // Unwrap the VAST to find the InLine ad, keeping the wrappers that were followed
+    let report = unwrap::unwrap_vast_with_errors(xml_content, fetcher, options)?;
     
     // Now stitch together the final VAST
-    let stitched_vast = stitch_vast_from_unwrapped(unwrapped_vast, wrapper_tracking)?;
-    
-    // Convert to XML
-    vast_to_xml(&stitched_vast)
+    stitch_report(report, options.dedup)
 }
 
-/// Structure to hold wrapper tracking information
-#[derive(Default)]
-struct WrapperTracking {
-    impressions: Vec<Impression>,
-    error_urls: Vec<String>,
-    tracking_events: HashMap<String, Vec<String>>, // event -> URLs
-    click_tracking: Vec<String>,
-    custom_click: Vec<String>,
+/// Async version of stitch_vast_with_fetcher
+pub async fn stitch_vast_async_with_fetcher(
+    xml_content: &str,
+    fetcher: &dyn AsyncVastFetcher,
+    options: &UnwrapOptions,
+) -> Result<String> {
+    // Make sure the initial document is valid before following the chain
+    parser::parse_vast(xml_content)?;
+    
+    // Unwrap the VAST to find the InLine ad asynchronously, keeping the wrappers that were followed
+    let report = unwrap::unwrap_vast_with_errors_async(xml_content, fetcher, options).await?;
+    
+    // Now stitch together the final VAST
+    stitch_report(report, options.dedup)
 }
 
-/// Collect tracking information from all wrappers in the chain
-fn collect_wrapper_tracking(xml_content: &str) -> Result<WrapperTracking> {
-    let mut result = WrapperTracking::default();
-    collect_wrapper_tracking_recursive(xml_content, &mut result, &mut Vec::new())?;
-    Ok(result)
+/// How tracking URLs that appear more than once in a stitched ad are deduplicated
+/// 
+/// Only repeats of the same beacon are dropped: for tracking events, the event and offset
+/// must match too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UrlDedup {
+    /// Keep every URL
+    #[default]
+    None,
+    
+    /// Drop URLs identical to an earlier one
+    Exact,
+    
+    /// Drop URLs equal to an earlier one once normalized: scheme and host lowercased,
+    /// default port, fragment and surrounding whitespace removed, query parameters sorted
+    Normalized,
 }
 
-/// Async version to collect tracking information from all wrappers in the chain
-/// Uses an iterative approach instead of recursion to avoid issues with async recursion
-async fn collect_wrapper_tracking_async(xml_content: &str) -> Result<WrapperTracking> {
-    let mut result = WrapperTracking::default();
-    let mut visited_urls = Vec::new();
-    
-    // Use a queue for breadth-first traversal instead of recursion
-    let mut queue = VecDeque::new();
-    queue.push_back(xml_content.to_string());
-    
-    while let Some(current_xml) = queue.pop_front() {
-        // Parse the VAST XML
-        let vast = crate::parser::parse_vast(&current_xml)?;
-        
-        // Process each ad
-        for ad in vast.ads {
-            if let Some(wrapper) = ad.wrapper {
-                // Extract tracking information from this wrapper
-                extract_wrapper_tracking(&wrapper, &mut result);
-                
-                // Check if we've seen this URL before to avoid cycles
-                if visited_urls.contains(&wrapper.vast_ad_tag_uri) {
-                    continue;
-                }
-                
-                // Add this URL to the visited list
-                visited_urls.push(wrapper.vast_ad_tag_uri.clone());
-                
-                // Fetch the next VAST XML asynchronously
-                match unwrap::fetch_vast_content_async(&wrapper.vast_ad_tag_uri).await {
-                    Ok(next_xml) => {
-                        // Add to the queue for processing
-                        queue.push_back(next_xml);
-                    }
-                    Err(_) => {
-                        // If we can't fetch the next XML, just continue
-                        continue;
-                    }
-                }
-            }
+impl FromStr for UrlDedup {
+    type Err = VastError;
+    
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "none" => Ok(UrlDedup::None),
+            "exact" => Ok(UrlDedup::Exact),
+            "normalized" => Ok(UrlDedup::Normalized),
+            _ => Err(VastError::Other(format!("Unknown deduplication mode: {}", value))),
         }
     }
-    
-    Ok(result)
 }
 
-/// Helper function to recursively collect wrapper tracking
-fn collect_wrapper_tracking_recursive(
-    xml_content: &str, 
-    result: &mut WrapperTracking,
-    visited_urls: &mut Vec<String>
-) -> Result<()> {
-    // Parse the VAST XML
-    let vast = crate::parser::parse_vast(xml_content)?;
-    
-    // Process each ad
-    for ad in vast.ads {
-        if let Some(wrapper) = ad.wrapper {
-            // Extract tracking information from this wrapper
-            extract_wrapper_tracking(&wrapper, result);
-            
-            // Check if we've seen this URL before to avoid cycles
-            if visited_urls.contains(&wrapper.vast_ad_tag_uri) {
-                continue;
-            }
-            
-            // Add this URL to the visited list
-            visited_urls.push(wrapper.vast_ad_tag_uri.clone());
-            
-            // Fetch the next VAST XML
-            match fetch_vast_content(&wrapper.vast_ad_tag_uri) {
-                Ok(next_xml) => {
-                    // Recursively collect tracking from the next level
-                    collect_wrapper_tracking_recursive(&next_xml, result, visited_urls)?;
-                }
-                Err(_) => {
-                    // If we can't fetch the next XML, just continue
-                    continue;
-                }
-            }
+impl UrlDedup {
+    /// The key two URLs are compared by, or None when not deduplicating
+    fn key(self, url: &str) -> Option<String> {
+        match self {
+            UrlDedup::None => None,
+            UrlDedup::Exact => Some(url.to_string()),
+            UrlDedup::Normalized => Some(normalize_url(url)),
         }
     }
     
-    Ok(())
+    /// Drop the items whose URL repeats an earlier one, keeping the first
+    fn apply<T>(self, items: &mut Vec<T>, url: impl Fn(&T) -> &str) {
+        let mut seen = HashSet::new();
+        items.retain(|item| match self.key(url(item)) {
+            Some(key) => seen.insert(key),
+            None => true,
+        });
+    }
 }
 
-/// Extract tracking information from a wrapper
-fn extract_wrapper_tracking(wrapper: &Wrapper, result: &mut WrapperTracking) {
-    // Add impressions
-    for impression in &wrapper.impressions {
-        result.impressions.push(impression.clone());
-    }
-    
-    // Add error URL if present
-    if let Some(error) = &wrapper.error {
-        result.error_urls.push(error.clone());
-    }
+/// Normalize a URL for deduplication, see `UrlDedup::Normalized`
+fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    
+    // The parser already lowercases the scheme and host and drops the default port
+    parsed.set_fragment(None);
+    
+    let mut pairs: Vec<String> = parsed.query()
+        .map(|query| query.split('&').filter(|pair| !pair.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    pairs.sort();
+    parsed.set_query(if pairs.is_empty() { None } else { Some(pairs.join("&")) }.as_deref());
+    
+    parsed.to_string()
+}
+
+/// Merge the tracking of the followed wrappers into the unwrapped VAST and serialize it
+fn stitch_report(report: UnwrapReport, dedup: UrlDedup) -> Result<String> {
+    let mut stitched_vast = report.vast;
     
-    // Process creatives
-    for creative in &wrapper.creatives {
-        if let Some(linear) = &creative.linear {
-            // Add tracking events
-            for event in &linear.tracking_events {
-                result.tracking_events
-                    .entry(event.event.clone())
-                    .or_insert_with(Vec::new)
-                    .push(event.url.clone());
-            }
-            
-            // Add video clicks
-            if let Some(video_clicks) = &linear.video_clicks {
-                // Add click tracking
-                for url in &video_clicks.click_tracking {
-                    result.click_tracking.push(url.clone());
-                }
-                
-                // Add custom click
-                for url in &video_clicks.custom_click {
-                    result.custom_click.push(url.clone());
-                }
-            }
+    // Each ad only gets the tracking of the wrappers that led to it
+    for (ad, wrappers) in stitched_vast.ads.iter_mut().zip(&report.ad_wrappers) {
+        if let Some(inline) = &mut ad.inline {
+            let wrapper_tracking = extract_wrapper_tracking(wrappers, &inline.creatives)?;
+            stitch_inline(inline, wrapper_tracking);
+            dedup_inline(inline, dedup);
         }
     }
+    
+    // Convert to XML
+    stitched_vast.to_xml(&WriteOptions::default())
 }
 
-/// Stitch a VAST document from unwrapped VAST and wrapper tracking
-fn stitch_vast_from_unwrapped(
-    mut unwrapped_vast: Vast,
-    wrapper_tracking: WrapperTracking
-) -> Result<Vast> {
-    // Process each ad
-    for ad in &mut unwrapped_vast.ads {
-        if let Some(inline) = &mut ad.inline {
-            // Add wrapper impressions
-            for impression in &wrapper_tracking.impressions {
-                inline.impressions.push(impression.clone());
-            }
+/// Tracking of a wrapper chain, gathered for one InLine ad
+#[derive(Default)]
+struct WrapperTracking {
+    impressions: Vec<Impression>,
+    viewable_impression: ViewableImpression,
+    error_urls: Vec<String>,
+    verifications: Vec<Verification>,
+    creatives: Vec<CreativeTracking>, // one per InLine creative
+}
+
+/// Tracking of a wrapper chain that applies to one InLine creative
+#[derive(Default)]
+struct CreativeTracking {
+    tracking_events: Vec<TrackingEvent>,
+    click_tracking: Vec<String>,
+    custom_click: Vec<String>,
+    icons: Vec<Icon>, // outermost wrapper first
+    companion_ads: Vec<CompanionAds>, // outermost wrapper first
+    non_linear_ads: Vec<NonLinearAds>, // outermost wrapper first
+}
+
+/// Extract the tracking of a wrapper chain, outermost first, for an InLine ad with the given creatives
+fn extract_wrapper_tracking(wrappers: &[Wrapper], inline_creatives: &[Creative]) -> Result<WrapperTracking> {
+    let mut result = WrapperTracking::default();
+    result.creatives.resize_with(inline_creatives.len(), CreativeTracking::default);
+    
+    for wrapper in wrappers {
+        // Add impressions
+        for impression in &wrapper.impressions {
+            result.impressions.push(impression.clone());
+        }
+        
+        // Add viewability URLs
+        if let Some(viewable_impression) = &wrapper.viewable_impression {
+            merge_viewable_impression(&mut result.viewable_impression, viewable_impression);
+        }
+        
+        // Add error URLs
+        result.error_urls.extend(wrapper.error.iter().cloned());
+        
+        // Add ad verifications, including VAST 3 ones kept in the wrapper's extensions
+        result.verifications.extend(wrapper.verifications()?);
+        
+        // Process creatives
+        for creative in &wrapper.creatives {
+            extract_linear_tracking(creative, inline_creatives, &mut result.creatives);
             
-            // Add wrapper error URL if inline doesn't have one
-            if inline.error.is_none() && !wrapper_tracking.error_urls.is_empty() {
-                inline.error = Some(wrapper_tracking.error_urls[0].clone());
+            // Companions and non-linears are matched to the InLine ones when merging
+            if let Some(companion_ads) = &creative.companion_ads {
+                for index in matching_creatives(inline_creatives, creative, |inline| inline.companion_ads.is_some()) {
+                    result.creatives[index].companion_ads.push(companion_ads.clone());
+                }
             }
-            
-            // Process creatives
-            for creative in &mut inline.creatives {
-                if let Some(linear) = &mut creative.linear {
-                    // Add wrapper tracking events
-                    for (event, urls) in &wrapper_tracking.tracking_events {
-                        for url in urls {
-                            linear.tracking_events.push(TrackingEvent {
-                                event: event.clone(),
-                                url: url.clone(),
-                            });
-                        }
-                    }
-                    
-                    // Process video clicks
-                    if let Some(video_clicks) = &mut linear.video_clicks {
-                        // Add wrapper click tracking
-                        for url in &wrapper_tracking.click_tracking {
-                            video_clicks.click_tracking.push(url.clone());
-                        }
-                        
-                        // Add wrapper custom click
-                        for url in &wrapper_tracking.custom_click {
-                            video_clicks.custom_click.push(url.clone());
-                        }
-                    } else if !wrapper_tracking.click_tracking.is_empty() || !wrapper_tracking.custom_click.is_empty() {
-                        // Create video clicks if it doesn't exist
-                        linear.video_clicks = Some(VideoClicks {
-                            click_through: None,
-                            click_tracking: wrapper_tracking.click_tracking.clone(),
-                            custom_click: wrapper_tracking.custom_click.clone(),
-                        });
-                    }
+            if let Some(non_linear_ads) = &creative.non_linear_ads {
+                for index in matching_creatives(inline_creatives, creative, |inline| inline.non_linear_ads.is_some()) {
+                    result.creatives[index].non_linear_ads.push(non_linear_ads.clone());
                 }
             }
         }
     }
     
-    Ok(unwrapped_vast)
-}
-
-/// Convert a Vast struct to XML
-fn vast_to_xml(vast: &Vast) -> Result<String> {
-    let mut xml = String::new();
-    
-    // XML declaration
-    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
-    
-    // VAST root element
-    xml.push_str(&format!("<VAST version=\"{}\">\n", vast.version));
-    
-    // Error URL if present
-    if let Some(error) = &vast.error {
-        xml.push_str(&format!("  <Error><![CDATA[{}]]></Error>\n", error));
-    }
-    
-    // Add ads
-    for ad in &vast.ads {
-        xml.push_str(&ad_to_xml(ad));
-    }
-    
-    // Close VAST element
-    xml.push_str("</VAST>");
-    
-    Ok(xml)
+    Ok(result)
 }
 
-/// Convert an Ad to XML
-fn ad_to_xml(ad: &Ad) -> String {
-    let mut xml = String::new();
-    
-    // Open Ad element with attributes
-    xml.push_str("  <Ad");
-    if let Some(id) = &ad.id {
-        xml.push_str(&format!(" id=\"{}\"", id));
-    }
-    if let Some(sequence) = &ad.sequence {
-        xml.push_str(&format!(" sequence=\"{}\"", sequence));
-    }
-    if let Some(conditional_ad) = &ad.conditional_ad {
-        xml.push_str(&format!(" conditionalAd=\"{}\"", conditional_ad));
-    }
-    xml.push_str(">\n");
-    
-    // Add InLine or Wrapper
-    if let Some(inline) = &ad.inline {
-        xml.push_str(&inline_to_xml(inline));
-    } else if let Some(wrapper) = &ad.wrapper {
-        xml.push_str(&wrapper_to_xml(wrapper));
+/// Extract the linear tracking of a wrapper creative for the InLine creatives it applies to
+fn extract_linear_tracking(creative: &Creative, inline_creatives: &[Creative], result: &mut [CreativeTracking]) {
+    let Some(linear) = &creative.linear else {
+        return;
+    };
+    
+    for index in matching_creatives(inline_creatives, creative, |inline| inline.linear.is_some()) {
+        let target = &mut result[index];
+        
+        // Add tracking events
+        target.tracking_events.extend(linear.tracking_events.iter().cloned());
+        
+        // Add video clicks
+        if let Some(video_clicks) = &linear.video_clicks {
+            target.click_tracking.extend(video_clicks.click_tracking.iter().cloned());
+            target.custom_click.extend(video_clicks.custom_click.iter().cloned());
+        }
+        
+        // Add icons
+        target.icons.extend(linear.icons.iter().cloned());
     }
-    
-    // Close Ad element
-    xml.push_str("  </Ad>\n");
-    
-    xml
 }
 
-/// Convert an InLine to XML
-fn inline_to_xml(inline: &InLine) -> String {
-    let mut xml = String::new();
-    
-    // Open InLine element
-    xml.push_str("    <InLine>\n");
-    
-    // Add AdSystem
-    xml.push_str("      <AdSystem");
-    if let Some(version) = &inline.ad_system.version {
-        xml.push_str(&format!(" version=\"{}\"", version));
+/// The indexes of the InLine creatives a wrapper creative's tracking applies to
+/// 
+/// A wrapper creative is matched by UniversalAdId, then adId, then sequence. Without a
+/// match, VAST applies it to the InLine creative of the same kind, which is only done
+/// when exactly one creative of that kind (as judged by `same_kind`) exists.
+fn matching_creatives(
+    inline_creatives: &[Creative],
+    wrapper_creative: &Creative,
+    same_kind: impl Fn(&Creative) -> bool,
+) -> Vec<usize> {
+    let find = |matches: &dyn Fn(&Creative) -> bool| -> Vec<usize> {
+        inline_creatives.iter()
+            .enumerate()
+            .filter(|(_, inline)| same_kind(inline) && matches(inline))
+            .map(|(index, _)| index)
+            .collect()
+    };
+    
+    if !wrapper_creative.universal_ad_ids.is_empty() {
+        let matched = find(&|inline| inline.universal_ad_ids.iter()
+            .any(|id| wrapper_creative.universal_ad_ids.iter()
+                .any(|wrapper_id| wrapper_id.id_registry == id.id_registry && wrapper_id.id == id.id)));
+        if !matched.is_empty() {
+            return matched;
+        }
     }
-    xml.push_str(&format!(">{}</AdSystem>\n", inline.ad_system.name));
-    
-    // Add AdTitle
-    xml.push_str(&format!("      <AdTitle>{}</AdTitle>\n", inline.ad_title));
     
-    // Add Description if present
-    if let Some(description) = &inline.description {
-        xml.push_str(&format!("      <Description>{}</Description>\n", description));
+    if let Some(ad_id) = &wrapper_creative.ad_id {
+        let matched = find(&|inline| inline.ad_id.as_ref() == Some(ad_id));
+        if !matched.is_empty() {
+            return matched;
+        }
     }
     
-    // Add Advertiser if present
-    if let Some(advertiser) = &inline.advertiser {
-        xml.push_str(&format!("      <Advertiser>{}</Advertiser>\n", advertiser));
+    if let Some(sequence) = wrapper_creative.sequence {
+        let matched = find(&|inline| inline.sequence == Some(sequence));
+        if !matched.is_empty() {
+            return matched;
+        }
     }
     
-    // Add Survey if present
-    if let Some(survey) = &inline.survey {
-        xml.push_str(&format!("      <Survey><![CDATA[{}]]></Survey>\n", survey));
+    let candidates = find(&|_| true);
+    if candidates.len() > 1 {
+        warn!(
+            creative_id = wrapper_creative.id.as_deref(),
+            candidates = candidates.len();
+            "Wrapper creative matches several InLine creatives, dropping its tracking"
+        );
+        return Vec::new();
     }
+    candidates
+}
+
+/// Merge the tracking of a wrapper chain into the InLine ad it resolved to
+fn stitch_inline(inline: &mut InLine, wrapper_tracking: WrapperTracking) {
+    // Add wrapper impressions
+    inline.impressions.extend(wrapper_tracking.impressions);
     
-    // Add Impressions
-    for impression in &inline.impressions {
-        xml.push_str("      <Impression");
-        if let Some(id) = &impression.id {
-            xml.push_str(&format!(" id=\"{}\"", id));
-        }
-        xml.push_str(&format!("><![CDATA[{}]]></Impression>\n", impression.url));
+    // Add wrapper viewability URLs, creating the ViewableImpression if the InLine has none
+    if !wrapper_tracking.viewable_impression.is_empty() {
+        let viewable_impression = inline.viewable_impression.get_or_insert_with(ViewableImpression::default);
+        merge_viewable_impression(viewable_impression, &wrapper_tracking.viewable_impression);
     }
     
-    // Add Error if present
-    if let Some(error) = &inline.error {
-        xml.push_str(&format!("      <Error><![CDATA[{}]]></Error>\n", error));
-    }
+    // Add wrapper error URLs, so every partner in the chain hears about failures
+    inline.error.extend(wrapper_tracking.error_urls);
     
-    // Add Pricing if present
-    if let Some(pricing) = &inline.pricing {
-        xml.push_str(&format!("      <Pricing model=\"{}\" currency=\"{}\">{}</Pricing>\n",
-                             pricing.model, pricing.currency, pricing.value));
-    }
+    // Add wrapper ad verifications
+    inline.ad_verifications.extend(wrapper_tracking.verifications);
     
-    // Add Extensions if present
-    if !inline.extensions.is_empty() {
-        xml.push_str("      <Extensions>\n");
-        for extension in &inline.extensions {
-            xml.push_str("        <Extension");
-            if let Some(extension_type) = &extension.r#type {
-                xml.push_str(&format!(" type=\"{}\"", extension_type));
+    // Process creatives
+    for (creative, tracking) in inline.creatives.iter_mut().zip(wrapper_tracking.creatives) {
+        if let Some(companion_ads) = &mut creative.companion_ads {
+            for wrapper_companion_ads in &tracking.companion_ads {
+                stitch_companion_ads(companion_ads, wrapper_companion_ads);
             }
-            xml.push_str(&format!(">{}</Extension>\n", extension.content));
         }
-        xml.push_str("      </Extensions>\n");
-    }
-    
-    // Add Creatives
-    if !inline.creatives.is_empty() {
-        xml.push_str("      <Creatives>\n");
-        for creative in &inline.creatives {
-            xml.push_str(&creative_to_xml(creative));
+        
+        if let Some(non_linear_ads) = &mut creative.non_linear_ads {
+            stitch_non_linear_ads(non_linear_ads, &tracking.non_linear_ads);
+        }
+        
+        let Some(linear) = &mut creative.linear else {
+            continue;
+        };
+        
+        // Add wrapper tracking events
+        linear.tracking_events.extend(tracking.tracking_events);
+        sort_tracking_events(&mut linear.tracking_events);
+        
+        // Process video clicks
+        if let Some(video_clicks) = &mut linear.video_clicks {
+            video_clicks.click_tracking.extend(tracking.click_tracking);
+            video_clicks.custom_click.extend(tracking.custom_click);
+        } else if !tracking.click_tracking.is_empty() || !tracking.custom_click.is_empty() {
+            // Create video clicks if it doesn't exist
+            linear.video_clicks = Some(VideoClicks {
+                click_through: None,
+                click_tracking: tracking.click_tracking,
+                custom_click: tracking.custom_click,
+                unknown: UnknownXml::default(),
+            });
         }
-        xml.push_str("      </Creatives>\n");
+        
+        // Add wrapper icons, walking the chain from the innermost wrapper
+        merge_icons(&mut linear.icons, tracking.icons.into_iter().rev());
     }
-    
-    // Close InLine element
-    xml.push_str("    </InLine>\n");
-    
-    xml
 }
 
-/// Convert a Wrapper to XML
-fn wrapper_to_xml(wrapper: &Wrapper) -> String {
-    let mut xml = String::new();
-    
-    // Open Wrapper element
-    xml.push_str("    <Wrapper>\n");
-    
-    // Add AdSystem
-    xml.push_str("      <AdSystem");
-    if let Some(version) = &wrapper.ad_system.version {
-        xml.push_str(&format!(" version=\"{}\"", version));
-    }
-    xml.push_str(&format!(">{}</AdSystem>\n", wrapper.ad_system.name));
-    
-    // Add VASTAdTagURI
-    xml.push_str(&format!("      <VASTAdTagURI><![CDATA[{}]]></VASTAdTagURI>\n", wrapper.vast_ad_tag_uri));
-    
-    // Add Impressions
-    for impression in &wrapper.impressions {
-        xml.push_str("      <Impression");
-        if let Some(id) = &impression.id {
-            xml.push_str(&format!(" id=\"{}\"", id));
+/// Add wrapper icons, given innermost first, to the icons of an InLine creative
+/// 
+/// Per VAST, when several icons share a program the one closest to the InLine wins,
+/// so only icons whose program isn't already present are added.
+fn merge_icons(icons: &mut Vec<Icon>, wrapper_icons: impl IntoIterator<Item = Icon>) {
+    for icon in wrapper_icons {
+        let duplicate = icon.program.is_some() && icons.iter()
+            .any(|existing| existing.program == icon.program);
+        if !duplicate {
+            icons.push(icon);
         }
-        xml.push_str(&format!("><![CDATA[{}]]></Impression>\n", impression.url));
     }
-    
-    // Add Error if present
-    if let Some(error) = &wrapper.error {
-        xml.push_str(&format!("      <Error><![CDATA[{}]]></Error>\n", error));
-    }
-    
-    // Add Creatives
-    if !wrapper.creatives.is_empty() {
-        xml.push_str("      <Creatives>\n");
-        for creative in &wrapper.creatives {
-            xml.push_str(&creative_to_xml(creative));
+}
+
+/// Merge the tracking of a wrapper's companions into the InLine companions they match
+fn stitch_companion_ads(companion_ads: &mut CompanionAds, wrapper_companion_ads: &CompanionAds) {
+    let slots: Vec<Slot<'_>> = companion_ads.companions.iter()
+        .map(|companion| (companion.id.as_deref(), companion.width, companion.height))
+        .collect();
+    
+    let matched: Vec<Option<usize>> = wrapper_companion_ads.companions.iter()
+        .map(|wrapper_companion| {
+            let slot = (wrapper_companion.id.as_deref(), wrapper_companion.width, wrapper_companion.height);
+            matching_slot(&slots, slot, "companion")
+        })
+        .collect();
+    
+    for (wrapper_companion, index) in wrapper_companion_ads.companions.iter().zip(matched) {
+        if let Some(index) = index {
+            let companion = &mut companion_ads.companions[index];
+            
+            // Add creativeView and other companion tracking events
+            companion.tracking_events.extend(wrapper_companion.tracking_events.iter().cloned());
+            sort_tracking_events(&mut companion.tracking_events);
+            
+            // Add CompanionClickTracking
+            companion.click_tracking.extend(wrapper_companion.click_tracking.iter().cloned());
         }
-        xml.push_str("      </Creatives>\n");
     }
-    
-    // Close Wrapper element
-    xml.push_str("    </Wrapper>\n");
-    
-    xml
 }
 
-/// Convert a Creative to XML
-fn creative_to_xml(creative: &Creative) -> String {
-    let mut xml = String::new();
-    
-    // Open Creative element with attributes
-    xml.push_str("        <Creative");
-    if let Some(id) = &creative.id {
-        xml.push_str(&format!(" id=\"{}\"", id));
-    }
-    if let Some(sequence) = &creative.sequence {
-        xml.push_str(&format!(" sequence=\"{}\"", sequence));
-    }
-    if let Some(ad_id) = &creative.ad_id {
-        xml.push_str(&format!(" adId=\"{}\"", ad_id));
-    }
-    if let Some(api_framework) = &creative.api_framework {
-        xml.push_str(&format!(" apiFramework=\"{}\"", api_framework));
-    }
-    xml.push_str(">\n");
-    
-    // Add Linear if present
-    if let Some(linear) = &creative.linear {
-        xml.push_str(&linear_to_xml(linear));
-    }
-    
-    // Add CompanionAds if present
-    if let Some(companion_ads) = &creative.companion_ads {
-        xml.push_str(&companion_ads_to_xml(companion_ads));
+/// Merge the tracking and icons of a wrapper chain's non-linears, outermost first, into
+/// the InLine non-linears they match
+fn stitch_non_linear_ads(non_linear_ads: &mut NonLinearAds, wrapper_chain: &[NonLinearAds]) {
+    let slots: Vec<Slot<'_>> = non_linear_ads.non_linears.iter()
+        .map(|non_linear| (non_linear.id.as_deref(), non_linear.width, non_linear.height))
+        .collect();
+    
+    let matched: Vec<Vec<Option<usize>>> = wrapper_chain.iter()
+        .map(|wrapper_non_linear_ads| wrapper_non_linear_ads.non_linears.iter()
+            .map(|wrapper_non_linear| {
+                let slot = (wrapper_non_linear.id.as_deref(), wrapper_non_linear.width, wrapper_non_linear.height);
+                matching_slot(&slots, slot, "non-linear")
+            })
+            .collect())
+        .collect();
+    
+    for (wrapper_non_linear_ads, indexes) in wrapper_chain.iter().zip(&matched) {
+        // Tracking events are shared by all the non-linears
+        non_linear_ads.tracking_events.extend(wrapper_non_linear_ads.tracking_events.iter().cloned());
+        
+        for (wrapper_non_linear, index) in wrapper_non_linear_ads.non_linears.iter().zip(indexes) {
+            if let Some(index) = *index {
+                // Add NonLinearClickTracking
+                non_linear_ads.non_linears[index].click_tracking.extend(wrapper_non_linear.click_tracking.iter().cloned());
+            }
+        }
     }
+    sort_tracking_events(&mut non_linear_ads.tracking_events);
     
-    // Add NonLinearAds if present
-    if let Some(non_linear_ads) = &creative.non_linear_ads {
-        xml.push_str(&non_linear_ads_to_xml(non_linear_ads));
+    // Add wrapper icons, walking the chain from the innermost wrapper
+    for (wrapper_non_linear_ads, indexes) in wrapper_chain.iter().zip(&matched).rev() {
+        for (wrapper_non_linear, index) in wrapper_non_linear_ads.non_linears.iter().zip(indexes) {
+            if let Some(index) = *index {
+                merge_icons(&mut non_linear_ads.non_linears[index].icons, wrapper_non_linear.icons.iter().cloned());
+            }
+        }
     }
-    
-    // Close Creative element
-    xml.push_str("        </Creative>\n");
-    
-    xml
 }
 
-/// Convert a Linear to XML
-fn linear_to_xml(linear: &Linear) -> String {
-    let mut xml = String::new();
-    
-    // Open Linear element
-    xml.push_str("          <Linear>\n");
-    
-    // Add Duration if present
-    if let Some(duration) = &linear.duration {
-        xml.push_str(&format!("            <Duration>{}</Duration>\n", duration));
-    }
-    
-    // Add TrackingEvents if present
-    if !linear.tracking_events.is_empty() {
-        xml.push_str("            <TrackingEvents>\n");
-        for event in &linear.tracking_events {
-            xml.push_str(&format!("              <Tracking event=\"{}\"><![CDATA[{}]]></Tracking>\n",
-                                 event.event, event.url));
+/// Group tracking events by kind, in `TrackingEventKind` order
+/// 
+/// The sort is stable, so the InLine's events of a kind come first, then the wrappers'
+/// in chain order, outermost first.
+fn sort_tracking_events(tracking_events: &mut [TrackingEvent]) {
+    tracking_events.sort_by(|a, b| a.event.cmp(&b.event));
+}
+
+/// The id, width and height identifying a companion or non-linear
+type Slot<'a> = (Option<&'a str>, u32, u32);
+
+/// The index of the InLine companion or non-linear that a wrapper one's tracking applies to
+/// 
+/// Matched by id, then by size, falling back to the only InLine one if there is just one.
+fn matching_slot(slots: &[Slot<'_>], (id, width, height): Slot<'_>, kind: &str) -> Option<usize> {
+    if let Some(id) = id
+        && let Some(index) = slots.iter().position(|(slot_id, _, _)| *slot_id == Some(id))
+    {
+        return Some(index);
+    }
+    
+    let same_size: Vec<usize> = slots.iter()
+        .enumerate()
+        .filter(|(_, (_, slot_width, slot_height))| width > 0 && *slot_width == width && *slot_height == height)
+        .map(|(index, _)| index)
+        .collect();
+    
+    match (same_size.as_slice(), slots.len()) {
+        ([index], _) => Some(*index),
+        ([], 1) => Some(0),
+        _ => {
+            warn!(
+                id = id,
+                width = width,
+                height = height;
+                "Wrapper {} matches no single InLine {}, dropping its tracking", kind, kind
+            );
+            None
         }
-        xml.push_str("            </TrackingEvents>\n");
     }
-    
-    // Add VideoClicks if present
-    if let Some(video_clicks) = &linear.video_clicks {
-        xml.push_str("            <VideoClicks>\n");
-        
-        if let Some(click_through) = &video_clicks.click_through {
-            xml.push_str(&format!("              <ClickThrough><![CDATA[{}]]></ClickThrough>\n", click_through));
-        }
-        
-        for url in &video_clicks.click_tracking {
-            xml.push_str(&format!("              <ClickTracking><![CDATA[{}]]></ClickTracking>\n", url));
+}
+
+/// Deduplicate the tracking URLs of a stitched InLine ad
+fn dedup_inline(inline: &mut InLine, dedup: UrlDedup) {
+    if dedup == UrlDedup::None {
+        return;
+    }
+    
+    dedup.apply(&mut inline.impressions, |impression| impression.url.as_str());
+    if let Some(viewable_impression) = &mut inline.viewable_impression {
+        dedup.apply(&mut viewable_impression.viewable, String::as_str);
+        dedup.apply(&mut viewable_impression.not_viewable, String::as_str);
+        dedup.apply(&mut viewable_impression.view_undetermined, String::as_str);
+    }
+    dedup.apply(&mut inline.error, String::as_str);
+    
+    for creative in &mut inline.creatives {
+        if let Some(linear) = &mut creative.linear {
+            dedup_tracking_events(&mut linear.tracking_events, dedup);
+            if let Some(video_clicks) = &mut linear.video_clicks {
+                dedup.apply(&mut video_clicks.click_tracking, String::as_str);
+                dedup.apply(&mut video_clicks.custom_click, String::as_str);
+            }
         }
         
-        for url in &video_clicks.custom_click {
-            xml.push_str(&format!("              <CustomClick><![CDATA[{}]]></CustomClick>\n", url));
+        if let Some(companion_ads) = &mut creative.companion_ads {
+            for companion in &mut companion_ads.companions {
+                dedup_tracking_events(&mut companion.tracking_events, dedup);
+                dedup.apply(&mut companion.click_tracking, String::as_str);
+            }
         }
         
-        xml.push_str("            </VideoClicks>\n");
-    }
-    
-    // Add MediaFiles if present
-    if !linear.media_files.is_empty() {
-        xml.push_str("            <MediaFiles>\n");
-        for media_file in &linear.media_files {
-            xml.push_str("              <MediaFile");
-            
-            // Add attributes
-            xml.push_str(&format!(" type=\"{}\"", media_file.mime_type));
-            
-            if let Some(delivery) = &media_file.delivery {
-                xml.push_str(&format!(" delivery=\"{}\"", delivery));
+        if let Some(non_linear_ads) = &mut creative.non_linear_ads {
+            dedup_tracking_events(&mut non_linear_ads.tracking_events, dedup);
+            for non_linear in &mut non_linear_ads.non_linears {
+                dedup.apply(&mut non_linear.click_tracking, String::as_str);
             }
-            
-            if let Some(width) = &media_file.width {
-                xml.push_str(&format!(" width=\"{}\"", width));
-            }
-            
-            if let Some(height) = &media_file.height {
-                xml.push_str(&format!(" height=\"{}\"", height));
-            }
-            
-            if let Some(codec) = &media_file.codec {
-                xml.push_str(&format!(" codec=\"{}\"", codec));
-            }
-            
-            if let Some(bitrate) = &media_file.bitrate {
-                xml.push_str(&format!(" bitrate=\"{}\"", bitrate));
-            }
-            
-            xml.push_str(&format!("><![CDATA[{}]]></MediaFile>\n", media_file.url));
         }
-        xml.push_str("            </MediaFiles>\n");
     }
-    
-    // Close Linear element
-    xml.push_str("          </Linear>\n");
-    
-    xml
 }
 
-/// Convert a CompanionAds to XML
-fn companion_ads_to_xml(companion_ads: &CompanionAds) -> String {
-    let mut xml = String::new();
-    
-    // Open CompanionAds element
-    xml.push_str("          <CompanionAds>\n");
-    
-    // Add Companions
-    for companion in &companion_ads.companions {
-        xml.push_str("            <Companion");
+/// Append the viewability URLs of a wrapper, keeping the ID of the target
+fn merge_viewable_impression(viewable_impression: &mut ViewableImpression, wrapper_viewable_impression: &ViewableImpression) {
+    viewable_impression.viewable.extend(wrapper_viewable_impression.viewable.iter().cloned());
+    viewable_impression.not_viewable.extend(wrapper_viewable_impression.not_viewable.iter().cloned());
+    viewable_impression.view_undetermined.extend(wrapper_viewable_impression.view_undetermined.iter().cloned());
+}
+
+/// Deduplicate tracking events, only comparing events of the same kind
+fn dedup_tracking_events(tracking_events: &mut Vec<TrackingEvent>, dedup: UrlDedup) {
+    let mut seen = HashSet::new();
+    tracking_events.retain(|event| match dedup.key(&event.url) {
+        // Progress events at different offsets are different beacons, even with the same URL
+        Some(key) => seen.insert((event.event.clone(), event.offset.map(|offset| offset.to_string()), key)),
+        None => true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::MapFetcher;
+    
+    /// A VAST 3 `<Extension type="AdVerifications">` for the given vendor
+    fn verification_extension(vendor: &str) -> String {
+        format!(
+            r#"<Extensions><Extension type="AdVerifications"><AdVerifications><Verification vendor="{}"><JavaScriptResource>https://example.com/{}.js</JavaScriptResource></Verification></AdVerifications></Extension></Extensions>"#,
+            vendor, vendor
+        )
+    }
+    
+    /// Stitch a root document, serving the others from memory, and parse the result
+    fn stitch(root: &str, documents: &[(&str, &str)], options: &UnwrapOptions) -> Vast {
+        let fetcher = documents.iter()
+            .fold(MapFetcher::new(), |fetcher, (uri, xml)| fetcher.with_document(*uri, *xml));
+        let xml = stitch_vast_with_fetcher(root, &fetcher, options).unwrap();
+        parser::parse_vast(&xml).unwrap()
+    }
+    
+    /// The creatives of an InLine ad with the given `<Creative>` elements
+    fn creatives(creatives: &str) -> Vec<Creative> {
+        let xml = format!(
+            r#"<VAST version="4.1"><Ad><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle><Creatives>{}</Creatives></InLine></Ad></VAST>"#,
+            creatives
+        );
+        parser::parse_vast(&xml).unwrap().ads.remove(0).inline.unwrap().creatives
+    }
+    
+    /// A Creative with the given attributes and content
+    fn creative(attributes: &str, content: &str) -> String {
+        format!("<Creative {}>{}</Creative>", attributes, content)
+    }
+    
+    const LINEAR: &str = "<Linear><Duration>00:00:10</Duration></Linear>";
+    
+    #[test]
+    fn wrapper_creatives_match_by_universal_ad_id_then_ad_id_then_sequence() {
+        let inline = creatives(&[
+            creative(r#"adId="a" sequence="1""#, &format!(r#"<UniversalAdId idRegistry="ad-id.org">U1</UniversalAdId>{}"#, LINEAR)),
+            creative(r#"adId="b" sequence="2""#, LINEAR),
+        ].concat());
+        let wrapper = |attributes: &str, universal_ad_id: &str| {
+            creatives(&creative(attributes, &format!("{}{}", universal_ad_id, LINEAR))).remove(0)
+        };
+        let is_linear = |creative: &Creative| creative.linear.is_some();
         
-        // Add attributes
-        if let Some(id) = &companion.id {
-            xml.push_str(&format!(" id=\"{}\"", id));
-        }
+        let by_universal_ad_id = wrapper(r#"adId="b""#, r#"<UniversalAdId idRegistry="ad-id.org">U1</UniversalAdId>"#);
+        assert_eq!(matching_creatives(&inline, &by_universal_ad_id, is_linear), [0]);
         
-        xml.push_str(&format!(" width=\"{}\" height=\"{}\"", companion.width, companion.height));
+        let by_ad_id = wrapper(r#"adId="b" sequence="1""#, "");
+        assert_eq!(matching_creatives(&inline, &by_ad_id, is_linear), [1]);
         
-        xml.push_str(">\n");
+        let by_sequence = wrapper(r#"adId="c" sequence="2""#, "");
+        assert_eq!(matching_creatives(&inline, &by_sequence, is_linear), [1]);
         
-        // Add resource based on type
-        if companion.resource_type == "StaticResource" {
-            xml.push_str(&format!("              <StaticResource><![CDATA[{}]]></StaticResource>\n", companion.resource));
-        } else if companion.resource_type == "IFrameResource" {
-            xml.push_str(&format!("              <IFrameResource><![CDATA[{}]]></IFrameResource>\n", companion.resource));
-        } else if companion.resource_type == "HTMLResource" {
-            xml.push_str(&format!("              <HTMLResource><![CDATA[{}]]></HTMLResource>\n", companion.resource));
-        }
+        // Without an identifier, two InLine linears are ambiguous
+        let unidentified = wrapper("", "");
+        assert!(matching_creatives(&inline, &unidentified, is_linear).is_empty());
+    }
+    
+    #[test]
+    fn unidentified_wrapper_creatives_apply_to_the_only_creative_of_their_kind() {
+        let inline = creatives(&[
+            creative("", r#"<CompanionAds><Companion width="300" height="250"/></CompanionAds>"#),
+            creative("", LINEAR),
+        ].concat());
+        let wrapper = creatives(&creative("", LINEAR)).remove(0);
         
-        // Add ClickThrough if present
-        if let Some(click_through) = &companion.click_through {
-            xml.push_str(&format!("              <CompanionClickThrough><![CDATA[{}]]></CompanionClickThrough>\n", click_through));
-        }
+        assert_eq!(matching_creatives(&inline, &wrapper, |creative| creative.linear.is_some()), [1]);
+    }
+    
+    #[test]
+    fn pod_ads_only_get_the_tracking_of_their_own_wrappers() {
+        let wrapper = |id: &str, sequence: u32| format!(
+            r#"<Ad id="{}" sequence="{}"><Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>https://example.com/{}</VASTAdTagURI><Impression>https://example.com/imp/{}</Impression><Creatives><Creative><Linear><TrackingEvents><Tracking event="start">https://example.com/start/{}</Tracking></TrackingEvents></Linear></Creative></Creatives></Wrapper></Ad>"#,
+            id, sequence, id, id, id
+        );
+        let inline = |id: &str| format!(
+            r#"<VAST version="4.1"><Ad id="{}"><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle><Impression>https://example.com/imp/{}</Impression><Creatives>{}</Creatives></InLine></Ad></VAST>"#,
+            id, id, creative("", LINEAR)
+        );
+        let root = format!(r#"<VAST version="4.1">{}{}</VAST>"#, wrapper("w1", 1), wrapper("w2", 2));
         
-        // Add TrackingEvents if present
-        if !companion.tracking_events.is_empty() {
-            xml.push_str("              <TrackingEvents>\n");
-            for event in &companion.tracking_events {
-                xml.push_str(&format!("                <Tracking event=\"{}\"><![CDATA[{}]]></Tracking>\n",
-                                     event.event, event.url));
-            }
-            xml.push_str("              </TrackingEvents>\n");
-        }
+        let vast = stitch(&root, &[
+            ("https://example.com/w1", &inline("i1")),
+            ("https://example.com/w2", &inline("i2")),
+        ], &UnwrapOptions::new());
         
-        // Close Companion element
-        xml.push_str("            </Companion>\n");
+        for (ad, (inline_id, wrapper_id)) in vast.ads.iter().zip([("i1", "w1"), ("i2", "w2")]) {
+            let inline = ad.inline.as_ref().unwrap();
+            let impressions: Vec<_> = inline.impressions.iter().map(|impression| impression.url.as_str()).collect();
+            assert_eq!(impressions, [
+                format!("https://example.com/imp/{}", inline_id),
+                format!("https://example.com/imp/{}", wrapper_id),
+            ]);
+            
+            let linear = inline.creatives[0].linear.as_ref().unwrap();
+            let tracking: Vec<_> = linear.tracking_events.iter().map(|event| event.url.as_str()).collect();
+            assert_eq!(tracking, [format!("https://example.com/start/{}", wrapper_id)]);
+        }
     }
     
-    // Close CompanionAds element
-    xml.push_str("          </CompanionAds>\n");
-    
-    xml
-}
-
-/// Convert a NonLinearAds to XML
-fn non_linear_ads_to_xml(non_linear_ads: &NonLinearAds) -> String {
-    let mut xml = String::new();
-    
-    // Open NonLinearAds element
-    xml.push_str("          <NonLinearAds>\n");
-    
-    // Add NonLinear elements
-    for non_linear in &non_linear_ads.non_linears {
-        xml.push_str("            <NonLinear");
+    #[test]
+    fn slots_match_by_id_then_size_then_the_only_slot() {
+        let slots = [(Some("a"), 300, 250), (None, 728, 90), (None, 300, 250)];
         
-        // Add attributes
-        if let Some(id) = &non_linear.id {
-            xml.push_str(&format!(" id=\"{}\"", id));
-        }
+        assert_eq!(matching_slot(&slots, (Some("a"), 0, 0), "companion"), Some(0));
+        assert_eq!(matching_slot(&slots, (Some("z"), 728, 90), "companion"), Some(1));
+        assert_eq!(matching_slot(&slots, (None, 300, 250), "companion"), None);
+        assert_eq!(matching_slot(&slots, (None, 160, 600), "companion"), None);
+        assert_eq!(matching_slot(&slots[1..2], (None, 160, 600), "companion"), Some(0));
+    }
+    
+    #[test]
+    fn companion_and_non_linear_tracking_is_stitched_onto_matching_slots() {
+        let root = r#"<VAST version="4.1"><Ad><Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>https://example.com/inline</VASTAdTagURI><Creatives>
+            <Creative><CompanionAds>
+                <Companion width="728" height="90">
+                    <TrackingEvents><Tracking event="creativeView">https://example.com/w/companion-view</Tracking></TrackingEvents>
+                    <CompanionClickTracking>https://example.com/w/companion-click</CompanionClickTracking>
+                </Companion>
+            </CompanionAds></Creative>
+            <Creative><NonLinearAds>
+                <TrackingEvents><Tracking event="creativeView">https://example.com/w/non-linear-view</Tracking></TrackingEvents>
+                <NonLinear id="overlay"><NonLinearClickTracking>https://example.com/w/non-linear-click</NonLinearClickTracking></NonLinear>
+            </NonLinearAds></Creative>
+        </Creatives></Wrapper></Ad></VAST>"#;
+        let inline = r#"<VAST version="4.1"><Ad><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle><Creatives>
+            <Creative><CompanionAds>
+                <Companion id="box" width="300" height="250"><StaticResource creativeType="image/png">https://example.com/box.png</StaticResource></Companion>
+                <Companion id="banner" width="728" height="90"><StaticResource creativeType="image/png">https://example.com/banner.png</StaticResource></Companion>
+            </CompanionAds></Creative>
+            <Creative><NonLinearAds>
+                <NonLinear id="ticker" width="480" height="60"><StaticResource creativeType="image/png">https://example.com/ticker.png</StaticResource></NonLinear>
+                <NonLinear id="overlay" width="480" height="60"><StaticResource creativeType="image/png">https://example.com/overlay.png</StaticResource></NonLinear>
+            </NonLinearAds></Creative>
+        </Creatives></InLine></Ad></VAST>"#;
         
-        xml.push_str(&format!(" width=\"{}\" height=\"{}\"", non_linear.width, non_linear.height));
+        let vast = stitch(root, &[("https://example.com/inline", inline)], &UnwrapOptions::new());
+        let creatives = &vast.ads[0].inline.as_ref().unwrap().creatives;
         
-        if let Some(expand_width) = &non_linear.expand_width {
-            xml.push_str(&format!(" expandedWidth=\"{}\"", expand_width));
-        }
+        // The wrapper companion only matches the InLine one of the same size
+        let companions = &creatives[0].companion_ads.as_ref().unwrap().companions;
+        assert!(companions[0].tracking_events.is_empty() && companions[0].click_tracking.is_empty());
+        assert_eq!(companions[1].tracking_events[0].url, "https://example.com/w/companion-view");
+        assert_eq!(companions[1].click_tracking, ["https://example.com/w/companion-click"]);
         
-        if let Some(expand_height) = &non_linear.expand_height {
-            xml.push_str(&format!(" expandedHeight=\"{}\"", expand_height));
-        }
+        // Non-linear tracking events are shared; click tracking goes to the matching id
+        let non_linear_ads = creatives[1].non_linear_ads.as_ref().unwrap();
+        assert_eq!(non_linear_ads.tracking_events[0].url, "https://example.com/w/non-linear-view");
+        assert!(non_linear_ads.non_linears[0].click_tracking.is_empty());
+        assert_eq!(non_linear_ads.non_linears[1].click_tracking, ["https://example.com/w/non-linear-click"]);
+    }
+    
+    #[test]
+    fn non_linear_icons_are_merged_with_the_innermost_icon_of_a_program_winning() {
+        let icon = |program: &str, source: &str| format!(
+            r#"<Icon program="{}" width="20" height="20"><StaticResource creativeType="image/png">https://example.com/{}/{}.png</StaticResource></Icon>"#,
+            program, source, program
+        );
+        let document = |ad: &str, non_linears: &str| format!(
+            r#"<VAST version="4.1"><Ad>{}<Creatives><Creative><NonLinearAds>{}</NonLinearAds></Creative></Creatives></Ad></VAST>"#,
+            ad, non_linears
+        );
+        let wrapper = |uri: &str, icons: &str| document(
+            &format!("<Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>{}</VASTAdTagURI>", uri),
+            &format!(r#"<NonLinear id="overlay"><Icons>{}</Icons></NonLinear>"#, icons),
+        ).replace("</Creatives></Ad>", "</Creatives></Wrapper></Ad>");
         
-        if let Some(scalable) = &non_linear.scalable {
-            xml.push_str(&format!(" scalable=\"{}\"", scalable));
-        }
+        let root = wrapper("https://example.com/middle", &(icon("Brand", "root") + &icon("Rating", "root")));
+        let middle = wrapper("https://example.com/inline", &(icon("AdChoices", "middle") + &icon("Brand", "middle")));
+        let inline = document(
+            "<InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle>",
+            &format!(
+                r#"<NonLinear id="ticker" width="480" height="60"></NonLinear><NonLinear id="overlay" width="480" height="60"><Icons>{}</Icons></NonLinear>"#,
+                icon("AdChoices", "inline")
+            ),
+        ).replace("</Creatives></Ad>", "</Creatives></InLine></Ad>");
         
-        if let Some(maintain_aspect_ratio) = &non_linear.maintain_aspect_ratio {
-            xml.push_str(&format!(" maintainAspectRatio=\"{}\"", maintain_aspect_ratio));
+        let vast = stitch(&root, &[
+            ("https://example.com/middle", &middle),
+            ("https://example.com/inline", &inline),
+        ], &UnwrapOptions::new());
+        let non_linears = &vast.ads[0].inline.as_ref().unwrap().creatives[0].non_linear_ads.as_ref().unwrap().non_linears;
+        
+        assert!(non_linears[0].icons.is_empty());
+        let icons: Vec<_> = non_linears[1].icons.iter().map(|icon| icon.resources[0].content.as_str()).collect();
+        assert_eq!(icons, [
+            "https://example.com/inline/AdChoices.png",
+            "https://example.com/middle/Brand.png",
+            "https://example.com/root/Rating.png",
+        ]);
+    }
+    
+    fn tracking(event: &str, offset: Option<&str>, url: &str) -> TrackingEvent {
+        TrackingEvent {
+            event: TrackingEventKind::from(event),
+            url: url.to_string(),
+            offset: offset.map(|offset| offset.parse().unwrap()),
+            unknown: UnknownXml::default(),
         }
+    }
+    
+    #[test]
+    fn tracking_dedup_keeps_progress_events_at_different_offsets() {
+        let mut tracking_events = vec![
+            tracking("progress", Some("00:00:05"), "https://example.com/progress"),
+            tracking("progress", Some("00:00:10"), "https://example.com/progress"),
+            tracking("progress", Some("00:00:05"), "https://example.com/progress"),
+            tracking("start", None, "https://example.com/progress"),
+        ];
         
-        xml.push_str(">\n");
+        dedup_tracking_events(&mut tracking_events, UrlDedup::Exact);
         
-        // Add resource based on type
-        if non_linear.resource_type == "StaticResource" {
-            xml.push_str(&format!("              <StaticResource><![CDATA[{}]]></StaticResource>\n", non_linear.resource));
-        } else if non_linear.resource_type == "IFrameResource" {
-            xml.push_str(&format!("              <IFrameResource><![CDATA[{}]]></IFrameResource>\n", non_linear.resource));
-        } else if non_linear.resource_type == "HTMLResource" {
-            xml.push_str(&format!("              <HTMLResource><![CDATA[{}]]></HTMLResource>\n", non_linear.resource));
-        }
+        let kept: Vec<_> = tracking_events.iter()
+            .map(|event| (event.event.as_str(), event.offset.map(|offset| offset.to_string())))
+            .collect();
+        assert_eq!(kept, [
+            ("progress", Some("00:00:05".to_string())),
+            ("progress", Some("00:00:10".to_string())),
+            ("start", None),
+        ]);
+    }
+    
+    #[test]
+    fn url_dedup_modes() {
+        let urls = [
+            "https://Example.com:443/imp?b=2&a=1#top",
+            "https://example.com/imp?a=1&b=2",
+            "https://example.com/imp?a=1&b=2",
+        ];
+        let dedup = |mode: UrlDedup| {
+            let mut urls = urls.map(str::to_string).to_vec();
+            mode.apply(&mut urls, String::as_str);
+            urls.len()
+        };
         
-        // Add ClickThrough if present
-        if let Some(click_through) = &non_linear.click_through {
-            xml.push_str(&format!("              <NonLinearClickThrough><![CDATA[{}]]></NonLinearClickThrough>\n", click_through));
-        }
+        assert_eq!(dedup(UrlDedup::None), 3);
+        assert_eq!(dedup(UrlDedup::Exact), 2);
+        assert_eq!(dedup(UrlDedup::Normalized), 1);
+        assert_eq!("normalized".parse::<UrlDedup>().unwrap(), UrlDedup::Normalized);
+        assert!("fuzzy".parse::<UrlDedup>().is_err());
+    }
+    
+    #[test]
+    fn every_error_url_is_kept_and_impressions_are_deduplicated() {
+        let root = r#"<VAST version="4.1"><Ad id="w"><Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>https://example.com/inline</VASTAdTagURI><Error>https://example.com/error/w1</Error><Error>https://example.com/error/w2</Error><Impression>https://example.com/imp</Impression></Wrapper></Ad></VAST>"#;
+        let inline = r#"<VAST version="4.1"><Ad id="i"><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle><Error>https://example.com/error/i</Error><Impression>https://example.com/imp</Impression><Creatives/></InLine></Ad></VAST>"#;
+        let options = UnwrapOptions::new().with_dedup(UrlDedup::Exact);
+        
+        let vast = stitch(root, &[("https://example.com/inline", inline)], &options);
+        let inline = vast.ads[0].inline.as_ref().unwrap();
+        
+        assert_eq!(inline.error, [
+            "https://example.com/error/i",
+            "https://example.com/error/w1",
+            "https://example.com/error/w2",
+        ]);
+        assert_eq!(inline.impressions.len(), 1);
+    }
+    
+    #[test]
+    fn vast_3_verification_extensions_survive_stitching() {
+        let root = format!(
+            r#"<VAST version="3.0"><Ad id="w"><Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>https://example.com/inline</VASTAdTagURI>{}</Wrapper></Ad></VAST>"#,
+            verification_extension("wrapper")
+        );
+        let inline = format!(
+            r#"<VAST version="3.0"><Ad id="i"><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle>{}<Creatives/></InLine></Ad></VAST>"#,
+            verification_extension("inline")
+        );
         
-        // Close NonLinear element
-        xml.push_str("            </NonLinear>\n");
+        let vast = stitch(&root, &[("https://example.com/inline", &inline)], &UnwrapOptions::new());
+        let inline = vast.ads[0].inline.as_ref().unwrap();
+        
+        // The InLine's own extension stays where it was; the wrapper's is carried over
+        assert_eq!(inline.extensions.len(), 1);
+        assert_eq!(inline.ad_verifications.len(), 1);
+        let vendors: Vec<_> = inline.verifications().unwrap().into_iter()
+            .filter_map(|verification| verification.vendor)
+            .collect();
+        assert_eq!(vendors, ["wrapper", "inline"]);
+    }
+    
+    #[test]
+    fn vast_4_ad_fields_survive_stitching() {
+        let root = r#"<VAST version="4.1"><Ad><Wrapper><AdSystem>W</AdSystem><VASTAdTagURI>https://example.com/inline</VASTAdTagURI></Wrapper></Ad></VAST>"#;
+        let inline = r#"<VAST version="4.1"><Ad><InLine><AdSystem>I</AdSystem><AdTitle>T</AdTitle>
+            <AdServingId>serving-1</AdServingId>
+            <Category authority="https://iabtechlab.com/categoryauthority">IAB1-1</Category>
+            <Expires>3600</Expires>
+            <Creatives><Creative>
+                <UniversalAdId idRegistry="ad-id.org">CNPA0484000H</UniversalAdId>
+                <UniversalAdId idRegistry="clearcast.co.uk">AAA/BBBB123/030</UniversalAdId>
+                <Linear><Duration>00:00:15</Duration></Linear>
+            </Creative></Creatives>
+        </InLine></Ad></VAST>"#;
+        
+        let vast = stitch(root, &[("https://example.com/inline", inline)], &UnwrapOptions::new());
+        let stitched = vast.ads[0].inline.as_ref().unwrap();
+        
+        assert_eq!(stitched.ad_serving_id.as_deref(), Some("serving-1"));
+        assert_eq!(stitched.expires, Some(3600));
+        assert_eq!(stitched.categories.len(), 1);
+        assert_eq!(stitched.categories[0].authority.as_deref(), Some("https://iabtechlab.com/categoryauthority"));
+        assert_eq!(stitched.categories[0].value, "IAB1-1");
+        let universal_ad_ids: Vec<_> = stitched.creatives[0].universal_ad_ids.iter()
+            .map(|universal_ad_id| (universal_ad_id.id_registry.as_str(), universal_ad_id.id.as_str()))
+            .collect();
+        assert_eq!(universal_ad_ids, [("ad-id.org", "CNPA0484000H"), ("clearcast.co.uk", "AAA/BBBB123/030")]);
     }
-    
-    // Close NonLinearAds element
-    xml.push_str("          </NonLinearAds>\n");
-    
-    xml
 }
-
-/// Helper function that calls unwrap module's fetch_vast_content
-fn fetch_vast_content(url_or_path: &str) -> Result<String> {
-    unwrap::fetch_vast_content(url_or_path)
-} 
\ No newline at end of file
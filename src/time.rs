@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Why a VAST time value couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid time value {value:?}: {reason}")]
+pub struct TimeParseError {
+    /// The malformed value
+    pub value: String,
+    
+    /// What is wrong with it
+    pub reason: String,
+}
+
+impl TimeParseError {
+    fn new(value: &str, reason: &str) -> Self {
+        TimeParseError {
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// A VAST clock time, `HH:MM:SS` or `HH:MM:SS.mmm`, with millisecond precision
+/// 
+/// Used for Duration, Icon duration and offset and NonLinear minSuggestedDuration.
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::time::VastDuration;
+/// 
+/// let duration: VastDuration = "00:00:15.250".parse().unwrap();
+/// assert_eq!(duration.as_duration(), Duration::from_millis(15_250));
+/// assert_eq!(duration.to_string(), "00:00:15.250");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct VastDuration(Duration);
+
+impl VastDuration {
+    /// A duration of the given number of milliseconds
+    pub fn from_millis(millis: u64) -> Self {
+        VastDuration(Duration::from_millis(millis))
+    }
+    
+    /// The duration as a std Duration
+    pub fn as_duration(self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for VastDuration {
+    /// Convert a std Duration, dropping anything below a millisecond
+    fn from(duration: Duration) -> Self {
+        VastDuration::from_millis(duration.as_millis() as u64)
+    }
+}
+
+impl From<VastDuration> for Duration {
+    fn from(duration: VastDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for VastDuration {
+    type Err = TimeParseError;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let format_error = || TimeParseError::new(value, "expected HH:MM:SS or HH:MM:SS.mmm");
+        
+        let mut parts = value.trim().split(':');
+        let (Some(hours), Some(minutes), Some(seconds), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(format_error());
+        };
+        let (seconds, fraction) = match seconds.split_once('.') {
+            Some((seconds, fraction)) => (seconds, Some(fraction)),
+            None => (seconds, None),
+        };
+        
+        let number = |part: &str| -> Result<u64, TimeParseError> {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(format_error());
+            }
+            part.parse().map_err(|_| format_error())
+        };
+        let hours = number(hours)?;
+        let minutes = number(minutes)?;
+        let seconds = number(seconds)?;
+        if minutes >= 60 {
+            return Err(TimeParseError::new(value, "minutes must be below 60"));
+        }
+        if seconds >= 60 {
+            return Err(TimeParseError::new(value, "seconds must be below 60"));
+        }
+        
+        // Milliseconds from the first three fractional digits
+        let millis = match fraction {
+            Some(fraction) => {
+                number(fraction)?;
+                fraction.bytes()
+                    .chain(std::iter::repeat(b'0'))
+                    .take(3)
+                    .fold(0, |millis, digit| millis * 10 + u64::from(digit - b'0'))
+            },
+            None => 0,
+        };
+        
+        let total_millis = hours.checked_mul(3_600_000)
+            .and_then(|millis_so_far| millis_so_far.checked_add(minutes * 60_000 + seconds * 1000 + millis))
+            .ok_or_else(|| TimeParseError::new(value, "value out of range"))?;
+        Ok(VastDuration::from_millis(total_millis))
+    }
+}
+
+impl fmt::Display for VastDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0.as_secs();
+        write!(f, "{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)?;
+        let millis = self.0.subsec_millis();
+        if millis != 0 {
+            write!(f, ".{:03}", millis)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for VastDuration {
+    type Error = TimeParseError;
+    
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<VastDuration> for String {
+    fn from(duration: VastDuration) -> Self {
+        duration.to_string()
+    }
+}
+
+/// A point in a linear ad, as a clock time or a percentage of the ad's duration
+/// 
+/// Used for the Linear skipoffset and progress Tracking offsets.
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::time::VastOffset;
+/// 
+/// let offset: VastOffset = "25%".parse().unwrap();
+/// assert_eq!(offset.resolve(Duration::from_secs(20)), Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum VastOffset {
+    /// A time from the start of the ad
+    Time(VastDuration),
+    
+    /// A percentage of the ad's duration, from 0 to 100
+    Percent(f64),
+}
+
+impl VastOffset {
+    /// The time from the start of an ad of the given duration
+    pub fn resolve(self, duration: Duration) -> Duration {
+        match self {
+            VastOffset::Time(time) => time.as_duration(),
+            VastOffset::Percent(percent) => VastDuration::from(duration.mul_f64(percent / 100.0)).as_duration(),
+        }
+    }
+}
+
+impl FromStr for VastOffset {
+    type Err = TimeParseError;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some(percent) = value.trim().strip_suffix('%') else {
+            return value.parse().map(VastOffset::Time);
+        };
+        
+        match percent.trim().parse::<f64>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(VastOffset::Percent(percent)),
+            Ok(_) => Err(TimeParseError::new(value, "percentage must be between 0% and 100%")),
+            Err(_) => Err(TimeParseError::new(value, "expected a percentage such as 25%")),
+        }
+    }
+}
+
+impl fmt::Display for VastOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VastOffset::Time(time) => time.fmt(f),
+            VastOffset::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl TryFrom<String> for VastOffset {
+    type Error = TimeParseError;
+    
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<VastOffset> for String {
+    fn from(offset: VastOffset) -> Self {
+        offset.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parses_clock_times() {
+        let cases = [
+            ("00:00:15", 15_000),
+            ("01:02:03", 3_723_000),
+            (" 00:00:30 ", 30_000),
+            ("00:00:15.5", 15_500),
+            ("00:00:15.25", 15_250),
+            ("00:00:15.250", 15_250),
+            ("00:00:15.2509", 15_250),
+            ("100:00:00", 360_000_000),
+        ];
+        for (value, millis) in cases {
+            let duration: VastDuration = value.parse().unwrap();
+            assert_eq!(duration, VastDuration::from_millis(millis), "{}", value);
+        }
+    }
+    
+    #[test]
+    fn formats_milliseconds_only_when_present() {
+        assert_eq!(VastDuration::from_millis(15_000).to_string(), "00:00:15");
+        assert_eq!(VastDuration::from_millis(15_050).to_string(), "00:00:15.050");
+        assert_eq!(VastDuration::from_millis(3_723_000).to_string(), "01:02:03");
+    }
+    
+    #[test]
+    fn rejects_malformed_clock_times() {
+        for value in ["", "15", "00:15", "00:00:00:15", "00:60:00", "00:00:60", "aa:00:00", "00:00:1.", "00:00:-1", "00:00:15.x"] {
+            assert!(value.parse::<VastDuration>().is_err(), "{:?}", value);
+        }
+    }
+    
+    #[test]
+    fn rejects_out_of_range_hours() {
+        for value in ["99999999999999999:00:00", "18446744073709551615:00:00", "99999999999999999999:00:00"] {
+            let error = value.parse::<VastDuration>().unwrap_err();
+            assert_eq!(error.value, value);
+        }
+        
+        let error = "99999999999999999:00:00".parse::<VastDuration>().unwrap_err();
+        assert_eq!(error.reason, "value out of range");
+    }
+    
+    #[test]
+    fn parses_offsets() {
+        let duration = Duration::from_secs(20);
+        let cases = [
+            ("0%", Duration::ZERO),
+            ("25%", Duration::from_secs(5)),
+            ("12.5%", Duration::from_millis(2_500)),
+            ("100%", duration),
+            ("00:00:05", Duration::from_secs(5)),
+            ("00:00:05.500", Duration::from_millis(5_500)),
+        ];
+        for (value, time) in cases {
+            let offset: VastOffset = value.parse().unwrap();
+            assert_eq!(offset.resolve(duration), time, "{}", value);
+        }
+    }
+    
+    #[test]
+    fn rejects_malformed_offsets() {
+        for value in ["%", "abc%", "-5%", "100.1%", "5", "00:00:60"] {
+            assert!(value.parse::<VastOffset>().is_err(), "{:?}", value);
+        }
+    }
+    
+    #[test]
+    fn formats_offsets() {
+        assert_eq!(VastOffset::Percent(25.0).to_string(), "25%");
+        assert_eq!(VastOffset::Percent(12.5).to_string(), "12.5%");
+        assert_eq!(VastOffset::Time(VastDuration::from_millis(5_000)).to_string(), "00:00:05");
+    }
+}
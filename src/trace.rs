@@ -0,0 +1,69 @@
+use crate::unwrap::ChainError;
+use serde::{Deserialize, Serialize};
+
+/// The wrapper chain followed while unwrapping a VAST document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainTrace {
+    /// The initial document, with the hops followed from it as children
+    pub root: TraceNode,
+    
+    /// Total time spent unwrapping, in milliseconds
+    pub elapsed_ms: f64,
+}
+
+impl ChainTrace {
+    /// All nodes of the trace, depth first, starting with the initial document
+    pub fn nodes(&self) -> Vec<&TraceNode> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(node.children.iter().rev());
+        }
+        nodes
+    }
+}
+
+/// One document in the wrapper chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceNode {
+    /// The VASTAdTagURI requested, or None for the initial document
+    pub uri: Option<String>,
+    
+    /// Wrapper depth of the document; the initial document is at depth 0
+    pub depth: usize,
+    
+    /// Time taken to fetch the document, in milliseconds
+    pub latency_ms: Option<f64>,
+    
+    /// HTTP status of the response, if it was fetched over HTTP
+    pub http_status: Option<u16>,
+    
+    /// Size of the document in bytes
+    pub bytes: Option<usize>,
+    
+    /// Ids of the ads parsed from the document
+    pub ad_ids: Vec<String>,
+    
+    /// The first failure recorded for this document, if any
+    pub error: Option<ChainError>,
+    
+    /// Hops followed from the wrappers in this document
+    pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    /// An empty node for a document at the given depth
+    pub fn new(uri: Option<String>, depth: usize) -> Self {
+        TraceNode {
+            uri,
+            depth,
+            latency_ms: None,
+            http_status: None,
+            bytes: None,
+            ad_ids: Vec::new(),
+            error: None,
+            children: Vec::new(),
+        }
+    }
+}
@@ -1,14 +1,138 @@
-use crate::error::{Result, VastError};
-use crate::models::Vast;
-use crate::parser;
+use crate::error::{Result, VastError, VastErrorCode};
+use crate::fetcher::{AsyncVastFetcher, DefaultFetcher, FetchRequest, FetchResponse, VastFetcher, DEFAULT_FETCH_TIMEOUT};
+use crate::macros::MacroContext;
+use crate::models::{Ad, UnknownXml, Vast, Wrapper};
+use crate::parser::{self, ParseOptions};
+use crate::stitcher::UrlDedup;
+use crate::trace::{ChainTrace, TraceNode};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashSet, VecDeque};
-use std::fs;
-use std::path::Path;
-use rand::{thread_rng, Rng};
-use rand::distributions::Alphanumeric;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
 
-/// Maximum depth of VAST wrapper chain to follow
-const MAX_WRAPPER_DEPTH: usize = 10;
+/// Default maximum depth of VAST wrapper chain to follow
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+
+/// Limits applied while following a wrapper chain
+/// 
+/// ```
+/// use std::time::Duration;
+/// use vast_parser::unwrap::UnwrapOptions;
+/// 
+/// let options = UnwrapOptions::new()
+///     .with_max_depth(5)
+///     .with_hop_timeout(Duration::from_millis(300))
+///     .with_deadline(Duration::from_millis(800));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwrapOptions {
+    /// Maximum depth of the wrapper chain to follow: the number of VASTAdTagURIs
+    /// fetched on the way from the root document to an InLine ad
+    pub max_depth: usize,
+    
+    /// Time allowed for each VASTAdTagURI fetch
+    pub hop_timeout: Duration,
+    
+    /// Time budget for the whole chain, if limited
+    pub deadline: Option<Duration>,
+    
+    /// Largest response body to accept from a VASTAdTagURI, in bytes
+    pub max_response_bytes: Option<usize>,
+    
+    /// Maximum number of ads taken from each document
+    pub max_ads_per_document: Option<usize>,
+    
+    /// Maximum number of VASTAdTagURIs fetched in total
+    pub max_total_fetches: Option<usize>,
+    
+    /// Whether to keep XML the parser doesn't model, see `ParseOptions::lossless`
+    pub lossless: bool,
+    
+    /// How the stitcher deduplicates the tracking URLs of each ad
+    pub dedup: UrlDedup,
+    
+    /// Macro values substituted into each VASTAdTagURI before it is fetched, if any
+    pub macros: Option<MacroContext>,
+}
+
+impl Default for UnwrapOptions {
+    fn default() -> Self {
+        UnwrapOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            hop_timeout: DEFAULT_FETCH_TIMEOUT,
+            deadline: None,
+            max_response_bytes: None,
+            max_ads_per_document: None,
+            max_total_fetches: None,
+            lossless: false,
+            dedup: UrlDedup::None,
+            macros: None,
+        }
+    }
+}
+
+impl UnwrapOptions {
+    /// Options with the default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Set the maximum depth of the wrapper chain to follow
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+    
+    /// Set the time allowed for each VASTAdTagURI fetch
+    pub fn with_hop_timeout(mut self, hop_timeout: Duration) -> Self {
+        self.hop_timeout = hop_timeout;
+        self
+    }
+    
+    /// Set the time budget for the whole chain
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    /// Set the largest response body to accept from a VASTAdTagURI, in bytes
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+    
+    /// Set the maximum number of ads taken from each document
+    pub fn with_max_ads_per_document(mut self, max_ads_per_document: usize) -> Self {
+        self.max_ads_per_document = Some(max_ads_per_document);
+        self
+    }
+    
+    /// Set the maximum number of VASTAdTagURIs fetched in total
+    pub fn with_max_total_fetches(mut self, max_total_fetches: usize) -> Self {
+        self.max_total_fetches = Some(max_total_fetches);
+        self
+    }
+    
+    /// Set whether to keep XML the parser doesn't model in every document of the chain
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+    
+    /// Set how the stitcher deduplicates the tracking URLs of each ad
+    pub fn with_dedup(mut self, dedup: UrlDedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+    
+    /// Expand macros in each VASTAdTagURI with the given values before fetching it
+    pub fn with_macros(mut self, macros: MacroContext) -> Self {
+        self.macros = Some(macros);
+        self
+    }
+}
 
 /// Unwrap a VAST document by following wrappers until an InLine ad is found
 /// 
@@ -16,11 +140,17 @@ const MAX_WRAPPER_DEPTH: usize = 10;
 /// 1. Parse the initial VAST XML
 /// 2. If there's an InLine ad, return it
 /// 3. If there's a Wrapper ad, fetch the VASTAdTagURI and repeat the process
-/// 4. Continue until an InLine ad is found or MAX_WRAPPER_DEPTH is reached
+/// 4. Continue until an InLine ad is found or the maximum depth is reached
+/// 
+/// The `followAdditionalWrappers`, `allowMultipleAds` and `fallbackOnNoAd`
+/// attributes of each wrapper are enforced on the documents it requests.
+/// Stand-alone (buffet) ads are only served in place of a wrapper with
+/// `fallbackOnNoAd="true"` that resolved to no ads, or as the single ad of
+/// a document without a pod.
 /// 
 /// If no InLine ad is found, returns the last valid VAST in the chain
 pub fn unwrap_vast(xml_content: &str) -> Result<Vast> {
-    unwrap_vast_with_depth(xml_content, 0, &mut HashSet::new())
+    unwrap_vast_with_options(xml_content, &UnwrapOptions::default())
 }
 
 /// Async version of unwrap_vast
@@ -29,325 +159,1025 @@ pub fn unwrap_vast(xml_content: &str) -> Result<Vast> {
 /// 1. Parse the initial VAST XML
 /// 2. If there's an InLine ad, return it
 /// 3. If there's a Wrapper ad, fetch the VASTAdTagURI and repeat the process
-/// 4. Continue until an InLine ad is found or MAX_WRAPPER_DEPTH is reached
+/// 4. Continue until an InLine ad is found or the maximum depth is reached
 /// 
 /// If no InLine ad is found, returns the last valid VAST in the chain
 pub async fn unwrap_vast_async(xml_content: &str) -> Result<Vast> {
-    // Use an iterative approach instead of recursion to avoid issues with async recursion
-    let mut result_ads = Vec::new();
-    let mut visited_urls = HashSet::new();
-    let mut last_valid_vast = None;
-    
-    // Queue of (XML content, depth) pairs for breadth-first traversal
-    let mut queue = VecDeque::new();
-    queue.push_back((xml_content.to_string(), 0));
-    
-    while let Some((current_xml, depth)) = queue.pop_front() {
-        // If we've reached the maximum depth, skip this item
-        if depth >= MAX_WRAPPER_DEPTH {
-            println!("Maximum wrapper depth exceeded");
-            continue;
+    unwrap_vast_async_with_options(xml_content, &UnwrapOptions::default()).await
+}
+
+/// Unwrap a VAST document like `unwrap_vast`, within the limits of the given options
+pub fn unwrap_vast_with_options(xml_content: &str, options: &UnwrapOptions) -> Result<Vast> {
+    unwrap_vast_with_fetcher(xml_content, &DefaultFetcher, options)
+}
+
+/// Async version of unwrap_vast_with_options
+pub async fn unwrap_vast_async_with_options(xml_content: &str, options: &UnwrapOptions) -> Result<Vast> {
+    unwrap_vast_async_with_fetcher(xml_content, &DefaultFetcher, options).await
+}
+
+/// Unwrap a VAST document like `unwrap_vast_with_options`, resolving VASTAdTagURIs with the given fetcher
+pub fn unwrap_vast_with_fetcher(xml_content: &str, fetcher: &dyn VastFetcher, options: &UnwrapOptions) -> Result<Vast> {
+    unwrap_vast_with_errors(xml_content, fetcher, options).map(|report| report.vast)
+}
+
+/// Async version of unwrap_vast_with_fetcher
+pub async fn unwrap_vast_async_with_fetcher(
+    xml_content: &str,
+    fetcher: &dyn AsyncVastFetcher,
+    options: &UnwrapOptions,
+) -> Result<Vast> {
+    unwrap_vast_with_errors_async(xml_content, fetcher, options).await.map(|report| report.vast)
+}
+
+/// Unwrap a VAST document like `unwrap_vast_with_fetcher`, also reporting every failure in the chain
+pub fn unwrap_vast_with_errors(xml_content: &str, fetcher: &dyn VastFetcher, options: &UnwrapOptions) -> Result<UnwrapReport> {
+    let mut state = UnwrapState::new(options);
+    let ads = resolve_document(xml_content, None, 0, WrapperRules::ROOT, &[], fetcher, &mut state);
+    Ok(finish_unwrap(ads, state))
+}
+
+/// Async version of unwrap_vast_with_errors
+/// 
+/// Each fetch is also cancelled once its timeout elapses, even if the fetcher doesn't honor it.
+pub async fn unwrap_vast_with_errors_async(
+    xml_content: &str,
+    fetcher: &dyn AsyncVastFetcher,
+    options: &UnwrapOptions,
+) -> Result<UnwrapReport> {
+    let mut state = UnwrapState::new(options);
+    let ads = resolve_document_async(xml_content.to_string(), None, 0, WrapperRules::ROOT, Vec::new(), fetcher, &mut state).await;
+    Ok(finish_unwrap(ads, state))
+}
+
+/// Unwrap a VAST document like `unwrap_vast_with_fetcher`, also returning a trace of every hop in the chain
+pub fn unwrap_vast_with_trace(
+    xml_content: &str,
+    fetcher: &dyn VastFetcher,
+    options: &UnwrapOptions,
+) -> Result<(Vast, ChainTrace)> {
+    unwrap_vast_with_errors(xml_content, fetcher, options).map(|report| (report.vast, report.trace))
+}
+
+/// Async version of unwrap_vast_with_trace
+pub async fn unwrap_vast_with_trace_async(
+    xml_content: &str,
+    fetcher: &dyn AsyncVastFetcher,
+    options: &UnwrapOptions,
+) -> Result<(Vast, ChainTrace)> {
+    unwrap_vast_with_errors_async(xml_content, fetcher, options).await.map(|report| (report.vast, report.trace))
+}
+
+/// The result of unwrapping a VAST document, including any failures along the chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnwrapReport {
+    /// The unwrapped VAST document
+    pub vast: Vast,
+    
+    /// Failures encountered while following the wrapper chain
+    pub errors: Vec<ChainError>,
+    
+    /// The wrappers whose VASTAdTagURI was fetched, outermost first
+    pub wrappers: Vec<Wrapper>,
+    
+    /// The wrappers leading to each ad of `vast`, outermost first, in the same order as `vast.ads`
+    pub ad_wrappers: Vec<Vec<Wrapper>>,
+    
+    /// The pod position of each ad of `vast`, in the same order as `vast.ads`
+    /// 
+    /// A position holds the sequence numbers of the wrapper ads leading to the ad and of
+    /// the ad itself, outermost first, so a wrapper that resolves into a pod keeps its slot.
+    /// Stand-alone ads reached only through stand-alone wrappers have an empty position.
+    pub pod_positions: Vec<Vec<u32>>,
+    
+    /// Every hop of the wrapper chain, with timings and failures
+    pub trace: ChainTrace,
+}
+
+impl UnwrapReport {
+    /// The error pixel URLs to fire for every failure in the chain, with macros substituted
+    pub fn error_pixel_urls(&self) -> Vec<String> {
+        self.errors.iter().flat_map(ChainError::error_pixel_urls).collect()
+    }
+}
+
+/// A failure encountered while following a wrapper chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainError {
+    /// The IAB error code describing the failure
+    pub code: VastErrorCode,
+    
+    /// The VAST URI being resolved when the failure happened, if any
+    pub uri: Option<String>,
+    
+    /// A human-readable description of the failure
+    pub message: String,
+    
+    /// Error URLs of the wrappers leading to the failure, outermost first
+    pub error_urls: Vec<String>,
+}
+
+impl ChainError {
+    /// The Error URLs of the chain with `[ERRORCODE]`, `[CACHEBUSTING]` and `[TIMESTAMP]` substituted
+    pub fn error_pixel_urls(&self) -> Vec<String> {
+        self.error_urls.iter()
+            .map(|url| MacroContext::new().with_error_code(self.code).expand(url))
+            .collect()
+    }
+}
+
+/// Restrictions a wrapper places on the VAST document it requests
+#[derive(Debug, Clone, Copy)]
+struct WrapperRules {
+    /// Whether wrapper ads in the requested document may be followed
+    follow_additional_wrappers: bool,
+    
+    /// Whether pods and multiple stand-alone ads are allowed in the requested document
+    allow_multiple_ads: bool,
+}
+
+impl WrapperRules {
+    /// Rules for the initial document, which has no parent wrapper
+    const ROOT: WrapperRules = WrapperRules {
+        follow_additional_wrappers: true,
+        allow_multiple_ads: true,
+    };
+    
+    /// Rules imposed by a wrapper; missing attributes don't restrict anything
+    fn from_wrapper(wrapper: &Wrapper) -> Self {
+        WrapperRules {
+            follow_additional_wrappers: wrapper.follow_additional_wrappers.unwrap_or(true),
+            allow_multiple_ads: wrapper.allow_multiple_ads.unwrap_or(true),
         }
-        
-        // Parse the VAST XML
-        let vast = match parser::parse_vast(&current_xml) {
-            Ok(vast) => vast,
-            Err(e) => {
-                println!("Failed to parse VAST XML: {:?}", e);
-                continue;
-            }
+    }
+    
+    /// Drop the pods and extra ads of a document that these rules don't allow
+    fn select_ads(&self, ads: Vec<Ad>) -> Vec<Ad> {
+        if self.allow_multiple_ads {
+            ads
+        } else {
+            // Only the first stand-alone ad is allowed; pods are discarded
+            ads.into_iter().filter(|ad| ad.sequence.is_none()).take(1).collect()
+        }
+    }
+}
+
+/// The order in which the ads of a document are resolved
+/// 
+/// Pod ads are resolved in document order and stand-alone (buffet) ads are held back as
+/// fallbacks. When a wrapper with `fallbackOnNoAd="true"` resolves to no ads, the next
+/// buffet ad takes its place; otherwise its slot stays empty. A document without a pod
+/// serves its first stand-alone ad, with the others as its fallbacks.
+struct AdPlan {
+    /// Ads always resolved, in document order
+    primary: VecDeque<Ad>,
+    
+    /// Stand-alone ads only resolved as fallbacks, in document order
+    buffet: VecDeque<Ad>,
+    
+    /// Whether the ad handed out last may be replaced by a buffet ad if it resolves to no ads
+    fallback_on_no_ad: bool,
+    
+    /// Whether the ad handed out last resolved to no ads
+    last_empty: bool,
+    
+    /// The pod position of the ads the ad handed out last resolves to
+    position: Vec<u32>,
+}
+
+impl AdPlan {
+    /// Plan the resolution of the ads of a document
+    fn new(ads: Vec<Ad>) -> Self {
+        let (pod, mut buffet): (VecDeque<Ad>, VecDeque<Ad>) = ads.into_iter()
+            .partition(|ad| ad.sequence.is_some());
+        let primary = if pod.is_empty() {
+            buffet.pop_front().into_iter().collect()
+        } else {
+            pod
         };
         
-        // Store this as the last valid VAST we've seen
-        last_valid_vast = Some(vast.clone());
+        AdPlan {
+            primary,
+            buffet,
+            fallback_on_no_ad: false,
+            last_empty: false,
+            position: Vec::new(),
+        }
+    }
+    
+    /// The next ad to resolve
+    fn next_ad(&mut self) -> Option<Ad> {
+        if self.last_empty
+            && self.fallback_on_no_ad
+            && let Some(ad) = self.buffet.pop_front()
+        {
+            // The fallback takes the pod position of the ad it replaces
+            debug!(ad_id = ad.id.as_deref(); "Wrapper returned no ads, falling back to a buffet ad");
+            self.fallback_on_no_ad = falls_back_on_no_ad(&ad);
+            return Some(ad);
+        }
         
-        // Process each ad in the VAST document
-        for ad in vast.ads {
-            // If the ad has an InLine element, include it in the result
-            if ad.inline.is_some() {
-                result_ads.push(ad);
-            }
-            // If the ad has a Wrapper element, follow the VASTAdTagURI
-            else if let Some(wrapper) = &ad.wrapper {
-                let vast_ad_tag_uri = &wrapper.vast_ad_tag_uri;
-                
-                println!("Following wrapper: {}", vast_ad_tag_uri);
-                
-                // Check for cycles (the same URL appearing more than once in the chain)
-                if visited_urls.contains(vast_ad_tag_uri) {
-                    println!("Cycle detected in wrapper chain, skipping: {}", vast_ad_tag_uri);
-                    continue; // Skip this wrapper to avoid infinite loops
-                }
-                
-                // Add this URL to the set of visited URLs
-                visited_urls.insert(vast_ad_tag_uri.clone());
-                
-                // Fetch the next VAST document asynchronously
-                match fetch_vast_content_async(vast_ad_tag_uri).await {
-                    Ok(next_xml) => {
-                        // Add to the queue for processing with incremented depth
-                        queue.push_back((next_xml, depth + 1));
-                    }
-                    Err(e) => {
-                        println!("Error fetching next VAST: {:?}", e);
-                        // If fetching fails, continue with the next ad
-                        continue;
-                    }
-                }
-            }
+        let ad = self.primary.pop_front()?;
+        self.fallback_on_no_ad = falls_back_on_no_ad(&ad);
+        self.position = ad.sequence.into_iter().collect();
+        Some(ad)
+    }
+    
+    /// Record the InLine ads the ad handed out last resolved to, returning them placed in the pod
+    fn place(&mut self, mut child_ads: Vec<ResolvedAd>) -> Vec<ResolvedAd> {
+        self.last_empty = child_ads.is_empty();
+        for child in &mut child_ads {
+            child.pod_position.splice(0..0, self.position.iter().copied());
         }
+        child_ads
     }
+}
+
+/// Whether an ad is a wrapper allowing a buffet ad in its place when it resolves to no ads
+fn falls_back_on_no_ad(ad: &Ad) -> bool {
+    ad.wrapper.as_ref().and_then(|wrapper| wrapper.fallback_on_no_ad) == Some(true)
+}
+
+/// An InLine ad found while unwrapping
+struct ResolvedAd {
+    /// The InLine ad
+    ad: Ad,
     
-    // If we found InLine ads, use them
-    if !result_ads.is_empty() {
-        // Get the version from the initial VAST or default to 4.0
-        let version = match parser::parse_vast(xml_content) {
-            Ok(vast) => vast.version,
-            Err(_) => "4.0".to_string(), // Default to latest version
+    /// The wrappers that led to the ad, outermost first
+    wrappers: Vec<Wrapper>,
+    
+    /// The pod position of the ad, see `UnwrapReport::pod_positions`
+    pod_position: Vec<u32>,
+}
+
+impl ResolvedAd {
+    /// An InLine ad found in the document being resolved, before it is placed in the pod
+    fn new(ad: Ad) -> Self {
+        ResolvedAd { ad, wrappers: Vec::new(), pod_position: Vec::new() }
+    }
+}
+
+/// State shared across a single unwrap run
+struct UnwrapState<'o> {
+    /// The limits of this run
+    options: &'o UnwrapOptions,
+    
+    /// When the run started, for the chain deadline
+    started: Instant,
+    
+    /// Number of VASTAdTagURIs fetched so far
+    fetches: usize,
+    
+    /// VASTAdTagURIs already requested, for cycle detection
+    visited_urls: HashSet<String>,
+    
+    /// The version of the initial VAST document
+    root_version: Option<String>,
+    
+    /// Unrecognized XML on the initial VAST element, kept in lossless mode
+    root_unknown: UnknownXml,
+    
+    /// The last VAST document that was parsed successfully
+    last_valid_vast: Option<Vast>,
+    
+    /// Failures encountered so far
+    errors: Vec<ChainError>,
+    
+    /// Wrappers fetched so far, outermost first
+    wrappers: Vec<Wrapper>,
+    
+    /// Trace nodes of the documents being resolved, innermost last
+    trace_stack: Vec<TraceNode>,
+}
+
+impl<'o> UnwrapState<'o> {
+    /// Start an unwrap run with the given limits
+    fn new(options: &'o UnwrapOptions) -> Self {
+        UnwrapState {
+            options,
+            started: Instant::now(),
+            fetches: 0,
+            visited_urls: HashSet::new(),
+            root_version: None,
+            root_unknown: UnknownXml::default(),
+            last_valid_vast: None,
+            errors: Vec::new(),
+            wrappers: Vec::new(),
+            trace_stack: vec![TraceNode::new(None, 0)],
+        }
+    }
+    
+    /// The trace node of the document currently being resolved
+    fn current_node(&mut self) -> &mut TraceNode {
+        self.trace_stack.last_mut().expect("trace stack always holds the root node")
+    }
+    
+    /// Start tracing a hop to the given VASTAdTagURI
+    fn begin_hop(&mut self, uri: &str, depth: usize) {
+        self.trace_stack.push(TraceNode::new(Some(uri.to_string()), depth));
+    }
+    
+    /// Finish tracing the current hop, attaching it to its parent document
+    fn end_hop(&mut self) {
+        if self.trace_stack.len() > 1
+            && let Some(node) = self.trace_stack.pop()
+        {
+            self.current_node().children.push(node);
+        }
+    }
+    
+    /// Record the outcome of fetching the current hop
+    fn record_fetch(&mut self, started: Instant, result: &Result<FetchResponse>) {
+        let node = self.current_node();
+        node.latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+        node.http_status = match result {
+            Ok(response) => response.http_status,
+            Err(VastError::HttpStatus(status)) => Some(*status),
+            Err(_) => None,
+        };
+    }
+    
+    /// Record a failure against the Error URLs of the chain
+    fn record_error(&mut self, code: VastErrorCode, uri: Option<&str>, message: String, error_urls: &[String]) {
+        warn!(code = code.code(), uri = uri; "{}", message);
+        let error = ChainError {
+            code,
+            uri: uri.map(str::to_string),
+            message,
+            error_urls: error_urls.to_vec(),
         };
         
-        return Ok(Vast {
-            version,
-            ads: result_ads,
-            error: None,
-        });
+        let node = self.current_node();
+        if node.error.is_none() {
+            node.error = Some(error.clone());
+        }
+        self.errors.push(error);
     }
     
-    // If no InLine ads were found but we have at least one valid VAST, return the last one
-    if let Some(last_vast) = last_valid_vast {
-        return Ok(last_vast);
+    /// Build the request for a wrapper's VASTAdTagURI, or record why it can't be fetched
+    /// 
+    /// The fetch timeout is the per-hop timeout, shortened to whatever is left of the chain deadline.
+    fn fetch_request<'u>(&mut self, uri: &'u str, error_urls: &[String]) -> Option<FetchRequest<'u>> {
+        if let Some(max_total_fetches) = self.options.max_total_fetches
+            && self.fetches >= max_total_fetches
+        {
+            self.record_error(
+                VastErrorCode::WrapperLimitReached,
+                Some(uri),
+                format!("Maximum number of fetches ({}) reached", max_total_fetches),
+                error_urls,
+            );
+            return None;
+        }
+        
+        let mut timeout = self.options.hop_timeout;
+        if let Some(deadline) = self.options.deadline {
+            let remaining = deadline.saturating_sub(self.started.elapsed());
+            if remaining.is_zero() {
+                self.record_error(
+                    VastErrorCode::WrapperTimeout,
+                    Some(uri),
+                    format!("Wrapper chain deadline of {:?} exceeded", deadline),
+                    error_urls,
+                );
+                return None;
+            }
+            timeout = timeout.min(remaining);
+        }
+        
+        self.fetches += 1;
+        Some(FetchRequest::new(uri)
+            .with_timeout(timeout)
+            .with_max_bytes(self.options.max_response_bytes))
     }
     
-    // If we got here, we didn't find any valid VAST at all
-    Ok(Vast {
-        version: "4.0".to_string(), // Default to latest version
-        ads: Vec::new(),
-        error: Some("No valid VAST documents found in the chain".to_string()),
-    })
+    /// The URI to request for a VASTAdTagURI, with macros expanded if the options ask for it
+    fn expand_macros(&self, vast_ad_tag_uri: &str) -> String {
+        match &self.options.macros {
+            Some(macros) => macros.expand(vast_ad_tag_uri),
+            None => vast_ad_tag_uri.to_string(),
+        }
+    }
+    
+    /// Drop the ads of a document that the wrapper rules or the options don't allow
+    /// 
+    /// Wrapper ads dropped because of `followAdditionalWrappers="false"` are reported as
+    /// 302 errors against the chain and their own Error URLs.
+    fn select_ads(&mut self, rules: WrapperRules, ads: Vec<Ad>, uri: Option<&str>, error_urls: &[String]) -> Vec<Ad> {
+        let mut ads = rules.select_ads(ads);
+        
+        if !rules.follow_additional_wrappers {
+            ads.retain(|ad| {
+                let Some(wrapper) = &ad.wrapper else {
+                    return true;
+                };
+                self.record_error(
+                    VastErrorCode::WrapperLimitReached,
+                    uri,
+                    format!(
+                        "Wrapper not allowed by followAdditionalWrappers=\"false\": {}",
+                        wrapper.vast_ad_tag_uri
+                    ),
+                    &chain_error_urls(error_urls, wrapper),
+                );
+                false
+            });
+        }
+        
+        if let Some(max_ads_per_document) = self.options.max_ads_per_document {
+            ads.truncate(max_ads_per_document);
+        }
+        ads
+    }
 }
 
-/// Internal implementation of unwrap_vast with depth tracking and cycle detection
-fn unwrap_vast_with_depth(xml_content: &str, depth: usize, visited_urls: &mut HashSet<String>) -> Result<Vast> {
-    // If we've reached the maximum depth, return an empty VAST
-    if depth >= MAX_WRAPPER_DEPTH {
-        println!("Maximum wrapper depth exceeded");
-        return Ok(Vast {
+/// Build the unwrap result from the resolved InLine ads
+fn finish_unwrap(resolved_ads: Vec<ResolvedAd>, mut state: UnwrapState<'_>) -> UnwrapReport {
+    let mut ads = Vec::new();
+    let mut ad_wrappers = Vec::new();
+    let mut pod_positions = Vec::new();
+    for resolved in resolved_ads {
+        ads.push(resolved.ad);
+        ad_wrappers.push(resolved.wrappers);
+        pod_positions.push(resolved.pod_position);
+    }
+    
+    let vast = if !ads.is_empty() {
+        // If we found InLine ads, use them
+        Vast {
+            // Get the version from the initial VAST or default to 4.0
+            version: state.root_version.unwrap_or_else(|| "4.0".to_string()),
+            ads,
+            error: None,
+            unknown: state.root_unknown,
+        }
+    } else if let Some(last_vast) = state.last_valid_vast {
+        // If no InLine ads were found but we have at least one valid VAST, return the last one
+        last_vast
+    } else {
+        // If we got here, we didn't find any valid VAST at all
+        Vast {
             version: "4.0".to_string(), // Default to latest version
             ads: Vec::new(),
-            error: Some("Maximum wrapper depth exceeded".to_string()),
-        });
+            error: Some("No valid VAST documents found in the chain".to_string()),
+            unknown: UnknownXml::default(),
+        }
+    };
+    
+    // Ads of a fallback document weren't reached through any wrapper
+    ad_wrappers.resize(vast.ads.len(), Vec::new());
+    if pod_positions.len() != vast.ads.len() {
+        pod_positions = vast.ads.iter().map(|ad| ad.sequence.into_iter().collect()).collect();
+    }
+    
+    let trace = ChainTrace {
+        root: state.trace_stack.swap_remove(0),
+        elapsed_ms: state.started.elapsed().as_secs_f64() * 1000.0,
+    };
+    
+    UnwrapReport {
+        vast,
+        errors: state.errors,
+        wrappers: state.wrappers,
+        ad_wrappers,
+        pod_positions,
+        trace,
     }
+}
 
+/// Parse a VAST document for unwrapping, recording it in the unwrap state
+fn parse_for_unwrap(
+    xml_content: &str,
+    depth: usize,
+    uri: Option<&str>,
+    error_urls: &[String],
+    state: &mut UnwrapState<'_>,
+) -> Option<Vast> {
+    state.current_node().bytes = Some(xml_content.len());
+    
     // Parse the VAST XML
-    let vast = match parser::parse_vast(xml_content) {
+    let parse_options = ParseOptions::new().with_lossless(state.options.lossless);
+    let vast = match parser::parse_vast_with_options(xml_content, &parse_options) {
         Ok(vast) => vast,
         Err(e) => {
-            println!("Failed to parse VAST XML: {:?}", e);
-            // If parsing fails, return an empty VAST
-            return Ok(Vast {
-                version: "4.0".to_string(),
-                ads: Vec::new(),
-                error: Some("Failed to parse VAST XML".to_string()),
-            });
+            state.record_error(e.error_code(), uri, format!("Failed to parse VAST XML: {:?}", e), error_urls);
+            return None;
         }
     };
+    
+    if depth == 0 {
+        state.root_version = Some(vast.version.clone());
+        state.root_unknown = vast.unknown.clone();
+    }
+    
+    state.current_node().ad_ids = vast.ads.iter().filter_map(|ad| ad.id.clone()).collect();
+    
+    // Store this as the last valid VAST we've seen
+    state.last_valid_vast = Some(vast.clone());
+    
+    Some(vast)
+}
 
-    // Process each ad in the VAST document
+/// Check a wrapper's VASTAdTagURI against the chain before it is fetched, returning
+/// false if the chain would get too deep or loop
+/// 
+/// `depth` is the depth of the document the wrapper points to.
+fn enter_wrapper(wrapper: &Wrapper, depth: usize, error_urls: &[String], state: &mut UnwrapState<'_>) -> bool {
+    let vast_ad_tag_uri = &wrapper.vast_ad_tag_uri;
+    
+    info!(
+        uri = vast_ad_tag_uri.as_str(),
+        depth = depth,
+        elapsed_ms = state.started.elapsed().as_millis() as u64;
+        "Following wrapper"
+    );
+    
+    // If following the wrapper goes past the maximum depth, stop following the chain
+    if depth > state.options.max_depth {
+        state.record_error(
+            VastErrorCode::WrapperLimitReached,
+            Some(vast_ad_tag_uri),
+            format!("Maximum wrapper depth ({}) exceeded", state.options.max_depth),
+            error_urls,
+        );
+        return false;
+    }
+    
+    // Check for cycles (the same URL appearing more than once in the chain)
+    if !state.visited_urls.insert(vast_ad_tag_uri.clone()) {
+        state.record_error(
+            VastErrorCode::GeneralWrapperError,
+            Some(vast_ad_tag_uri),
+            format!("Cycle detected in wrapper chain, skipping: {}", vast_ad_tag_uri),
+            error_urls,
+        );
+        return false;
+    }
+    
+    true
+}
+
+/// The Error URLs of a chain extended with those of the given wrapper
+fn chain_error_urls(error_urls: &[String], wrapper: &Wrapper) -> Vec<String> {
+    let mut chain = error_urls.to_vec();
+    chain.extend(wrapper.error.iter().cloned());
+    chain
+}
+
+/// Record a 303 error when a wrapper resolved to no ads without a more specific failure
+fn check_wrapper_result(
+    wrapper: &Wrapper,
+    child_ads: &[ResolvedAd],
+    errors_before: usize,
+    error_urls: &[String],
+    state: &mut UnwrapState<'_>,
+) {
+    if child_ads.is_empty() && state.errors.len() == errors_before {
+        state.record_error(
+            VastErrorCode::NoAdsAfterWrapper,
+            Some(&wrapper.vast_ad_tag_uri),
+            format!("No ads returned by wrapper: {}", wrapper.vast_ad_tag_uri),
+            error_urls,
+        );
+    }
+}
+
+/// Add a wrapper to the front of the chains of the ads it resolved to
+fn prepend_wrapper(wrapper: &Wrapper, mut child_ads: Vec<ResolvedAd>) -> Vec<ResolvedAd> {
+    for child in &mut child_ads {
+        child.wrappers.insert(0, wrapper.clone());
+    }
+    child_ads
+}
+
+/// Resolve the ads of a VAST document into InLine ads, following wrappers
+/// 
+/// `error_urls` holds the Error URLs of the wrappers that led to this document.
+fn resolve_document(
+    xml_content: &str,
+    uri: Option<&str>,
+    depth: usize,
+    rules: WrapperRules,
+    error_urls: &[String],
+    fetcher: &dyn VastFetcher,
+    state: &mut UnwrapState<'_>,
+) -> Vec<ResolvedAd> {
+    let Some(vast) = parse_for_unwrap(xml_content, depth, uri, error_urls, state) else {
+        return Vec::new();
+    };
+    
     let mut result_ads = Vec::new();
-    let mut found_inline = false;
+    let mut plan = AdPlan::new(state.select_ads(rules, vast.ads, uri, error_urls));
     
-    for ad in vast.ads.clone() {
+    while let Some(ad) = plan.next_ad() {
+        let mut child_ads = Vec::new();
+        
         // If the ad has an InLine element, include it in the result
         if ad.inline.is_some() {
-            result_ads.push(ad);
-            found_inline = true;
+            child_ads.push(ResolvedAd::new(ad));
         }
         // If the ad has a Wrapper element, follow the VASTAdTagURI
         else if let Some(wrapper) = &ad.wrapper {
-            let vast_ad_tag_uri = &wrapper.vast_ad_tag_uri;
-            
-            println!("Following wrapper: {}", vast_ad_tag_uri);
-            
-            // Check for cycles (the same URL appearing more than once in the chain)
-            if visited_urls.contains(vast_ad_tag_uri) {
-                println!("Cycle detected in wrapper chain, skipping: {}", vast_ad_tag_uri);
-                continue; // Skip this wrapper to avoid infinite loops
-            }
-            
-            // Add this URL to the set of visited URLs
-            visited_urls.insert(vast_ad_tag_uri.clone());
-            
-            // Fetch the next VAST document
-            match fetch_vast_content(vast_ad_tag_uri) {
-                Ok(next_xml) => {
-                    // Recursively unwrap the next VAST document
-                    match unwrap_vast_with_depth(&next_xml, depth + 1, visited_urls) {
-                        Ok(next_vast) => {
-                            // If the next level has InLine ads, add them to our result
-                            if !next_vast.ads.is_empty() {
-                                for next_ad in next_vast.ads {
-                                    result_ads.push(next_ad);
-                                    found_inline = true;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            println!("Error unwrapping next level: {:?}", e);
-                            // If unwrapping fails, continue with the next ad
-                            continue;
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!("Error fetching next VAST: {:?}", e);
-                    // If fetching fails, continue with the next ad
-                    continue;
-                }
-            }
+            state.begin_hop(&wrapper.vast_ad_tag_uri, depth + 1);
+            child_ads = follow_wrapper(wrapper, depth, error_urls, fetcher, state);
+            state.end_hop();
         }
+        
+        result_ads.extend(plan.place(child_ads));
     }
     
-    // If we found InLine ads, return them
-    if found_inline {
-        return Ok(Vast {
-            version: vast.version,
-            ads: result_ads,
-            error: vast.error,
-        });
+    result_ads
+}
+
+/// Fetch and resolve the document a wrapper points to
+fn follow_wrapper(
+    wrapper: &Wrapper,
+    depth: usize,
+    error_urls: &[String],
+    fetcher: &dyn VastFetcher,
+    state: &mut UnwrapState<'_>,
+) -> Vec<ResolvedAd> {
+    let error_urls = chain_error_urls(error_urls, wrapper);
+    if !enter_wrapper(wrapper, depth + 1, &error_urls, state) {
+        return Vec::new();
     }
     
-    // If no InLine ads were found, return the current VAST document
-    Ok(vast)
+    let errors_before = state.errors.len();
+    let vast_ad_tag_uri = &wrapper.vast_ad_tag_uri;
+    
+    let request_uri = state.expand_macros(vast_ad_tag_uri);
+    let Some(request) = state.fetch_request(&request_uri, &error_urls) else {
+        return Vec::new();
+    };
+    
+    // Fetch the next VAST document
+    let fetch_started = Instant::now();
+    let fetched = fetcher.fetch(request);
+    state.record_fetch(fetch_started, &fetched);
+    
+    let child_ads = match fetched {
+        Ok(response) => {
+            state.wrappers.push(wrapper.clone());
+            let rules = WrapperRules::from_wrapper(wrapper);
+            resolve_document(&response.body, Some(vast_ad_tag_uri), depth + 1, rules, &error_urls, fetcher, state)
+        }
+        Err(e) => {
+            state.record_error(
+                fetch_error_code(&e),
+                Some(vast_ad_tag_uri),
+                format!("Error fetching next VAST: {:?}", e),
+                &error_urls,
+            );
+            Vec::new()
+        }
+    };
+    
+    check_wrapper_result(wrapper, &child_ads, errors_before, &error_urls, state);
+    prepend_wrapper(wrapper, child_ads)
 }
 
-/// Fetch VAST content from a URL or file path
-pub fn fetch_vast_content(url_or_path: &str) -> Result<String> {
-    // Check if it's a file URL
-    if url_or_path.starts_with("file://") {
-        let path = url_or_path.trim_start_matches("file://");
+/// Async version of resolve_document
+/// 
+/// Returns a boxed future so the document/wrapper recursion has a finite size
+fn resolve_document_async<'a, 'o: 'a>(
+    xml_content: String,
+    uri: Option<&'a str>,
+    depth: usize,
+    rules: WrapperRules,
+    error_urls: Vec<String>,
+    fetcher: &'a dyn AsyncVastFetcher,
+    state: &'a mut UnwrapState<'o>,
+) -> Pin<Box<dyn Future<Output = Vec<ResolvedAd>> + Send + 'a>> {
+    Box::pin(async move {
+        let Some(vast) = parse_for_unwrap(&xml_content, depth, uri, &error_urls, state) else {
+            return Vec::new();
+        };
         
-        #[cfg(target_os = "windows")]
-        let path = path.trim_start_matches("/");
+        let mut result_ads = Vec::new();
+        let mut plan = AdPlan::new(state.select_ads(rules, vast.ads, uri, &error_urls));
         
-        // If the path doesn't exist directly, try to resolve it relative to the current directory
-        let path_buf = std::path::PathBuf::from(path);
-        let file_path = if path_buf.exists() {
-            path_buf
-        } else {
-            // Check if we need to look in the samples directory
-            let samples_path = std::path::PathBuf::from("samples").join(path);
-            if samples_path.exists() {
-                samples_path
-            } else {
-                // Try current directory
-                std::path::PathBuf::from(path)
+        while let Some(ad) = plan.next_ad() {
+            let mut child_ads = Vec::new();
+            
+            // If the ad has an InLine element, include it in the result
+            if ad.inline.is_some() {
+                child_ads.push(ResolvedAd::new(ad));
             }
-        };
+            // If the ad has a Wrapper element, follow the VASTAdTagURI
+            else if let Some(wrapper) = &ad.wrapper {
+                state.begin_hop(&wrapper.vast_ad_tag_uri, depth + 1);
+                child_ads = follow_wrapper_async(wrapper, depth, &error_urls, fetcher, state).await;
+                state.end_hop();
+            }
+            
+            result_ads.extend(plan.place(child_ads));
+        }
         
-        println!("Reading from file: {}", file_path.display());
-        return fs::read_to_string(file_path)
-            .map_err(|e| VastError::IoError(e));
+        result_ads
+    })
+}
+
+/// Async version of follow_wrapper
+async fn follow_wrapper_async(
+    wrapper: &Wrapper,
+    depth: usize,
+    error_urls: &[String],
+    fetcher: &dyn AsyncVastFetcher,
+    state: &mut UnwrapState<'_>,
+) -> Vec<ResolvedAd> {
+    let error_urls = chain_error_urls(error_urls, wrapper);
+    if !enter_wrapper(wrapper, depth + 1, &error_urls, state) {
+        return Vec::new();
     }
     
-    // Check if it's a plain file path
-    if Path::new(url_or_path).exists() {
-        println!("Reading from local file: {}", url_or_path);
-        return fs::read_to_string(url_or_path)
-            .map_err(|e| VastError::IoError(e));
-    }
+    let errors_before = state.errors.len();
+    let vast_ad_tag_uri = &wrapper.vast_ad_tag_uri;
+    
+    let request_uri = state.expand_macros(vast_ad_tag_uri);
+    let Some(request) = state.fetch_request(&request_uri, &error_urls) else {
+        return Vec::new();
+    };
     
-    // Assume it's a web URL - use a runtime to run the async function
-    let rt = tokio::runtime::Runtime::new()
-        .map_err(|e| VastError::Other(format!("Failed to create Tokio runtime: {}", e)))?;
+    // Fetch the next VAST document asynchronously, giving up once the timeout elapses
+    let fetch_started = Instant::now();
+    let fetched = tokio::time::timeout(request.timeout, fetcher.fetch_async(request))
+        .await
+        .unwrap_or_else(|_| Err(VastError::Timeout(
+            format!("No response from {} within {:?}", vast_ad_tag_uri, request.timeout)
+        )));
+    state.record_fetch(fetch_started, &fetched);
     
-    rt.block_on(fetch_vast_from_url(url_or_path))
+    let child_ads = match fetched {
+        Ok(response) => {
+            state.wrappers.push(wrapper.clone());
+            let rules = WrapperRules::from_wrapper(wrapper);
+            resolve_document_async(response.body, Some(vast_ad_tag_uri), depth + 1, rules, error_urls.clone(), fetcher, state).await
+        }
+        Err(e) => {
+            state.record_error(
+                fetch_error_code(&e),
+                Some(vast_ad_tag_uri),
+                format!("Error fetching next VAST: {:?}", e),
+                &error_urls,
+            );
+            Vec::new()
+        }
+    };
+    
+    check_wrapper_result(wrapper, &child_ads, errors_before, &error_urls, state);
+    prepend_wrapper(wrapper, child_ads)
+}
+
+/// The error code to report when a VASTAdTagURI couldn't be fetched
+fn fetch_error_code(error: &VastError) -> VastErrorCode {
+    match error {
+        VastError::LimitExceeded(_) => VastErrorCode::GeneralWrapperError,
+        _ => VastErrorCode::WrapperTimeout,
+    }
+}
+
+/// Fetch VAST content from a URL or file path using the default fetcher
+pub fn fetch_vast_content(url_or_path: &str) -> Result<String> {
+    DefaultFetcher.fetch(FetchRequest::new(url_or_path)).map(|response| response.body)
 }
 
 /// Async version of fetch_vast_content to fetch VAST content from a URL or file path
 pub async fn fetch_vast_content_async(url_or_path: &str) -> Result<String> {
-    // Check if it's a file URL
-    if url_or_path.starts_with("file://") {
-        let path = url_or_path.trim_start_matches("file://");
+    DefaultFetcher.fetch_async(FetchRequest::new(url_or_path)).await.map(|response| response.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::MapFetcher;
+    
+    /// A VAST document holding the given ads
+    fn vast(ads: &[String]) -> String {
+        format!(r#"<VAST version="4.1">{}</VAST>"#, ads.concat())
+    }
+    
+    /// An InLine ad, in a pod if `sequence` is set
+    fn inline_ad(id: &str, sequence: Option<u32>) -> String {
+        let sequence = sequence.map(|sequence| format!(r#" sequence="{}""#, sequence)).unwrap_or_default();
+        format!(
+            r#"<Ad id="{}"{}><InLine><AdSystem>test</AdSystem><AdTitle>{}</AdTitle><Impression>https://example.com/{}</Impression></InLine></Ad>"#,
+            id, sequence, id, id
+        )
+    }
+    
+    /// A Wrapper ad pointing at `uri`, with the given Wrapper attributes
+    fn wrapper_ad(id: &str, sequence: Option<u32>, uri: &str, attributes: &str) -> String {
+        let sequence = sequence.map(|sequence| format!(r#" sequence="{}""#, sequence)).unwrap_or_default();
+        format!(
+            r#"<Ad id="{}"{}><Wrapper {}><AdSystem>test</AdSystem><VASTAdTagURI>{}</VASTAdTagURI><Error>https://example.com/error/{}?code=[ERRORCODE]</Error></Wrapper></Ad>"#,
+            id, sequence, attributes, uri, id
+        )
+    }
+    
+    /// The ids of the unwrapped ads
+    fn ad_ids(report: &UnwrapReport) -> Vec<&str> {
+        report.vast.ads.iter().map(|ad| ad.id.as_deref().unwrap_or_default()).collect()
+    }
+    
+    /// The error codes of the unwrap failures
+    fn error_codes(report: &UnwrapReport) -> Vec<VastErrorCode> {
+        report.errors.iter().map(|error| error.code).collect()
+    }
+    
+    /// A pod of a wrapper and an InLine ad, followed by two buffet ads
+    fn pod_with_buffet(fallback_on_no_ad: &str) -> String {
+        vast(&[
+            wrapper_ad("pod-wrapper", Some(1), "https://example.com/child", fallback_on_no_ad),
+            inline_ad("pod-inline", Some(2)),
+            inline_ad("buffet-1", None),
+            inline_ad("buffet-2", None),
+        ])
+    }
+    
+    #[test]
+    fn filled_pod_does_not_serve_buffet_ads() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/child", vast(&[inline_ad("child", None)]));
+        let root = pod_with_buffet(r#"fallbackOnNoAd="true""#);
         
-        #[cfg(target_os = "windows")]
-        let path = path.trim_start_matches("/");
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new()).unwrap();
         
-        // If the path doesn't exist directly, try to resolve it relative to the current directory
-        let path_buf = std::path::PathBuf::from(path);
-        let file_path = if path_buf.exists() {
-            path_buf
-        } else {
-            // Check if we need to look in the samples directory
-            let samples_path = std::path::PathBuf::from("samples").join(path);
-            if samples_path.exists() {
-                samples_path
-            } else {
-                // Try current directory
-                std::path::PathBuf::from(path)
-            }
-        };
+        assert_eq!(ad_ids(&report), ["child", "pod-inline"]);
+        assert_eq!(report.pod_positions, [vec![1], vec![2]]);
+        assert!(report.errors.is_empty());
+    }
+    
+    #[test]
+    fn empty_wrapper_falls_back_to_next_buffet_ad() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/child", vast(&[]));
+        let root = pod_with_buffet(r#"fallbackOnNoAd="true""#);
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new()).unwrap();
         
-        println!("Reading from file: {}", file_path.display());
-        return tokio::fs::read_to_string(file_path)
-            .await
-            .map_err(|e| VastError::IoError(e));
+        // The first buffet ad takes the slot of the empty wrapper
+        assert_eq!(ad_ids(&report), ["buffet-1", "pod-inline"]);
+        assert_eq!(report.pod_positions, [vec![1], vec![2]]);
+        assert_eq!(error_codes(&report), [VastErrorCode::NoAdsAfterWrapper]);
     }
     
-    // Check if it's a plain file path
-    if Path::new(url_or_path).exists() {
-        println!("Reading from local file: {}", url_or_path);
-        return tokio::fs::read_to_string(url_or_path)
-            .await
-            .map_err(|e| VastError::IoError(e));
+    #[test]
+    fn empty_wrapper_without_fallback_leaves_slot_empty() {
+        for attributes in [r#"fallbackOnNoAd="false""#, ""] {
+            let fetcher = MapFetcher::new()
+                .with_document("https://example.com/child", vast(&[]));
+            let root = pod_with_buffet(attributes);
+            
+            let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new()).unwrap();
+            
+            assert_eq!(ad_ids(&report), ["pod-inline"]);
+            assert_eq!(error_codes(&report), [VastErrorCode::NoAdsAfterWrapper]);
+            assert_eq!(report.error_pixel_urls(), ["https://example.com/error/pod-wrapper?code=303"]);
+        }
     }
     
-    // Directly call the async function without a runtime
-    fetch_vast_from_url(url_or_path).await
-}
-
-/// Fetch VAST XML from a URL
-async fn fetch_vast_from_url(url: &str) -> Result<String> {
-    // Generate a random request ID for tracking in logs
-    let req_id: String = thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(6)
-        .map(char::from)
-        .collect();
+    #[test]
+    fn fallback_moves_past_empty_fallback_wrappers() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/empty", vast(&[]))
+            .with_document("https://example.com/empty-again", vast(&[]));
+        let root = vast(&[
+            wrapper_ad("first", None, "https://example.com/empty", r#"fallbackOnNoAd="true""#),
+            wrapper_ad("second", None, "https://example.com/empty-again", r#"fallbackOnNoAd="true""#),
+            inline_ad("third", None),
+            inline_ad("fourth", None),
+        ]);
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new()).unwrap();
+        
+        assert_eq!(ad_ids(&report), ["third"]);
+        assert_eq!(report.pod_positions, [Vec::<u32>::new()]);
+        assert_eq!(error_codes(&report), [VastErrorCode::NoAdsAfterWrapper; 2]);
+    }
     
-    // Validate URL
-    let url = url::Url::parse(url).map_err(|e| VastError::UrlError(e))?;
+    #[test]
+    fn stand_alone_document_serves_first_ad() {
+        let root = vast(&[inline_ad("first", None), inline_ad("second", None)]);
+        
+        let report = unwrap_vast_with_errors(&root, &MapFetcher::new(), &UnwrapOptions::new()).unwrap();
+        
+        assert_eq!(ad_ids(&report), ["first"]);
+    }
     
-    println!("[{}] Fetching from URL: {}", req_id, url);
+    #[test]
+    fn disallowed_wrapper_reports_302() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/child", vast(&[wrapper_ad("nested", None, "https://example.com/grandchild", "")]))
+            .with_document("https://example.com/grandchild", vast(&[inline_ad("grandchild", None)]));
+        let root = vast(&[wrapper_ad("restricted", None, "https://example.com/child", r#"followAdditionalWrappers="false""#)]);
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new()).unwrap();
+        
+        assert!(report.vast.ads.iter().all(|ad| ad.inline.is_none()));
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperLimitReached]);
+        assert_eq!(report.errors[0].uri.as_deref(), Some("https://example.com/child"));
+        assert_eq!(report.error_pixel_urls(), [
+            "https://example.com/error/restricted?code=302",
+            "https://example.com/error/nested?code=302",
+        ]);
+    }
     
-    // Start timing
-    let start_time = std::time::Instant::now();
+    /// A chain of two wrappers ending in an InLine ad
+    fn two_wrapper_chain() -> (String, MapFetcher) {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/child", vast(&[wrapper_ad("child", None, "https://example.com/grandchild", "")]))
+            .with_document("https://example.com/grandchild", vast(&[inline_ad("grandchild", None)]));
+        (vast(&[wrapper_ad("root", None, "https://example.com/child", "")]), fetcher)
+    }
     
-    // Create a client with timeout
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(3))
-        .build()
-        .map_err(|e| VastError::Other(format!("Failed to build HTTP client: {}", e)))?;
+    #[test]
+    fn max_depth_counts_the_wrappers_followed() {
+        let (root, fetcher) = two_wrapper_chain();
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new().with_max_depth(2)).unwrap();
+        assert_eq!(ad_ids(&report), ["grandchild"]);
+        assert!(report.errors.is_empty());
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new().with_max_depth(1)).unwrap();
+        assert!(report.vast.ads.iter().all(|ad| ad.inline.is_none()));
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperLimitReached]);
+        assert_eq!(report.errors[0].uri.as_deref(), Some("https://example.com/grandchild"));
+        assert_eq!(report.error_pixel_urls(), [
+            "https://example.com/error/root?code=302",
+            "https://example.com/error/child?code=302",
+        ]);
+    }
     
-    // Fetch content from URL with timeout
-    let response = client.get(url).send().await.map_err(|e| {
-        println!("[{}] Request failed after {:?}", req_id, start_time.elapsed());
-        VastError::Other(format!("Failed to fetch URL: {}", e))
-    })?;
+    #[test]
+    fn max_depth_zero_keeps_the_root_document() {
+        let options = UnwrapOptions::new().with_max_depth(0);
+        
+        let root = vast(&[inline_ad("root", None)]);
+        let report = unwrap_vast_with_errors(&root, &MapFetcher::new(), &options).unwrap();
+        assert_eq!(ad_ids(&report), ["root"]);
+        assert!(report.errors.is_empty());
+        
+        let (root, fetcher) = two_wrapper_chain();
+        let report = unwrap_vast_with_errors(&root, &fetcher, &options).unwrap();
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperLimitReached]);
+        assert_eq!(report.errors[0].uri.as_deref(), Some("https://example.com/child"));
+    }
     
-    println!("[{}] Received response in {:?}", req_id, start_time.elapsed());
+    #[test]
+    fn max_total_fetches_stops_further_wrappers() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/first", vast(&[inline_ad("first", None)]))
+            .with_document("https://example.com/second", vast(&[inline_ad("second", None)]));
+        let root = vast(&[
+            wrapper_ad("first-wrapper", Some(1), "https://example.com/first", ""),
+            wrapper_ad("second-wrapper", Some(2), "https://example.com/second", ""),
+        ]);
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new().with_max_total_fetches(1)).unwrap();
+        
+        assert_eq!(ad_ids(&report), ["first"]);
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperLimitReached]);
+        assert_eq!(report.errors[0].uri.as_deref(), Some("https://example.com/second"));
+    }
     
-    if !response.status().is_success() {
-        return Err(VastError::Other(
-            format!("Failed to fetch URL: HTTP status {}", response.status())
-        ));
+    #[test]
+    fn exceeded_deadline_reports_301() {
+        let (root, fetcher) = two_wrapper_chain();
+        
+        let report = unwrap_vast_with_errors(&root, &fetcher, &UnwrapOptions::new().with_deadline(Duration::ZERO)).unwrap();
+        
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperTimeout]);
+        assert_eq!(report.error_pixel_urls(), ["https://example.com/error/root?code=301"]);
     }
     
-    // Get the response body as text
-    let xml_content = response.text().await.map_err(|e| {
-        VastError::Other(format!("Failed to read response body: {}", e))
-    })?;
+    #[test]
+    fn max_ads_per_document_truncates_pods() {
+        let root = vast(&[inline_ad("one", Some(1)), inline_ad("two", Some(2)), inline_ad("three", Some(3))]);
+        
+        let report = unwrap_vast_with_errors(&root, &MapFetcher::new(), &UnwrapOptions::new().with_max_ads_per_document(2)).unwrap();
+        
+        assert_eq!(ad_ids(&report), ["one", "two"]);
+    }
     
-    println!("[{}] Total request completed in {:?}", req_id, start_time.elapsed());
+    #[tokio::test]
+    async fn async_limits_match_sync() {
+        let (root, fetcher) = two_wrapper_chain();
+        
+        let report = unwrap_vast_with_errors_async(&root, &fetcher, &UnwrapOptions::new().with_max_depth(1)).await.unwrap();
+        
+        assert_eq!(error_codes(&report), [VastErrorCode::WrapperLimitReached]);
+        assert_eq!(report.errors[0].uri.as_deref(), Some("https://example.com/grandchild"));
+    }
     
-    Ok(xml_content)
-} 
\ No newline at end of file
+    #[tokio::test]
+    async fn async_fallback_matches_sync() {
+        let fetcher = MapFetcher::new()
+            .with_document("https://example.com/child", vast(&[]));
+        let root = pod_with_buffet(r#"fallbackOnNoAd="true""#);
+        
+        let report = unwrap_vast_with_errors_async(&root, &fetcher, &UnwrapOptions::new()).await.unwrap();
+        
+        assert_eq!(ad_ids(&report), ["buffet-1", "pod-inline"]);
+        assert_eq!(report.pod_positions, [vec![1], vec![2]]);
+    }
+}